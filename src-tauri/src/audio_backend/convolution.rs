@@ -0,0 +1,480 @@
+use std::f64::consts::PI;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use serde::{Deserialize, Serialize};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::event_handler::CURRENT_CONVOLUTION;
+use crate::util::lmdb::settings::{get_setting, store_setting};
+use super::transport::frames_to_secs;
+use super::opus::codecs;
+
+const CONVOLUTION_SETTINGS: &str = "convolution";
+
+// room correction filters are rarely longer than a second, this is only there to stop accidents
+const MAX_IR_SECS: f64 = 10.0;
+
+// how long swapping impulse responses crossfades between the old and the new one
+const MIN_CROSSFADE_FRAMES: usize = 4096;
+
+// taps on each side of the resampling kernel
+const RESAMPLE_HALF_TAPS: f64 = 32.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConvolutionSettings {
+    pub enabled: bool,
+    pub ir_path: Option<String>,
+    // block size of the partitions, the latency added is exactly this many frames
+    pub partition_size: usize,
+}
+
+impl Default for ConvolutionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ir_path: None,
+            partition_size: 512,
+        }
+    }
+}
+
+impl ConvolutionSettings {
+    pub fn load() -> Self {
+        match get_setting::<ConvolutionSettings>(CONVOLUTION_SETTINGS) {
+            Ok(Some(settings)) => settings,
+            Ok(None) => ConvolutionSettings::default(),
+            Err(e) => {
+                println!("error: {:?}", e);
+                ConvolutionSettings::default()
+            }
+        }
+    }
+
+    pub fn store(&self) -> Result<()> {
+        store_setting(CONVOLUTION_SETTINGS, self)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConvolutionInfo {
+    pub ir_path: String,
+    pub ir_channels: usize,
+    pub ir_sample_rate: u32,
+    pub output_sample_rate: u32,
+    // the ir was recorded at a different rate than the device runs at
+    pub resampled: bool,
+    pub ir_frames: usize,
+    pub partition_size: usize,
+    pub partitions: usize,
+    pub latency_frames: usize,
+    pub latency_ms: f64,
+}
+
+// decodes the impulse response into one Vec per channel
+fn read_impulse_response(path: &Path) -> Result<(Vec<Vec<f32>>, u32)> {
+    let file = File::open(path)?;
+
+    let mut hint = Hint::new();
+
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default()
+    )?;
+
+    let mut format = probed.format;
+
+    let track = format
+        .default_track()
+        .filter(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| anyhow::anyhow!("{} does not contain an audio track", path.display()))?;
+
+    let track_id = track.id;
+    let sample_rate = track.codec_params.sample_rate
+        .ok_or_else(|| anyhow::anyhow!("{} does not report its sample rate", path.display()))?;
+
+    let mut decoder = codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let max_frames = (MAX_IR_SECS * sample_rate as f64) as usize;
+    let mut channels: Vec<Vec<f32>> = Vec::new();
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = decoder.decode(&packet)?;
+        let spec = *decoded.spec();
+        let num_channels = spec.channels.count();
+
+        if num_channels > 2 {
+            return Err(anyhow::anyhow!("impulse response has {} channels, only mono and stereo are supported", num_channels));
+        }
+
+        channels.resize_with(num_channels, Vec::new);
+
+        let mut sample_buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        sample_buffer.copy_interleaved_ref(decoded);
+
+        for frame in sample_buffer.samples().chunks(num_channels) {
+            for (channel, sample) in frame.iter().enumerate() {
+                channels[channel].push(*sample);
+            }
+        }
+
+        if channels[0].len() > max_frames {
+            return Err(anyhow::anyhow!("impulse response is longer than {} seconds", MAX_IR_SECS));
+        }
+    }
+
+    if channels.is_empty() || channels[0].is_empty() {
+        return Err(anyhow::anyhow!("impulse response is empty"));
+    }
+
+    Ok((channels, sample_rate))
+}
+
+// band limited resampling with a blackman windowed sinc, slow but it only runs once per loaded ir
+fn resample(input: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to {
+        return input.to_vec();
+    }
+
+    let ratio = to as f64 / from as f64;
+    // when going down the kernel has to cut off below the new nyquist
+    let cutoff = ratio.min(1.0);
+    let radius = RESAMPLE_HALF_TAPS / cutoff;
+
+    let output_len = (input.len() as f64 * ratio).ceil() as usize;
+
+    (0..output_len)
+        .map(|n| {
+            let t = n as f64 / ratio;
+
+            let first = (t - radius).ceil().max(0.0) as usize;
+            let last = ((t + radius).floor() as usize).min(input.len() - 1);
+
+            let mut sum = 0.0;
+
+            for (k, sample) in input.iter().enumerate().take(last + 1).skip(first) {
+                let x = t - k as f64;
+
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (PI * cutoff * x).sin() / (PI * cutoff * x)
+                };
+
+                let window = 0.42 + 0.5 * (PI * x / radius).cos() + 0.08 * (2.0 * PI * x / radius).cos();
+
+                sum += *sample as f64 * sinc * cutoff * window;
+            }
+
+            sum as f32
+        })
+        .collect()
+}
+
+// uniformly partitioned overlap-save convolution of one stereo signal with a stereo (or mono) ir.
+// everything is allocated up front, process_frame never allocates
+pub struct Convolver {
+    info: ConvolutionInfo,
+
+    block: usize,
+    fft: Arc<dyn Fft<f32>>,
+    ifft: Arc<dyn Fft<f32>>,
+
+    // spectra of the ir partitions, [channel][partition][bin]
+    filter: Vec<Vec<Vec<Complex<f32>>>>,
+    // spectra of the last `partitions` input blocks, used as a ring, [channel][partition][bin]
+    delay_line: Vec<Vec<Vec<Complex<f32>>>>,
+    delay_pos: usize,
+
+    // the previous and the current input block back to back, [channel][2 * block]
+    input: Vec<Vec<f32>>,
+    output: Vec<Vec<f32>>,
+    pos: usize,
+
+    spectrum: Vec<Complex<f32>>,
+    accumulator: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl Convolver {
+    pub fn load(path: &Path, partition_size: usize, output_sample_rate: u32) -> Result<Self> {
+        if !partition_size.is_power_of_two() || !(64..=8192).contains(&partition_size) {
+            return Err(anyhow::anyhow!("partition size has to be a power of two between 64 and 8192"));
+        }
+
+        let (channels, ir_sample_rate) = read_impulse_response(path)?;
+
+        // an ir at a higher rate has more taps for the same response, so its gain has to follow the ratio
+        let gain = ir_sample_rate as f32 / output_sample_rate as f32;
+
+        let ir: Vec<Vec<f32>> = channels
+            .iter()
+            .map(|channel| {
+                resample(channel, ir_sample_rate, output_sample_rate)
+                    .into_iter()
+                    .map(|sample| sample * gain)
+                    .collect()
+            })
+            .collect();
+
+        let block = partition_size;
+        let fft_size = block * 2;
+        let ir_frames = ir[0].len();
+        let partitions = ir_frames.div_ceil(block);
+
+        let mut planner = FftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(fft_size);
+        let ifft = planner.plan_fft_inverse(fft_size);
+
+        let scratch_len = fft.get_inplace_scratch_len().max(ifft.get_inplace_scratch_len());
+        let mut scratch = vec![Complex::default(); scratch_len];
+
+        // a mono ir is used for both sides
+        let filter: Vec<Vec<Vec<Complex<f32>>>> = (0..2)
+            .map(|channel| {
+                let channel_ir = &ir[channel.min(ir.len() - 1)];
+
+                channel_ir
+                    .chunks(block)
+                    .map(|partition| {
+                        let mut spectrum = vec![Complex::default(); fft_size];
+
+                        for (bin, sample) in spectrum.iter_mut().zip(partition) {
+                            bin.re = *sample;
+                        }
+
+                        fft.process_with_scratch(&mut spectrum, &mut scratch);
+                        spectrum
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let latency_frames = block;
+
+        let info = ConvolutionInfo {
+            ir_path: path.to_string_lossy().to_string(),
+            ir_channels: channels.len(),
+            ir_sample_rate,
+            output_sample_rate,
+            resampled: ir_sample_rate != output_sample_rate,
+            ir_frames,
+            partition_size: block,
+            partitions,
+            latency_frames,
+            latency_ms: frames_to_secs(latency_frames, output_sample_rate) * 1000.0,
+        };
+
+        Ok(Self {
+            info,
+            block,
+            fft,
+            ifft,
+            filter,
+            delay_line: vec![vec![vec![Complex::default(); fft_size]; partitions]; 2],
+            delay_pos: 0,
+            input: vec![vec![0.0; fft_size]; 2],
+            output: vec![vec![0.0; block]; 2],
+            pos: 0,
+            spectrum: vec![Complex::default(); fft_size],
+            accumulator: vec![Complex::default(); fft_size],
+            scratch,
+        })
+    }
+
+    pub fn info(&self) -> &ConvolutionInfo {
+        &self.info
+    }
+
+    pub fn latency_frames(&self) -> usize {
+        self.info.latency_frames
+    }
+
+    // feeds one frame in and returns the frame from `block` frames ago, convolved
+    pub fn process_frame(&mut self, frame: [f32; 2]) -> [f32; 2] {
+        let block = self.block;
+
+        self.input[0][block + self.pos] = frame[0];
+        self.input[1][block + self.pos] = frame[1];
+
+        let output = [self.output[0][self.pos], self.output[1][self.pos]];
+
+        self.pos += 1;
+
+        if self.pos == block {
+            self.process_block();
+            self.pos = 0;
+        }
+
+        output
+    }
+
+    fn process_block(&mut self) {
+        let block = self.block;
+        let fft_size = block * 2;
+        let partitions = self.filter[0].len();
+        let scale = 1.0 / fft_size as f32;
+
+        for channel in 0..2 {
+            for (bin, sample) in self.spectrum.iter_mut().zip(&self.input[channel]) {
+                *bin = Complex::new(*sample, 0.0);
+            }
+
+            self.fft.process_with_scratch(&mut self.spectrum, &mut self.scratch);
+            self.delay_line[channel][self.delay_pos].copy_from_slice(&self.spectrum);
+
+            // the newest input block goes with the first partition, the oldest with the last
+            self.accumulator.fill(Complex::default());
+
+            for partition in 0..partitions {
+                let delayed = &self.delay_line[channel][(self.delay_pos + partitions - partition) % partitions];
+                let filter = &self.filter[channel][partition];
+
+                for ((acc, x), h) in self.accumulator.iter_mut().zip(delayed).zip(filter) {
+                    *acc += x * h;
+                }
+            }
+
+            self.ifft.process_with_scratch(&mut self.accumulator, &mut self.scratch);
+
+            // overlap-save, only the second half is free of circular wrap-around
+            for (out, bin) in self.output[channel].iter_mut().zip(&self.accumulator[block..]) {
+                *out = bin.re * scale;
+            }
+
+            // this block becomes the first half of the next fft
+            self.input[channel].copy_within(block.., 0);
+        }
+
+        self.delay_pos = (self.delay_pos + 1) % partitions;
+    }
+}
+
+// the convolver `Process` runs, plus the one that's being faded out after a swap
+pub struct ConvolutionStage {
+    current: Option<Box<Convolver>>,
+    // Some while crossfading, holding whatever was active before (None means the dry signal)
+    fading_out: Option<Option<Box<Convolver>>>,
+    fade_pos: usize,
+    fade_len: usize,
+}
+
+impl Default for ConvolutionStage {
+    fn default() -> Self {
+        Self {
+            current: None,
+            fading_out: None,
+            fade_pos: 0,
+            fade_len: MIN_CROSSFADE_FRAMES,
+        }
+    }
+}
+
+impl ConvolutionStage {
+    pub fn latency_frames(&self) -> usize {
+        self.current.as_ref().map(|convolver| convolver.latency_frames()).unwrap_or(0)
+    }
+
+    // starts a crossfade to `next` and returns a convolver that has to be dropped off the audio thread,
+    // which happens when a swap comes in while the last one is still fading
+    pub fn swap(&mut self, next: Option<Box<Convolver>>) -> Option<Box<Convolver>> {
+        let interrupted = self.fading_out.take().flatten();
+
+        let previous = std::mem::replace(&mut self.current, next);
+
+        // give the new convolver time to fill its first block before it is fully audible
+        let block = self.current.as_ref().map(|convolver| convolver.block).unwrap_or(0);
+
+        self.fading_out = Some(previous);
+        self.fade_pos = 0;
+        self.fade_len = MIN_CROSSFADE_FRAMES.max(block * 4);
+
+        interrupted
+    }
+
+    // interleaved stereo, in place. returns the old convolver once its fade out is done
+    pub fn process(&mut self, data: &mut [f32]) -> Option<Box<Convolver>> {
+        if self.current.is_none() && self.fading_out.is_none() {
+            return None;
+        }
+
+        for frame in data.chunks_exact_mut(2) {
+            let input = [frame[0], frame[1]];
+
+            let wet = match &mut self.current {
+                Some(convolver) => convolver.process_frame(input),
+                None => input,
+            };
+
+            let output = match &mut self.fading_out {
+                Some(previous) if self.fade_pos < self.fade_len => {
+                    let old = match previous {
+                        Some(convolver) => convolver.process_frame(input),
+                        None => input,
+                    };
+
+                    let t = self.fade_pos as f32 / self.fade_len as f32;
+                    self.fade_pos += 1;
+
+                    [old[0] * (1.0 - t) + wet[0] * t, old[1] * (1.0 - t) + wet[1] * t]
+                }
+                _ => wet,
+            };
+
+            frame[0] = output[0];
+            frame[1] = output[1];
+        }
+
+        if self.fade_pos >= self.fade_len {
+            return self.fading_out.take().flatten();
+        }
+
+        None
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConvolutionStatePayload {
+    pub info: Option<ConvolutionInfo>,
+    pub error: Option<String>,
+}
+
+#[tauri::command]
+pub fn get_convolution_settings() -> Result<ConvolutionSettings, String> {
+    Ok(ConvolutionSettings::load())
+}
+
+#[tauri::command]
+pub fn get_convolution_info() -> Result<Option<ConvolutionInfo>, String> {
+    let convolution_guard = CURRENT_CONVOLUTION.lock().unwrap();
+
+    Ok(convolution_guard.clone())
+}
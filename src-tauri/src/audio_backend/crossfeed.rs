@@ -0,0 +1,128 @@
+use std::f64::consts::PI;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::util::lmdb::settings::{get_setting, store_setting};
+
+const CROSSFEED_SETTINGS: &str = "crossfeed";
+
+// the three presets bs2b ships with, (cutoff of the crossfed signal in hz, feed level in db)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossfeedPreset {
+    // close to a virtual speaker placement of 30 degrees
+    Default,
+    // Chu Moy's values, a bit stronger
+    Cmoy,
+    // Jan Meier's values, the strongest and the most "speaker like"
+    Jmeier,
+}
+
+impl CrossfeedPreset {
+    fn params(&self) -> (f64, f64) {
+        match self {
+            CrossfeedPreset::Default => (700.0, 4.5),
+            CrossfeedPreset::Cmoy => (700.0, 6.0),
+            CrossfeedPreset::Jmeier => (650.0, 9.5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CrossfeedSettings {
+    pub enabled: bool,
+    pub preset: CrossfeedPreset,
+}
+
+impl Default for CrossfeedSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            preset: CrossfeedPreset::Default,
+        }
+    }
+}
+
+impl CrossfeedSettings {
+    pub fn load() -> Self {
+        match get_setting::<CrossfeedSettings>(CROSSFEED_SETTINGS) {
+            Ok(Some(settings)) => settings,
+            Ok(None) => CrossfeedSettings::default(),
+            Err(e) => {
+                println!("error: {:?}", e);
+                CrossfeedSettings::default()
+            }
+        }
+    }
+
+    pub fn store(&self) -> Result<()> {
+        store_setting(CROSSFEED_SETTINGS, self)
+    }
+}
+
+// Bauer stereophonic-to-binaural crossfeed the way bs2b does it: each side gets a low passed copy of
+// the other side, and its own signal goes through a high shelf so the overall tonality stays the same
+pub struct Crossfeed {
+    a0_lo: f32,
+    b1_lo: f32,
+    a0_hi: f32,
+    a1_hi: f32,
+    b1_hi: f32,
+    gain: f32,
+
+    // filter state per channel, [left, right]
+    lo: [f32; 2],
+    hi: [f32; 2],
+    last_input: [f32; 2],
+}
+
+impl Crossfeed {
+    // no allocation in here, so it's fine to build one on the audio thread
+    pub fn new(preset: CrossfeedPreset, sample_rate: u32) -> Self {
+        let (cutoff, feed_db) = preset.params();
+        let sample_rate = sample_rate.max(1) as f64;
+
+        let gain_lo_db = feed_db * -5.0 / 6.0 - 3.0;
+        let gain_hi_db = feed_db / 6.0 - 3.0;
+
+        let gain_lo = 10f64.powf(gain_lo_db / 20.0);
+        let gain_hi = 1.0 - 10f64.powf(gain_hi_db / 20.0);
+        let cutoff_hi = cutoff * 2f64.powf((gain_lo_db - 20.0 * gain_hi.log10()) / 12.0);
+
+        let x_lo = (-2.0 * PI * cutoff / sample_rate).exp();
+        let x_hi = (-2.0 * PI * cutoff_hi / sample_rate).exp();
+
+        Self {
+            a0_lo: (gain_lo * (1.0 - x_lo)) as f32,
+            b1_lo: x_lo as f32,
+            a0_hi: (1.0 - gain_hi * (1.0 - x_hi)) as f32,
+            a1_hi: -x_hi as f32,
+            b1_hi: x_hi as f32,
+            gain: (1.0 / (1.0 - gain_hi + gain_lo)) as f32,
+
+            lo: [0.0; 2],
+            hi: [0.0; 2],
+            last_input: [0.0; 2],
+        }
+    }
+
+    // interleaved stereo, in place
+    pub fn process(&mut self, data: &mut [f32]) {
+        for frame in data.chunks_exact_mut(2) {
+            for (channel, &input) in frame.iter().enumerate() {
+                self.lo[channel] = self.a0_lo * input + self.b1_lo * self.lo[channel];
+                self.hi[channel] = self.a0_hi * input + self.a1_hi * self.last_input[channel] + self.b1_hi * self.hi[channel];
+                self.last_input[channel] = input;
+            }
+
+            frame[0] = (self.hi[0] + self.lo[1]) * self.gain;
+            frame[1] = (self.hi[1] + self.lo[0]) * self.gain;
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_crossfeed_settings() -> Result<CrossfeedSettings, String> {
+    Ok(CrossfeedSettings::load())
+}
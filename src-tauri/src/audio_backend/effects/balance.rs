@@ -0,0 +1,47 @@
+use std::borrow::Cow;
+
+use super::{clamp_to, AudioEffect, ParameterInfo};
+
+pub const PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo { id: Cow::Borrowed("balance"), name: Cow::Borrowed("Balance"), min: -1.0, max: 1.0, default: 0.0 },
+];
+
+// -1 is left only, 1 is right only. the louder side stays at unity so centered material doesn't get quieter
+#[derive(Default)]
+pub struct Balance {
+    balance: f32,
+}
+
+impl AudioEffect for Balance {
+    fn kind(&self) -> &str {
+        "balance"
+    }
+
+    fn prepare(&mut self, _sample_rate: u32, _max_block_frames: usize) {}
+
+    fn process(&mut self, data: &mut [f32]) {
+        let left = (1.0 - self.balance).min(1.0);
+        let right = (1.0 + self.balance).min(1.0);
+
+        for frame in data.chunks_exact_mut(2) {
+            frame[0] *= left;
+            frame[1] *= right;
+        }
+    }
+
+    fn reset(&mut self) {}
+
+    fn parameters(&self) -> &[ParameterInfo] {
+        PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        (index == 0).then_some(self.balance)
+    }
+
+    fn set_parameter(&mut self, index: usize, value: f32) {
+        if index == 0 {
+            self.balance = clamp_to(&PARAMETERS[0], value);
+        }
+    }
+}
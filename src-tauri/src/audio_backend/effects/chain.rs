@@ -0,0 +1,328 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::event_handler::{EFFECT_CHAIN, EFFECT_HANDLES};
+use crate::util::lmdb::records::{delete_record, get_record, store_record};
+use crate::util::lmdb::settings::{get_setting, store_setting};
+use super::{create_effect, effect_kinds, AudioEffect, EffectKind, ParameterInfo, MAX_BLOCK_FRAMES, MAX_EFFECTS};
+
+const EFFECT_CHAIN_SETTING: &str = "effect-chain";
+// opaque plugin state by effect id, kept apart from the chain so the setting stays small
+const EFFECT_STATE_TABLE: &str = "effect-state";
+
+pub struct EffectSlot {
+    pub id: u64,
+    pub bypassed: bool,
+    pub effect: Box<dyn AudioEffect>,
+}
+
+// edits of the chain the process applies between two callbacks
+pub enum EffectChainMsg {
+    Insert { position: usize, slot: EffectSlot },
+    Remove { id: u64 },
+    Move { id: u64, position: usize },
+    Bypass { id: u64, bypassed: bool },
+    SetParameter { id: u64, index: usize, value: f32 },
+}
+
+// the audio thread side, runs the effects in order
+pub struct EffectChain {
+    slots: Vec<EffectSlot>,
+}
+
+impl Default for EffectChain {
+    fn default() -> Self {
+        Self {
+            slots: Vec::with_capacity(MAX_EFFECTS),
+        }
+    }
+}
+
+impl EffectChain {
+    // returns an effect that left the chain, which has to be dropped off the audio thread
+    pub fn handle(&mut self, msg: EffectChainMsg) -> Option<Box<dyn AudioEffect>> {
+        match msg {
+            EffectChainMsg::Insert { position, slot } => {
+                // pushing past the capacity would reallocate in here
+                if self.slots.len() == self.slots.capacity() {
+                    return Some(slot.effect);
+                }
+
+                self.slots.insert(position.min(self.slots.len()), slot);
+                None
+            }
+            EffectChainMsg::Remove { id } => {
+                let index = self.index_of(id)?;
+                let mut effect = self.slots.remove(index).effect;
+
+                effect.release();
+                Some(effect)
+            }
+            EffectChainMsg::Move { id, position } => {
+                let index = self.index_of(id)?;
+                let slot = self.slots.remove(index);
+
+                self.slots.insert(position.min(self.slots.len()), slot);
+                None
+            }
+            EffectChainMsg::Bypass { id, bypassed } => {
+                let index = self.index_of(id)?;
+                let slot = &mut self.slots[index];
+
+                // don't let old filter state ring out when it comes back
+                if slot.bypassed && !bypassed {
+                    slot.effect.reset();
+                }
+
+                slot.bypassed = bypassed;
+                None
+            }
+            EffectChainMsg::SetParameter { id, index, value } => {
+                let slot_index = self.index_of(id)?;
+
+                self.slots[slot_index].effect.set_parameter(index, value);
+                None
+            }
+        }
+    }
+
+    pub fn process(&mut self, data: &mut [f32]) {
+        for slot in self.slots.iter_mut().filter(|slot| !slot.bypassed) {
+            slot.effect.process(data);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for slot in self.slots.iter_mut() {
+            slot.effect.reset();
+        }
+    }
+
+    pub fn latency_frames(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| !slot.bypassed)
+            .map(|slot| slot.effect.latency_frames())
+            .sum()
+    }
+
+    fn index_of(&self, id: u64) -> Option<usize> {
+        self.slots.iter().position(|slot| slot.id == id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectConfig {
+    pub id: u64,
+    pub kind: String,
+    pub bypassed: bool,
+    // by parameter id, so a saved chain survives parameters being added or reordered
+    pub parameters: BTreeMap<String, f32>,
+    // only filled in for the frontend, never saved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    // what the built instance reports, a plugin's can differ from what the scan saw. filled in by build_slot
+    #[serde(skip)]
+    pub parameter_infos: Vec<ParameterInfo>,
+}
+
+// the gui side mirror of the chain, this is what gets saved and what the frontend sees
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EffectChainConfig {
+    pub effects: Vec<EffectConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum EffectChainEdit {
+    Insert { kind: String, position: Option<usize> },
+    Remove { id: u64 },
+    Move { id: u64, position: usize },
+    Bypass { id: u64, bypassed: bool },
+    SetParameter { id: u64, parameter: String, value: f32 },
+}
+
+impl EffectChainConfig {
+    pub fn load() -> Self {
+        match get_setting::<EffectChainConfig>(EFFECT_CHAIN_SETTING) {
+            Ok(Some(config)) => config,
+            Ok(None) => EffectChainConfig::default(),
+            Err(e) => {
+                println!("error: {:?}", e);
+                EffectChainConfig::default()
+            }
+        }
+    }
+
+    pub fn store(&self) -> Result<()> {
+        store_setting(EFFECT_CHAIN_SETTING, self)
+    }
+
+    // applies the edit here and returns what the process has to do to end up with the same chain
+    pub fn apply(&mut self, edit: EffectChainEdit, sample_rate: u32) -> Result<EffectChainMsg> {
+        match edit {
+            EffectChainEdit::Insert { kind, position } => {
+                if self.effects.len() >= MAX_EFFECTS {
+                    return Err(anyhow::anyhow!("the chain can't hold more than {} effects", MAX_EFFECTS));
+                }
+
+                let effect = create_effect(&kind)?;
+
+                let parameters = effect
+                    .parameters()
+                    .iter()
+                    .enumerate()
+                    .map(|(index, parameter)| {
+                        (parameter.id.to_string(), effect.get_parameter(index).unwrap_or(parameter.default))
+                    })
+                    .collect();
+
+                let mut config = EffectConfig {
+                    id: self.effects.iter().map(|effect| effect.id + 1).max().unwrap_or(0),
+                    kind: effect.kind().to_string(),
+                    bypassed: false,
+                    parameters,
+                    error: None,
+                    parameter_infos: Vec::new(),
+                };
+
+                let slot = into_slot(effect, &mut config, sample_rate)?;
+                let position = position.unwrap_or(self.effects.len()).min(self.effects.len());
+
+                self.effects.insert(position, config);
+
+                Ok(EffectChainMsg::Insert { position, slot })
+            }
+            EffectChainEdit::Remove { id } => {
+                let index = self.index_of(id)?;
+                self.effects.remove(index);
+
+                EFFECT_HANDLES.lock().unwrap().remove(&id);
+
+                if let Err(e) = delete_record(EFFECT_STATE_TABLE, &id.to_string()) {
+                    println!("error: {:?}", e);
+                }
+
+                Ok(EffectChainMsg::Remove { id })
+            }
+            EffectChainEdit::Move { id, position } => {
+                let index = self.index_of(id)?;
+                let config = self.effects.remove(index);
+
+                let position = position.min(self.effects.len());
+                self.effects.insert(position, config);
+
+                Ok(EffectChainMsg::Move { id, position })
+            }
+            EffectChainEdit::Bypass { id, bypassed } => {
+                let index = self.index_of(id)?;
+                self.effects[index].bypassed = bypassed;
+
+                Ok(EffectChainMsg::Bypass { id, bypassed })
+            }
+            EffectChainEdit::SetParameter { id, parameter, value } => {
+                let index = self.index_of(id)?;
+                let config = &mut self.effects[index];
+
+                let parameter_index = config.parameter_infos
+                    .iter()
+                    .position(|info| info.id == parameter)
+                    .ok_or_else(|| anyhow::anyhow!("{} has no parameter {}", config.kind, parameter))?;
+
+                let value = super::clamp_to(&config.parameter_infos[parameter_index], value);
+                config.parameters.insert(parameter, value);
+
+                Ok(EffectChainMsg::SetParameter { id, index: parameter_index, value })
+            }
+        }
+    }
+
+    // the chain as the frontend gets to see it, with whatever went wrong in the effects since
+    pub fn with_errors(&self) -> EffectChainConfig {
+        let handles = EFFECT_HANDLES.lock().unwrap();
+
+        let mut chain = self.clone();
+
+        for effect in chain.effects.iter_mut() {
+            effect.error = handles.get(&effect.id).and_then(|handle| handle.error());
+        }
+
+        chain
+    }
+
+    fn index_of(&self, id: u64) -> Result<usize> {
+        self.effects
+            .iter()
+            .position(|effect| effect.id == id)
+            .ok_or_else(|| anyhow::anyhow!("no effect with id {} in the chain", id))
+    }
+}
+
+// creates the effect, restores its state and parameters and prepares it, all before it gets near the audio thread
+pub fn build_slot(config: &mut EffectConfig, sample_rate: u32) -> Result<EffectSlot> {
+    into_slot(create_effect(&config.kind)?, config, sample_rate)
+}
+
+fn into_slot(mut effect: Box<dyn AudioEffect>, config: &mut EffectConfig, sample_rate: u32) -> Result<EffectSlot> {
+    if let Some(state) = get_record::<Vec<u8>>(EFFECT_STATE_TABLE, &config.id.to_string())? {
+        // the parameters below still get restored, so a plugin that lost its state isn't the end of the world
+        if let Err(e) = effect.load_state(&state) {
+            println!("error: {:?}", e);
+        }
+    }
+
+    let values: Vec<(usize, f32)> = effect
+        .parameters()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, parameter)| config.parameters.get(parameter.id.as_ref()).map(|value| (index, *value)))
+        .collect();
+
+    for (index, value) in values {
+        effect.set_parameter(index, value);
+    }
+
+    effect.prepare(sample_rate, MAX_BLOCK_FRAMES);
+
+    config.parameter_infos = effect.parameters().to_vec();
+
+    if let Some(handle) = effect.handle() {
+        EFFECT_HANDLES.lock().unwrap().insert(config.id, handle);
+    }
+
+    Ok(EffectSlot {
+        id: config.id,
+        bypassed: config.bypassed,
+        effect,
+    })
+}
+
+// asks every effect with state of its own for it, so it's there the next time the chain is built
+pub fn save_effect_states() {
+    let handles = EFFECT_HANDLES.lock().unwrap();
+
+    for (id, handle) in handles.iter() {
+        let saved = handle.save_state().and_then(|state| match state.is_empty() {
+            true => Ok(()),
+            false => store_record(EFFECT_STATE_TABLE, &id.to_string(), &state),
+        });
+
+        if let Err(e) = saved {
+            println!("error: {:?}", e);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_effect_kinds() -> Result<Vec<EffectKind>, String> {
+    Ok(effect_kinds())
+}
+
+#[tauri::command]
+pub fn get_effect_chain() -> Result<EffectChainConfig, String> {
+    let chain_guard = EFFECT_CHAIN.lock().unwrap();
+
+    Ok(chain_guard.with_errors())
+}
@@ -0,0 +1,791 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+use anyhow::Result;
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::events::{
+    clap_event_header, clap_event_param_value, clap_input_events, clap_output_events, CLAP_CORE_EVENT_SPACE_ID,
+    CLAP_EVENT_PARAM_VALUE,
+};
+use clap_sys::ext::audio_ports::{clap_audio_port_info, clap_plugin_audio_ports, CLAP_EXT_AUDIO_PORTS};
+use clap_sys::ext::params::{
+    clap_host_params, clap_param_clear_flags, clap_param_info, clap_param_rescan_flags, clap_plugin_params, CLAP_EXT_PARAMS,
+    CLAP_PARAM_IS_HIDDEN, CLAP_PARAM_IS_READONLY,
+};
+use clap_sys::ext::state::{clap_host_state, clap_plugin_state, CLAP_EXT_STATE};
+use clap_sys::ext::thread_check::{clap_host_thread_check, CLAP_EXT_THREAD_CHECK};
+use clap_sys::id::clap_id;
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::plugin::clap_plugin;
+use clap_sys::process::{clap_process, CLAP_PROCESS_ERROR};
+use clap_sys::stream::{clap_istream, clap_ostream};
+use clap_sys::version::{clap_version_is_compatible, CLAP_VERSION};
+use libloading::Library;
+
+use super::clap_thread::{is_audio_thread, is_plugin_thread, mark_audio_thread, on_plugin_thread, watch_instance, HostData};
+use super::{clamp_to, AudioEffect, EffectHandle, ParameterInfo};
+
+const NO_ERROR: u8 = 0;
+const ACTIVATION_FAILED: u8 = 1;
+const START_FAILED: u8 = 2;
+const PROCESS_FAILED: u8 = 3;
+const NON_FINITE_OUTPUT: u8 = 4;
+
+lazy_static::lazy_static! {
+    // the entry's init/deinit must only run once per loaded binary, so every instance of it shares one
+    static ref LOADED_LIBRARIES: Mutex<HashMap<PathBuf, Weak<ClapLibrary>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone)]
+pub struct ClapDescriptor {
+    pub id: String,
+    pub name: String,
+    pub vendor: String,
+    pub version: String,
+}
+
+pub struct ClapLibrary {
+    entry: *const clap_plugin_entry,
+    // has to outlive everything the entry handed out, it's dropped after deinit ran in Drop
+    _library: Library,
+}
+
+// the entry is plain data that stays valid as long as the library is loaded
+unsafe impl Send for ClapLibrary {}
+unsafe impl Sync for ClapLibrary {}
+
+// on macOS a .clap is a bundle, everywhere else it's the shared library itself
+fn binary_path(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        let name = path.file_stem().unwrap_or_default();
+        path.join("Contents").join("MacOS").join(name)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+fn string_from(chars: *const c_char) -> String {
+    if chars.is_null() {
+        return String::new();
+    }
+
+    unsafe { CStr::from_ptr(chars) }.to_string_lossy().to_string()
+}
+
+impl ClapLibrary {
+    pub fn load(path: &Path) -> Result<Arc<ClapLibrary>> {
+        let mut loaded = LOADED_LIBRARIES.lock().unwrap();
+
+        if let Some(library) = loaded.get(path).and_then(Weak::upgrade) {
+            return Ok(library);
+        }
+
+        let library = unsafe { Library::new(binary_path(path)) }?;
+
+        // clap_entry is the struct itself, not a function returning it
+        let entry = unsafe { *library.get::<*const clap_plugin_entry>(b"clap_entry\0")? };
+
+        if entry.is_null() {
+            return Err(anyhow::anyhow!("{} does not export clap_entry", path.display()));
+        }
+
+        let entry_ref = unsafe { &*entry };
+
+        if !clap_version_is_compatible(entry_ref.clap_version) {
+            return Err(anyhow::anyhow!("{} was built for an incompatible clap version", path.display()));
+        }
+
+        let plugin_path = CString::new(path.to_string_lossy().as_bytes())?;
+        let init = entry_ref.init.ok_or_else(|| anyhow::anyhow!("{} has no init", path.display()))?;
+
+        if !on_plugin_thread(|| unsafe { init(plugin_path.as_ptr()) }) {
+            return Err(anyhow::anyhow!("{} failed to initialize", path.display()));
+        }
+
+        let library = Arc::new(ClapLibrary {
+            entry,
+            _library: library,
+        });
+
+        loaded.insert(path.to_path_buf(), Arc::downgrade(&library));
+
+        Ok(library)
+    }
+
+    fn factory(&self) -> Result<&clap_plugin_factory> {
+        let get_factory = unsafe { (*self.entry).get_factory }.ok_or_else(|| anyhow::anyhow!("no get_factory"))?;
+        let factory = unsafe { get_factory(CLAP_PLUGIN_FACTORY_ID.as_ptr()) } as *const clap_plugin_factory;
+
+        if factory.is_null() {
+            return Err(anyhow::anyhow!("no plugin factory"));
+        }
+
+        Ok(unsafe { &*factory })
+    }
+
+    pub fn descriptors(&self) -> Result<Vec<ClapDescriptor>> {
+        let factory = self.factory()?;
+
+        let (Some(get_plugin_count), Some(get_plugin_descriptor)) = (factory.get_plugin_count, factory.get_plugin_descriptor) else {
+            return Err(anyhow::anyhow!("incomplete plugin factory"));
+        };
+
+        let count = unsafe { get_plugin_count(factory) };
+
+        Ok((0..count)
+            .filter_map(|index| {
+                let descriptor = unsafe { get_plugin_descriptor(factory, index) };
+
+                if descriptor.is_null() {
+                    return None;
+                }
+
+                let descriptor = unsafe { &*descriptor };
+
+                Some(ClapDescriptor {
+                    id: string_from(descriptor.id),
+                    name: string_from(descriptor.name),
+                    vendor: string_from(descriptor.vendor),
+                    version: string_from(descriptor.version),
+                })
+            })
+            .collect())
+    }
+}
+
+impl Drop for ClapLibrary {
+    fn drop(&mut self) {
+        if let Some(deinit) = unsafe { (*self.entry).deinit } {
+            on_plugin_thread(|| unsafe { deinit() });
+        }
+    }
+}
+
+unsafe fn host_data<'a>(host: *const clap_host) -> Option<&'a HostData> {
+    if host.is_null() || (*host).host_data.is_null() {
+        return None;
+    }
+
+    Some(&*((*host).host_data as *const HostData))
+}
+
+unsafe extern "C" fn host_is_main_thread(_host: *const clap_host) -> bool {
+    is_plugin_thread()
+}
+
+unsafe extern "C" fn host_is_audio_thread(_host: *const clap_host) -> bool {
+    is_audio_thread()
+}
+
+// the chain keeps the parameters a slot was built with, a plugin that changed them shows the new ones once it's added again
+unsafe extern "C" fn host_params_rescan(_host: *const clap_host, _flags: clap_param_rescan_flags) {}
+
+// there's no automation or modulation to clear
+unsafe extern "C" fn host_params_clear(_host: *const clap_host, _param_id: clap_id, _flags: clap_param_clear_flags) {}
+
+// process runs for as long as the effect is in the chain, the next block flushes whatever is pending
+unsafe extern "C" fn host_params_request_flush(_host: *const clap_host) {}
+
+unsafe extern "C" fn host_state_mark_dirty(host: *const clap_host) {
+    if let Some(data) = host_data(host) {
+        data.state_dirty.store(true, Ordering::Release);
+    }
+}
+
+static HOST_THREAD_CHECK: clap_host_thread_check = clap_host_thread_check {
+    is_main_thread: Some(host_is_main_thread),
+    is_audio_thread: Some(host_is_audio_thread),
+};
+
+static HOST_PARAMS: clap_host_params = clap_host_params {
+    rescan: Some(host_params_rescan),
+    clear: Some(host_params_clear),
+    request_flush: Some(host_params_request_flush),
+};
+
+static HOST_STATE: clap_host_state = clap_host_state {
+    mark_dirty: Some(host_state_mark_dirty),
+};
+
+unsafe extern "C" fn host_get_extension(_host: *const clap_host, extension_id: *const c_char) -> *const c_void {
+    if extension_id.is_null() {
+        return ptr::null();
+    }
+
+    let extension_id = CStr::from_ptr(extension_id);
+
+    if extension_id == CLAP_EXT_THREAD_CHECK {
+        &HOST_THREAD_CHECK as *const clap_host_thread_check as *const c_void
+    } else if extension_id == CLAP_EXT_PARAMS {
+        &HOST_PARAMS as *const clap_host_params as *const c_void
+    } else if extension_id == CLAP_EXT_STATE {
+        &HOST_STATE as *const clap_host_state as *const c_void
+    } else {
+        ptr::null()
+    }
+}
+
+// the chain is never restarted or paused, so restart and process requests have nothing to do
+unsafe extern "C" fn host_request(_host: *const clap_host) {}
+
+// can come from any thread, including the audio thread, so it only sets a flag the plugin thread picks up
+unsafe extern "C" fn host_request_callback(host: *const clap_host) {
+    if let Some(data) = host_data(host) {
+        data.callback_requested.store(true, Ordering::Release);
+    }
+}
+
+fn new_host(data: &Arc<HostData>) -> Box<clap_host> {
+    Box::new(clap_host {
+        clap_version: CLAP_VERSION,
+        host_data: Arc::as_ptr(data) as *mut c_void,
+        name: c"tauriaudiopl".as_ptr(),
+        vendor: c"".as_ptr(),
+        url: c"".as_ptr(),
+        version: c"0.1.0".as_ptr(),
+        get_extension: Some(host_get_extension),
+        request_restart: Some(host_request),
+        request_process: Some(host_request),
+        request_callback: Some(host_request_callback),
+    })
+}
+
+unsafe extern "C" fn write_to_vec(stream: *const clap_ostream, buffer: *const c_void, size: u64) -> i64 {
+    let out = &mut *((*stream).ctx as *mut Vec<u8>);
+
+    out.extend_from_slice(std::slice::from_raw_parts(buffer as *const u8, size as usize));
+
+    size as i64
+}
+
+struct ReadCursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+unsafe extern "C" fn read_from_slice(stream: *const clap_istream, buffer: *mut c_void, size: u64) -> i64 {
+    let cursor = &mut *((*stream).ctx as *mut ReadCursor);
+    let read = (cursor.data.len() - cursor.pos).min(size as usize);
+
+    ptr::copy_nonoverlapping(cursor.data[cursor.pos..].as_ptr(), buffer as *mut u8, read);
+    cursor.pos += read;
+
+    read as i64
+}
+
+// one instance of a plugin. the gui side and the audio thread share it, which is the threading model
+// clap is built around: state and activation on the "main" thread while process runs on the audio thread.
+// everything [main-thread] is handed to the plugin thread, whoever calls it
+pub struct ClapInstance {
+    plugin: *const clap_plugin,
+    params: *const clap_plugin_params,
+    state: *const clap_plugin_state,
+    activated: AtomicBool,
+    error: AtomicU8,
+    // the plugin keeps pointers to these, so they're kept until the plugin is destroyed
+    host_data: Arc<HostData>,
+    _host: Box<clap_host>,
+    _library: Arc<ClapLibrary>,
+}
+
+unsafe impl Send for ClapInstance {}
+unsafe impl Sync for ClapInstance {}
+
+impl ClapInstance {
+    pub fn new(library: Arc<ClapLibrary>, plugin_id: &str) -> Result<ClapInstance> {
+        on_plugin_thread(|| Self::create(library, plugin_id))
+    }
+
+    fn create(library: Arc<ClapLibrary>, plugin_id: &str) -> Result<ClapInstance> {
+        let factory = library.factory()?;
+        let create_plugin = factory.create_plugin.ok_or_else(|| anyhow::anyhow!("incomplete plugin factory"))?;
+
+        let host_data = HostData::new();
+        let host = new_host(&host_data);
+        let plugin_id_c = CString::new(plugin_id)?;
+
+        let plugin = unsafe { create_plugin(factory, &*host, plugin_id_c.as_ptr()) };
+
+        if plugin.is_null() {
+            return Err(anyhow::anyhow!("could not create {}", plugin_id));
+        }
+
+        let plugin_ref = unsafe { &*plugin };
+
+        let initialized = plugin_ref.init.map(|init| unsafe { init(plugin) }).unwrap_or(false);
+
+        if !initialized {
+            if let Some(destroy) = plugin_ref.destroy {
+                unsafe { destroy(plugin) };
+            }
+
+            return Err(anyhow::anyhow!("{} failed to initialize", plugin_id));
+        }
+
+        host_data.plugin.store(plugin as *mut clap_plugin, Ordering::Release);
+        watch_instance(&host_data);
+
+        let extension = |id: &CStr| {
+            plugin_ref
+                .get_extension
+                .map(|get_extension| unsafe { get_extension(plugin, id.as_ptr()) })
+                .unwrap_or(ptr::null())
+        };
+
+        let instance = ClapInstance {
+            plugin,
+            params: extension(CLAP_EXT_PARAMS) as *const clap_plugin_params,
+            state: extension(CLAP_EXT_STATE) as *const clap_plugin_state,
+            activated: AtomicBool::new(false),
+            error: AtomicU8::new(NO_ERROR),
+            host_data,
+            _host: host,
+            _library: library,
+        };
+
+        // from here on Drop takes care of destroying it
+        instance.check_stereo(extension(CLAP_EXT_AUDIO_PORTS) as *const clap_plugin_audio_ports)?;
+
+        Ok(instance)
+    }
+
+    // the chain is interleaved stereo, so only plugins with a stereo main in and output fit in
+    fn check_stereo(&self, audio_ports: *const clap_plugin_audio_ports) -> Result<()> {
+        if audio_ports.is_null() {
+            return Err(anyhow::anyhow!("the plugin has no audio ports"));
+        }
+
+        let audio_ports = unsafe { &*audio_ports };
+
+        let (Some(count), Some(get)) = (audio_ports.count, audio_ports.get) else {
+            return Err(anyhow::anyhow!("the plugin has no audio ports"));
+        };
+
+        for is_input in [true, false] {
+            if unsafe { count(self.plugin, is_input) } == 0 {
+                return Err(anyhow::anyhow!("the plugin is not an effect, it has no {}", if is_input { "input" } else { "output" }));
+            }
+
+            let mut info: clap_audio_port_info = unsafe { std::mem::zeroed() };
+
+            if !unsafe { get(self.plugin, 0, is_input, &mut info) } || info.channel_count != 2 {
+                return Err(anyhow::anyhow!("only stereo plugins are supported"));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn plugin(&self) -> &clap_plugin {
+        unsafe { &*self.plugin }
+    }
+
+    // (clap id, info) of everything the user is supposed to touch
+    pub fn parameters(&self) -> Vec<(u32, ParameterInfo)> {
+        on_plugin_thread(|| self.parameters_here())
+    }
+
+    fn parameters_here(&self) -> Vec<(u32, ParameterInfo)> {
+        if self.params.is_null() {
+            return Vec::new();
+        }
+
+        let params = unsafe { &*self.params };
+
+        let (Some(count), Some(get_info)) = (params.count, params.get_info) else {
+            return Vec::new();
+        };
+
+        (0..unsafe { count(self.plugin) })
+            .filter_map(|index| {
+                let mut info: clap_param_info = unsafe { std::mem::zeroed() };
+
+                if !unsafe { get_info(self.plugin, index, &mut info) } {
+                    return None;
+                }
+
+                if info.flags & (CLAP_PARAM_IS_HIDDEN | CLAP_PARAM_IS_READONLY) != 0 {
+                    return None;
+                }
+
+                Some((info.id, ParameterInfo {
+                    id: Cow::Owned(info.id.to_string()),
+                    name: Cow::Owned(string_from(info.name.as_ptr())),
+                    min: info.min_value as f32,
+                    max: info.max_value as f32,
+                    default: info.default_value as f32,
+                }))
+            })
+            .collect()
+    }
+
+    fn activate(&self, sample_rate: u32, max_block_frames: usize) -> bool {
+        let Some(activate) = self.plugin().activate else {
+            return false;
+        };
+
+        let activated = on_plugin_thread(|| unsafe { activate(self.plugin, sample_rate as f64, 1, max_block_frames as u32) });
+        self.activated.store(activated, Ordering::Release);
+
+        activated
+    }
+
+    fn load_state(&self, state: &[u8]) -> Result<()> {
+        let Some(load) = (!self.state.is_null()).then(|| unsafe { (*self.state).load }).flatten() else {
+            return Ok(());
+        };
+
+        let loaded = on_plugin_thread(|| {
+            let mut cursor = ReadCursor { data: state, pos: 0 };
+
+            let stream = clap_istream {
+                ctx: &mut cursor as *mut ReadCursor as *mut c_void,
+                read: Some(read_from_slice),
+            };
+
+            unsafe { load(self.plugin, &stream) }
+        });
+
+        if !loaded {
+            return Err(anyhow::anyhow!("the plugin rejected its saved state"));
+        }
+
+        // what's stored is what it just got
+        self.host_data.state_dirty.store(false, Ordering::Release);
+
+        Ok(())
+    }
+
+    fn set_error(&self, error: u8) {
+        self.error.store(error, Ordering::Release);
+    }
+
+    fn failed(&self) -> bool {
+        self.error.load(Ordering::Acquire) != NO_ERROR
+    }
+}
+
+impl EffectHandle for ClapInstance {
+    // nothing when the plugin didn't mark its state dirty since it was last saved or loaded
+    fn save_state(&self) -> Result<Vec<u8>> {
+        let Some(save) = (!self.state.is_null()).then(|| unsafe { (*self.state).save }).flatten() else {
+            return Ok(Vec::new());
+        };
+
+        if !self.host_data.state_dirty.swap(false, Ordering::AcqRel) {
+            return Ok(Vec::new());
+        }
+
+        let mut state = Vec::new();
+
+        let saved = on_plugin_thread(|| {
+            let stream = clap_ostream {
+                ctx: &mut state as *mut Vec<u8> as *mut c_void,
+                write: Some(write_to_vec),
+            };
+
+            unsafe { save(self.plugin, &stream) }
+        });
+
+        if !saved {
+            self.host_data.state_dirty.store(true, Ordering::Release);
+            return Err(anyhow::anyhow!("the plugin could not save its state"));
+        }
+
+        Ok(state)
+    }
+
+    fn error(&self) -> Option<String> {
+        let message = match self.error.load(Ordering::Acquire) {
+            ACTIVATION_FAILED => "the plugin could not be activated",
+            START_FAILED => "the plugin refused to start processing",
+            PROCESS_FAILED => "the plugin reported an error while processing",
+            NON_FINITE_OUTPUT => "the plugin produced invalid samples",
+            _ => return None,
+        };
+
+        Some(format!("{}, it's bypassed now", message))
+    }
+}
+
+impl Drop for ClapInstance {
+    fn drop(&mut self) {
+        let instance: &ClapInstance = self;
+
+        on_plugin_thread(|| {
+            // no callbacks for it from here on
+            instance.host_data.plugin.store(ptr::null_mut(), Ordering::Release);
+
+            let plugin = instance.plugin();
+
+            if instance.activated.load(Ordering::Acquire) {
+                if let Some(deactivate) = plugin.deactivate {
+                    unsafe { deactivate(instance.plugin) };
+                }
+            }
+
+            if let Some(destroy) = plugin.destroy {
+                unsafe { destroy(instance.plugin) };
+            }
+        });
+    }
+}
+
+unsafe extern "C" fn events_size(list: *const clap_input_events) -> u32 {
+    let events = &*((*list).ctx as *const Vec<clap_event_param_value>);
+
+    events.len() as u32
+}
+
+unsafe extern "C" fn events_get(list: *const clap_input_events, index: u32) -> *const clap_event_header {
+    let events = &*((*list).ctx as *const Vec<clap_event_param_value>);
+
+    match events.get(index as usize) {
+        Some(event) => event as *const clap_event_param_value as *const clap_event_header,
+        None => ptr::null(),
+    }
+}
+
+// nothing listens to what the plugin has to say (yet)
+unsafe extern "C" fn discard_event(_list: *const clap_output_events, _event: *const clap_event_header) -> bool {
+    true
+}
+
+// the chain's view of a plugin. a plugin that errors or outputs garbage is bypassed from then on
+// instead of taking playback down with it. a plugin that crashes the process can't be caught like
+// that though, that would need it to run in a process of its own
+pub struct ClapEffect {
+    kind: String,
+    instance: Arc<ClapInstance>,
+    parameters: Vec<ParameterInfo>,
+    param_ids: Vec<u32>,
+    values: Vec<f32>,
+
+    // parameter changes waiting for the next process call, at most one per parameter so it never grows
+    pending: Vec<clap_event_param_value>,
+
+    // clap wants one buffer per channel
+    input: [Vec<f32>; 2],
+    output: [Vec<f32>; 2],
+    max_block_frames: usize,
+
+    started: bool,
+    steady_time: i64,
+}
+
+impl ClapEffect {
+    pub fn new(kind: String, instance: ClapInstance) -> Self {
+        let (param_ids, parameters): (Vec<u32>, Vec<ParameterInfo>) = instance.parameters().into_iter().unzip();
+        let values = parameters.iter().map(|parameter| parameter.default).collect();
+        let pending = Vec::with_capacity(parameters.len());
+
+        Self {
+            kind,
+            instance: Arc::new(instance),
+            parameters,
+            param_ids,
+            values,
+            pending,
+            input: [Vec::new(), Vec::new()],
+            output: [Vec::new(), Vec::new()],
+            max_block_frames: 0,
+            started: false,
+            steady_time: 0,
+        }
+    }
+
+    fn process_block(&mut self, block: &mut [f32]) -> Result<(), u8> {
+        let frames = block.len() / 2;
+
+        for (frame, samples) in block.chunks_exact(2).enumerate() {
+            self.input[0][frame] = samples[0];
+            self.input[1][frame] = samples[1];
+        }
+
+        let mut input_channels = [self.input[0].as_mut_ptr(), self.input[1].as_mut_ptr()];
+        let mut output_channels = [self.output[0].as_mut_ptr(), self.output[1].as_mut_ptr()];
+
+        let input_buffer = clap_audio_buffer {
+            data32: input_channels.as_mut_ptr(),
+            data64: ptr::null_mut(),
+            channel_count: 2,
+            latency: 0,
+            constant_mask: 0,
+        };
+
+        let mut output_buffer = clap_audio_buffer {
+            data32: output_channels.as_mut_ptr(),
+            data64: ptr::null_mut(),
+            channel_count: 2,
+            latency: 0,
+            constant_mask: 0,
+        };
+
+        let in_events = clap_input_events {
+            ctx: &self.pending as *const Vec<clap_event_param_value> as *mut c_void,
+            size: Some(events_size),
+            get: Some(events_get),
+        };
+
+        let out_events = clap_output_events {
+            ctx: ptr::null_mut(),
+            try_push: Some(discard_event),
+        };
+
+        let process = clap_process {
+            steady_time: self.steady_time,
+            frames_count: frames as u32,
+            transport: ptr::null(),
+            audio_inputs: &input_buffer,
+            audio_outputs: &mut output_buffer,
+            audio_inputs_count: 1,
+            audio_outputs_count: 1,
+            in_events: &in_events,
+            out_events: &out_events,
+        };
+
+        let Some(process_fn) = self.instance.plugin().process else {
+            return Err(PROCESS_FAILED);
+        };
+
+        let status = unsafe { process_fn(self.instance.plugin, &process) };
+
+        self.pending.clear();
+        self.steady_time += frames as i64;
+
+        if status == CLAP_PROCESS_ERROR {
+            return Err(PROCESS_FAILED);
+        }
+
+        let output = [&self.output[0][..frames], &self.output[1][..frames]];
+
+        if output.iter().any(|channel| channel.iter().any(|sample| !sample.is_finite())) {
+            return Err(NON_FINITE_OUTPUT);
+        }
+
+        for (frame, samples) in block.chunks_exact_mut(2).enumerate() {
+            samples[0] = output[0][frame];
+            samples[1] = output[1][frame];
+        }
+
+        Ok(())
+    }
+}
+
+impl AudioEffect for ClapEffect {
+    fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    fn prepare(&mut self, sample_rate: u32, max_block_frames: usize) {
+        self.max_block_frames = max_block_frames;
+        self.input = [vec![0.0; max_block_frames], vec![0.0; max_block_frames]];
+        self.output = [vec![0.0; max_block_frames], vec![0.0; max_block_frames]];
+
+        if !self.instance.activate(sample_rate, max_block_frames) {
+            self.instance.set_error(ACTIVATION_FAILED);
+        }
+    }
+
+    fn process(&mut self, data: &mut [f32]) {
+        if self.instance.failed() || self.max_block_frames == 0 {
+            return;
+        }
+
+        mark_audio_thread();
+
+        if !self.started {
+            let started = self.instance.plugin().start_processing.map(|start| unsafe { start(self.instance.plugin) });
+
+            // start_processing is optional, not having it means there's nothing to start
+            if started == Some(false) {
+                self.instance.set_error(START_FAILED);
+                return;
+            }
+
+            self.started = true;
+        }
+
+        for block in data.chunks_mut(self.max_block_frames * 2) {
+            // the block keeps what it had, so a failing plugin just drops out of the chain
+            if let Err(error) = self.process_block(block) {
+                self.instance.set_error(error);
+                return;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        if self.instance.failed() || !self.instance.activated.load(Ordering::Acquire) {
+            return;
+        }
+
+        if let Some(reset) = self.instance.plugin().reset {
+            unsafe { reset(self.instance.plugin) };
+        }
+    }
+
+    fn parameters(&self) -> &[ParameterInfo] {
+        &self.parameters
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        self.values.get(index).copied()
+    }
+
+    fn set_parameter(&mut self, index: usize, value: f32) {
+        let (Some(parameter), Some(&param_id)) = (self.parameters.get(index), self.param_ids.get(index)) else {
+            return;
+        };
+
+        let value = clamp_to(parameter, value);
+        self.values[index] = value;
+
+        if let Some(event) = self.pending.iter_mut().find(|event| event.param_id == param_id) {
+            event.value = value as f64;
+            return;
+        }
+
+        self.pending.push(clap_event_param_value {
+            header: clap_event_header {
+                size: std::mem::size_of::<clap_event_param_value>() as u32,
+                time: 0,
+                space_id: CLAP_CORE_EVENT_SPACE_ID,
+                type_: CLAP_EVENT_PARAM_VALUE,
+                flags: 0,
+            },
+            param_id,
+            cookie: ptr::null_mut(),
+            note_id: -1,
+            port_index: -1,
+            channel: -1,
+            key: -1,
+            value: value as f64,
+        });
+    }
+
+    fn release(&mut self) {
+        if self.started {
+            if let Some(stop) = self.instance.plugin().stop_processing {
+                unsafe { stop(self.instance.plugin) };
+            }
+
+            self.started = false;
+        }
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> Result<()> {
+        self.instance.load_state(state)
+    }
+
+    fn handle(&self) -> Option<Arc<dyn EffectHandle>> {
+        Some(self.instance.clone())
+    }
+}
@@ -0,0 +1,131 @@
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+use clap_sys::plugin::clap_plugin;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// how long a plugin that asked for on_main_thread waits at most when nothing else is going on
+const CALLBACK_TICK: Duration = Duration::from_millis(20);
+
+lazy_static::lazy_static! {
+    static ref PLUGIN_THREAD: (Mutex<Sender<Job>>, ThreadId) = spawn_plugin_thread();
+}
+
+thread_local! {
+    // instances created on the plugin thread, only ever touched there
+    static INSTANCES: RefCell<Vec<Weak<HostData>>> = const { RefCell::new(Vec::new()) };
+    static IS_AUDIO_THREAD: Cell<bool> = const { Cell::new(false) };
+}
+
+// what a plugin reaches through clap_host.host_data. the flags are set from whatever thread the plugin
+// calls the host on and acted upon on the plugin thread
+pub struct HostData {
+    // null before init and again once the plugin is destroyed
+    pub plugin: AtomicPtr<clap_plugin>,
+    pub callback_requested: AtomicBool,
+    pub state_dirty: AtomicBool,
+}
+
+impl HostData {
+    pub fn new() -> Arc<HostData> {
+        Arc::new(HostData {
+            plugin: AtomicPtr::new(ptr::null_mut()),
+            callback_requested: AtomicBool::new(false),
+            // saved once in any case, not every plugin tells when its state changed
+            state_dirty: AtomicBool::new(true),
+        })
+    }
+}
+
+// clap's "main thread". a gui toolkit owns our actual main thread, so every [main-thread] call of every
+// plugin goes through this one instead: init, activation, state, parameter info and destroy
+fn spawn_plugin_thread() -> (Mutex<Sender<Job>>, ThreadId) {
+    let (tx, rx) = mpsc::channel::<Job>();
+
+    let handle = thread::Builder::new()
+        .name("clap plugins".to_string())
+        .spawn(move || loop {
+            match rx.recv_timeout(CALLBACK_TICK) {
+                Ok(job) => job(),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            run_requested_callbacks();
+        })
+        .expect("unable to start the plugin thread");
+
+    (Mutex::new(tx), handle.thread().id())
+}
+
+fn run_requested_callbacks() {
+    let requested: Vec<*const clap_plugin> = INSTANCES.with(|instances| {
+        let mut instances = instances.borrow_mut();
+        instances.retain(|data| data.strong_count() > 0);
+
+        instances
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|data| data.callback_requested.swap(false, Ordering::AcqRel))
+            .map(|data| data.plugin.load(Ordering::Acquire) as *const clap_plugin)
+            .filter(|plugin| !plugin.is_null())
+            .collect()
+    });
+
+    // destroy runs on this thread as well, so none of them can be gone by now
+    for plugin in requested {
+        if let Some(on_main_thread) = unsafe { (*plugin).on_main_thread } {
+            unsafe { on_main_thread(plugin) };
+        }
+    }
+}
+
+pub fn is_plugin_thread() -> bool {
+    thread::current().id() == PLUGIN_THREAD.1
+}
+
+// whatever thread runs the chain, the first process call marks it
+pub fn mark_audio_thread() {
+    IS_AUDIO_THREAD.with(|is_audio_thread| is_audio_thread.set(true));
+}
+
+pub fn is_audio_thread() -> bool {
+    IS_AUDIO_THREAD.with(Cell::get)
+}
+
+// has to be called on the plugin thread, right after the plugin was created
+pub fn watch_instance(data: &Arc<HostData>) {
+    debug_assert!(is_plugin_thread());
+
+    INSTANCES.with(|instances| instances.borrow_mut().push(Arc::downgrade(data)));
+}
+
+// runs `f` on the plugin thread and waits for it. a panic in there comes back out here
+pub fn on_plugin_thread<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    if is_plugin_thread() {
+        return f();
+    }
+
+    let (result_tx, result_rx) = mpsc::sync_channel(1);
+
+    let job: Box<dyn FnOnce() + Send + '_> = Box::new(move || {
+        let _ = result_tx.send(panic::catch_unwind(AssertUnwindSafe(f)));
+    });
+
+    // the result is waited for below, so nothing the job borrows can go away while it runs
+    let job: Job = unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + '_>, Job>(job) };
+
+    PLUGIN_THREAD.0.lock().unwrap().send(job).expect("the plugin thread is gone");
+
+    match result_rx.recv().expect("the plugin thread is gone") {
+        Ok(result) => result,
+        Err(panic) => panic::resume_unwind(panic),
+    }
+}
@@ -0,0 +1,71 @@
+use std::borrow::Cow;
+
+use super::{clamp_to, AudioEffect, ParameterInfo};
+
+pub const PARAMETERS: &[ParameterInfo] = &[
+    ParameterInfo { id: Cow::Borrowed("gain_db"), name: Cow::Borrowed("Gain (dB)"), min: -60.0, max: 24.0, default: 0.0 },
+];
+
+pub struct Gain {
+    gain_db: f32,
+    // the gain actually applied, ramped towards the target over one block so changes don't click
+    current: f32,
+}
+
+impl Default for Gain {
+    fn default() -> Self {
+        Self {
+            gain_db: 0.0,
+            current: 1.0,
+        }
+    }
+}
+
+impl Gain {
+    fn target(&self) -> f32 {
+        10f32.powf(self.gain_db / 20.0)
+    }
+}
+
+impl AudioEffect for Gain {
+    fn kind(&self) -> &str {
+        "gain"
+    }
+
+    fn prepare(&mut self, _sample_rate: u32, _max_block_frames: usize) {
+        self.current = self.target();
+    }
+
+    fn process(&mut self, data: &mut [f32]) {
+        let target = self.target();
+        let frames = (data.len() / 2).max(1) as f32;
+        let step = (target - self.current) / frames;
+
+        for frame in data.chunks_exact_mut(2) {
+            self.current += step;
+
+            frame[0] *= self.current;
+            frame[1] *= self.current;
+        }
+
+        self.current = target;
+    }
+
+    fn reset(&mut self) {
+        self.current = self.target();
+    }
+
+    fn parameters(&self) -> &[ParameterInfo] {
+        PARAMETERS
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        (index == 0).then_some(self.gain_db)
+    }
+
+    fn set_parameter(&mut self, index: usize, value: f32) {
+        if index == 0 {
+            self.gain_db = clamp_to(&PARAMETERS[0], value);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+pub mod chain;
+pub mod gain;
+pub mod balance;
+pub mod clap;
+pub mod clap_thread;
+pub mod plugins;
+pub mod wasm;
+
+use gain::Gain;
+use balance::Balance;
+
+// what the chain is prepared for when the device doesn't tell us its buffer size up front
+pub const MAX_BLOCK_FRAMES: usize = 8192;
+
+// the chain Vec is allocated with this capacity so inserting never reallocates on the audio thread
+pub const MAX_EFFECTS: usize = 32;
+
+// built in effects describe their parameters in consts, plugins only at runtime, hence the Cows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParameterInfo {
+    pub id: Cow<'static, str>,
+    pub name: Cow<'static, str>,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+}
+
+// anything that can sit in the chain. process, reset and set_parameter run on the audio thread
+// and must not block or allocate, prepare runs before the effect is handed over
+pub trait AudioEffect: Send {
+    fn kind(&self) -> &str;
+
+    // called once with the device rate before the effect is inserted
+    fn prepare(&mut self, sample_rate: u32, max_block_frames: usize);
+
+    // interleaved stereo, in place
+    fn process(&mut self, data: &mut [f32]);
+
+    // clears filter state/tails, e.g. when a new track starts
+    fn reset(&mut self);
+
+    fn parameters(&self) -> &[ParameterInfo];
+
+    fn get_parameter(&self, index: usize) -> Option<f32>;
+
+    // values are clamped to the parameter's range
+    fn set_parameter(&mut self, index: usize, value: f32);
+
+    fn latency_frames(&self) -> usize {
+        0
+    }
+
+    // called on the audio thread when the effect leaves the chain, before it's sent off to be dropped
+    fn release(&mut self) {}
+
+    // opaque state on top of the parameters, only plugins have any. called before prepare
+    fn load_state(&mut self, _state: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    // what the gui side keeps to reach the effect once it lives on the audio thread
+    fn handle(&self) -> Option<Arc<dyn EffectHandle>> {
+        None
+    }
+}
+
+// shared between the audio thread and the gui side, so everything in here has to be thread safe
+pub trait EffectHandle: Send + Sync {
+    fn save_state(&self) -> Result<Vec<u8>>;
+
+    // set once the effect stopped working and is passing audio through instead
+    fn error(&self) -> Option<String>;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectKind {
+    pub kind: String,
+    pub name: String,
+    pub parameters: Vec<ParameterInfo>,
+}
+
+// every effect the chain can be built from, new built in effects only have to be added here
+pub fn create_effect(kind: &str) -> Result<Box<dyn AudioEffect>> {
+    match kind {
+        "gain" => Ok(Box::new(Gain::default())),
+        "balance" => Ok(Box::new(Balance::default())),
+        _ => match plugins::plugin_key(kind) {
+            Some(key) => plugins::create_plugin_effect(key),
+            None => Err(anyhow::anyhow!("unknown effect {}", kind)),
+        },
+    }
+}
+
+pub fn effect_kinds() -> Vec<EffectKind> {
+    let mut kinds = vec![
+        EffectKind { kind: "gain".to_string(), name: "Gain".to_string(), parameters: gain::PARAMETERS.to_vec() },
+        EffectKind { kind: "balance".to_string(), name: "Balance".to_string(), parameters: balance::PARAMETERS.to_vec() },
+    ];
+
+    kinds.extend(plugins::plugin_effect_kinds());
+
+    kinds
+}
+
+pub fn clamp_to(parameter: &ParameterInfo, value: f32) -> f32 {
+    value.clamp(parameter.min, parameter.max)
+}
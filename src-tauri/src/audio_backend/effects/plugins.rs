@@ -0,0 +1,340 @@
+use std::env;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::util::hash::fnv1a_64;
+use crate::util::lmdb::records::{clear_records, get_record, list_records, store_record};
+use super::clap::{ClapEffect, ClapInstance, ClapLibrary};
+use super::wasm::WasmEffect;
+use super::{AudioEffect, EffectKind, ParameterInfo};
+
+const PLUGIN_TABLE: &str = "plugin";
+
+// effect kinds of plugins are one of these followed by the key of their scan result
+const CLAP_KIND_PREFIX: &str = "clap:";
+const WASM_KIND_PREFIX: &str = "wasm:";
+
+// where users put their own wasm effects, next to the lmdb data
+const WASM_PLUGINS_FOLDER: &str = "audio_plugins";
+
+// bundles nest a little, whole system folders shouldn't be walked endlessly though
+const MAX_SCAN_DEPTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginFormat {
+    Clap,
+    Lv2,
+    Wasm,
+}
+
+impl PluginFormat {
+    fn kind_prefix(&self) -> &'static str {
+        match self {
+            PluginFormat::Wasm => WASM_KIND_PREFIX,
+            _ => CLAP_KIND_PREFIX,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginInfo {
+    pub key: String,
+    pub format: PluginFormat,
+    pub path: String,
+    pub plugin_id: String,
+    pub name: String,
+    pub vendor: String,
+    pub version: String,
+    pub parameters: Vec<ParameterInfo>,
+    // why the plugin can't be put into the chain, None when it can
+    pub error: Option<String>,
+}
+
+fn plugin_key_for(path: &Path, plugin_id: &str) -> String {
+    format!("{:016x}", fnv1a_64(format!("{}#{}", path.to_string_lossy(), plugin_id).as_bytes()))
+}
+
+pub fn plugin_key(kind: &str) -> Option<&str> {
+    kind.strip_prefix(CLAP_KIND_PREFIX).or_else(|| kind.strip_prefix(WASM_KIND_PREFIX))
+}
+
+pub fn wasm_plugins_folder() -> Result<PathBuf> {
+    let folder = dirs::home_dir()
+        .ok_or_else(|| anyhow::anyhow!("unable to get the home directory"))?
+        .join(WASM_PLUGINS_FOLDER);
+
+    fs::create_dir_all(&folder)?;
+
+    Ok(folder)
+}
+
+fn env_paths(var: &str) -> Vec<PathBuf> {
+    env::var_os(var).map(|paths| env::split_paths(&paths).collect()).unwrap_or_default()
+}
+
+// the locations from the clap spec, CLAP_PATH comes first
+fn clap_search_paths() -> Vec<PathBuf> {
+    let mut paths = env_paths("CLAP_PATH");
+
+    if cfg!(target_os = "windows") {
+        if let Some(common) = env::var_os("COMMONPROGRAMFILES") {
+            paths.push(PathBuf::from(common).join("CLAP"));
+        }
+
+        if let Some(local) = env::var_os("LOCALAPPDATA") {
+            paths.push(PathBuf::from(local).join("Programs").join("Common").join("CLAP"));
+        }
+    } else if cfg!(target_os = "macos") {
+        paths.push(PathBuf::from("/Library/Audio/Plug-Ins/CLAP"));
+
+        if let Some(home) = dirs::home_dir() {
+            paths.push(home.join("Library/Audio/Plug-Ins/CLAP"));
+        }
+    } else {
+        if let Some(home) = dirs::home_dir() {
+            paths.push(home.join(".clap"));
+        }
+
+        paths.push(PathBuf::from("/usr/lib/clap"));
+        paths.push(PathBuf::from("/usr/local/lib/clap"));
+    }
+
+    paths
+}
+
+fn lv2_search_paths() -> Vec<PathBuf> {
+    let mut paths = env_paths("LV2_PATH");
+
+    if !paths.is_empty() {
+        return paths;
+    }
+
+    if cfg!(target_os = "windows") {
+        if let Some(appdata) = env::var_os("APPDATA") {
+            paths.push(PathBuf::from(appdata).join("LV2"));
+        }
+
+        if let Some(common) = env::var_os("COMMONPROGRAMFILES") {
+            paths.push(PathBuf::from(common).join("LV2"));
+        }
+    } else if cfg!(target_os = "macos") {
+        paths.push(PathBuf::from("/Library/Audio/Plug-Ins/LV2"));
+
+        if let Some(home) = dirs::home_dir() {
+            paths.push(home.join("Library/Audio/Plug-Ins/LV2"));
+        }
+    } else {
+        if let Some(home) = dirs::home_dir() {
+            paths.push(home.join(".lv2"));
+        }
+
+        paths.push(PathBuf::from("/usr/lib/lv2"));
+        paths.push(PathBuf::from("/usr/local/lib/lv2"));
+    }
+
+    paths
+}
+
+// everything below `dir` with the given extension, files or bundle directories alike
+fn find_with_extension(dir: &Path, extension: &str, depth: usize, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext.eq_ignore_ascii_case(extension)) {
+            found.push(path);
+        } else if path.is_dir() && depth < MAX_SCAN_DEPTH {
+            find_with_extension(&path, extension, depth + 1, found);
+        }
+    }
+}
+
+fn failed_plugin(format: PluginFormat, path: &Path, error: String) -> PluginInfo {
+    PluginInfo {
+        key: plugin_key_for(path, ""),
+        format,
+        path: path.to_string_lossy().to_string(),
+        plugin_id: String::new(),
+        name: path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+        vendor: String::new(),
+        version: String::new(),
+        parameters: Vec::new(),
+        error: Some(error),
+    }
+}
+
+// instantiates every plugin in the binary once, to learn its parameters and whether it fits the chain at all
+fn scan_clap(path: &Path) -> Result<Vec<PluginInfo>> {
+    let library = ClapLibrary::load(path)?;
+
+    Ok(library
+        .descriptors()?
+        .into_iter()
+        .map(|descriptor| {
+            let instance = ClapInstance::new(library.clone(), &descriptor.id);
+
+            PluginInfo {
+                key: plugin_key_for(path, &descriptor.id),
+                format: PluginFormat::Clap,
+                path: path.to_string_lossy().to_string(),
+                plugin_id: descriptor.id,
+                name: descriptor.name,
+                vendor: descriptor.vendor,
+                version: descriptor.version,
+                parameters: instance
+                    .as_ref()
+                    .map(|instance| instance.parameters().into_iter().map(|(_, info)| info).collect())
+                    .unwrap_or_default(),
+                error: instance.err().map(|e| e.to_string()),
+            }
+        })
+        .collect())
+}
+
+fn scan_wasm(path: &Path) -> PluginInfo {
+    match WasmEffect::load(path, String::new()) {
+        Ok(effect) => PluginInfo {
+            key: plugin_key_for(path, ""),
+            format: PluginFormat::Wasm,
+            path: path.to_string_lossy().to_string(),
+            plugin_id: String::new(),
+            name: path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            vendor: String::new(),
+            version: String::new(),
+            parameters: effect.parameters().to_vec(),
+            error: None,
+        },
+        Err(e) => failed_plugin(PluginFormat::Wasm, path, e.to_string()),
+    }
+}
+
+// loading a plugin runs its code in our process. a panic on our side of the ffi is caught here,
+// a plugin that segfaults while being scanned still takes the player down
+pub fn scan_plugins_on_disk() -> Vec<PluginInfo> {
+    let mut plugins = Vec::new();
+
+    let mut clap_paths = Vec::new();
+
+    for dir in clap_search_paths() {
+        find_with_extension(&dir, "clap", 0, &mut clap_paths);
+    }
+
+    clap_paths.sort();
+    clap_paths.dedup();
+
+    for path in clap_paths {
+        match panic::catch_unwind(AssertUnwindSafe(|| scan_clap(&path))) {
+            Ok(Ok(found)) => plugins.extend(found),
+            Ok(Err(e)) => plugins.push(failed_plugin(PluginFormat::Clap, &path, e.to_string())),
+            Err(_) => plugins.push(failed_plugin(PluginFormat::Clap, &path, "the plugin panicked while loading".to_string())),
+        }
+    }
+
+    match wasm_plugins_folder() {
+        Ok(folder) => {
+            let mut wasm_paths = Vec::new();
+
+            find_with_extension(&folder, "wasm", 0, &mut wasm_paths);
+            find_with_extension(&folder, "wat", 0, &mut wasm_paths);
+
+            wasm_paths.sort();
+
+            plugins.extend(wasm_paths.iter().map(|path| scan_wasm(path)));
+        }
+        Err(e) => println!("error: {:?}", e),
+    }
+
+    // lv2 needs a turtle parser and lilv to get at the plugins inside a bundle, which we don't ship,
+    // so bundles are only listed so it's visible why they don't show up in the chain
+    let mut lv2_paths = Vec::new();
+
+    for dir in lv2_search_paths() {
+        find_with_extension(&dir, "lv2", 0, &mut lv2_paths);
+    }
+
+    for path in lv2_paths {
+        plugins.push(failed_plugin(PluginFormat::Lv2, &path, "LV2 isn't supported, only CLAP and wasm plugins can be used".to_string()));
+    }
+
+    plugins
+}
+
+pub fn create_plugin_effect(key: &str) -> Result<Box<dyn AudioEffect>> {
+    let plugin = get_record::<PluginInfo>(PLUGIN_TABLE, key)?
+        .ok_or_else(|| anyhow::anyhow!("plugin {} is not installed anymore, try scanning again", key))?;
+
+    let kind = format!("{}{}", plugin.format.kind_prefix(), key);
+
+    match plugin.format {
+        PluginFormat::Clap => {
+            let library = ClapLibrary::load(Path::new(&plugin.path))?;
+            let instance = ClapInstance::new(library, &plugin.plugin_id)?;
+
+            Ok(Box::new(ClapEffect::new(kind, instance)))
+        }
+        PluginFormat::Wasm => Ok(Box::new(WasmEffect::load(Path::new(&plugin.path), kind)?)),
+        PluginFormat::Lv2 => Err(anyhow::anyhow!("{} can't be loaded", plugin.name)),
+    }
+}
+
+// from the last scan, so listing them doesn't load every plugin again
+pub fn plugin_effect_kinds() -> Vec<EffectKind> {
+    let plugins = match list_records::<PluginInfo>(PLUGIN_TABLE) {
+        Ok(plugins) => plugins,
+        Err(e) => {
+            println!("error: {:?}", e);
+            return Vec::new();
+        }
+    };
+
+    plugins
+        .into_iter()
+        .filter(|(_, plugin)| plugin.error.is_none())
+        .map(|(key, plugin)| EffectKind {
+            kind: format!("{}{}", plugin.format.kind_prefix(), key),
+            name: if plugin.vendor.is_empty() { plugin.name } else { format!("{} ({})", plugin.name, plugin.vendor) },
+            parameters: plugin.parameters,
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn scan_plugins() -> Result<Vec<PluginInfo>, String> {
+    let plugins = scan_plugins_on_disk();
+
+    clear_records(PLUGIN_TABLE).map_err(|e| e.to_string())?;
+
+    for plugin in plugins.iter() {
+        store_record(PLUGIN_TABLE, &plugin.key, plugin).map_err(|e| e.to_string())?;
+    }
+
+    Ok(plugins)
+}
+
+#[tauri::command]
+pub fn get_wasm_plugins_folder() -> Result<String, String> {
+    wasm_plugins_folder()
+        .map(|folder| folder.to_string_lossy().to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_plugins() -> Result<Vec<PluginInfo>, String> {
+    let mut plugins: Vec<PluginInfo> = list_records::<PluginInfo>(PLUGIN_TABLE)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|(_, plugin)| plugin)
+        .collect();
+
+    plugins.sort_by_key(|plugin| plugin.name.to_lowercase());
+
+    Ok(plugins)
+}
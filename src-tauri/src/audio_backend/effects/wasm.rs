@@ -0,0 +1,321 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+use wasmtime::{Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, Trap, TypedFunc};
+
+use super::{clamp_to, AudioEffect, EffectHandle, ParameterInfo};
+
+// the abi a plugin has to implement, everything is exported by the module, nothing can be imported:
+//
+//   memory                                   the plugin's linear memory
+//   prepare(sample_rate: i32, max_frames: i32) -> i32
+//                                            returns a pointer to room for max_frames of interleaved stereo f32
+//   process(frames: i32)                     processes that many frames in place in the buffer from prepare
+//   reset()                                  optional, clears filter state
+//   param_count() -> i32                     optional, everything below is only needed with parameters
+//   param_name(index: i32) -> i32            pointer to a nul terminated utf-8 name, the name is the parameter's id
+//   param_min(index: i32) -> f32
+//   param_max(index: i32) -> f32
+//   param_default(index: i32) -> f32
+//   set_param(index: i32, value: f32)
+
+// a plugin can't take more than this, no matter how much it tries to grow its memory
+const MAX_MEMORY_BYTES: usize = 16 * 1024 * 1024;
+
+// fuel is roughly one wasm instruction. a call that runs out of it traps instead of hanging the audio thread
+const FUEL_PER_CALL: u64 = 100_000;
+const FUEL_PER_FRAME: u64 = 2_000;
+
+const MAX_PARAM_NAME_BYTES: usize = 64;
+
+const NO_ERROR: u8 = 0;
+const PREPARE_FAILED: u8 = 1;
+const OUT_OF_FUEL: u8 = 2;
+const TRAPPED: u8 = 3;
+const NON_FINITE_OUTPUT: u8 = 4;
+
+lazy_static::lazy_static! {
+    static ref ENGINE: Engine = {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+
+        Engine::new(&config).unwrap()
+    };
+}
+
+#[derive(Default)]
+pub struct WasmStatus {
+    error: AtomicU8,
+}
+
+impl WasmStatus {
+    fn set_error(&self, error: u8) {
+        self.error.store(error, Ordering::Release);
+    }
+
+    fn failed(&self) -> bool {
+        self.error.load(Ordering::Acquire) != NO_ERROR
+    }
+}
+
+impl EffectHandle for WasmStatus {
+    // the parameters are all there is to a wasm plugin, and those are saved with the chain
+    fn save_state(&self) -> Result<Vec<u8>> {
+        Ok(Vec::new())
+    }
+
+    fn error(&self) -> Option<String> {
+        let message = match self.error.load(Ordering::Acquire) {
+            PREPARE_FAILED => "the plugin failed to prepare",
+            OUT_OF_FUEL => "the plugin took too long to process a block",
+            TRAPPED => "the plugin crashed",
+            NON_FINITE_OUTPUT => "the plugin produced invalid samples",
+            _ => return None,
+        };
+
+        Some(format!("{}, it's bypassed now", message))
+    }
+}
+
+fn trap_error(e: &anyhow::Error) -> u8 {
+    match e.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => OUT_OF_FUEL,
+        _ => TRAPPED,
+    }
+}
+
+pub struct WasmEffect {
+    kind: String,
+    store: Store<StoreLimits>,
+    memory: Memory,
+
+    prepare: TypedFunc<(i32, i32), i32>,
+    process: TypedFunc<i32, ()>,
+    reset: Option<TypedFunc<(), ()>>,
+    set_param: Option<TypedFunc<(i32, f32), ()>>,
+
+    parameters: Vec<ParameterInfo>,
+    values: Vec<f32>,
+
+    // where prepare told us to put the samples, in bytes into the plugin's memory
+    buffer: usize,
+    max_block_frames: usize,
+
+    status: Arc<WasmStatus>,
+}
+
+impl WasmEffect {
+    // compiles and instantiates the plugin, .wat text works just as well as binary .wasm
+    pub fn load(path: &Path, kind: String) -> Result<Self> {
+        let module = Module::new(&ENGINE, fs::read(path)?)?;
+
+        if module.imports().len() > 0 {
+            return Err(anyhow::anyhow!("plugins can't import anything, everything has to be inside the module"));
+        }
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY_BYTES)
+            .instances(1)
+            .build();
+
+        let mut store = Store::new(&ENGINE, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL_PER_CALL)?;
+
+        let instance = Instance::new(&mut store, &module, &[])?;
+
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow::anyhow!("the plugin doesn't export its memory"))?;
+
+        let prepare = instance.get_typed_func::<(i32, i32), i32>(&mut store, "prepare")?;
+        let process = instance.get_typed_func::<i32, ()>(&mut store, "process")?;
+        let reset = instance.get_typed_func::<(), ()>(&mut store, "reset").ok();
+        let set_param = instance.get_typed_func::<(i32, f32), ()>(&mut store, "set_param").ok();
+
+        let parameters = read_parameters(&mut store, &instance, memory)?;
+        let values = parameters.iter().map(|parameter| parameter.default).collect();
+
+        Ok(Self {
+            kind,
+            store,
+            memory,
+            prepare,
+            process,
+            reset,
+            set_param,
+            parameters,
+            values,
+            buffer: 0,
+            max_block_frames: 0,
+            status: Arc::new(WasmStatus::default()),
+        })
+    }
+
+    fn process_block(&mut self, block: &mut [f32]) -> Result<(), u8> {
+        let frames = block.len() / 2;
+
+        let bytes = &mut self.memory.data_mut(&mut self.store)[self.buffer..self.buffer + block.len() * 4];
+
+        for (sample, slot) in block.iter().zip(bytes.chunks_exact_mut(4)) {
+            slot.copy_from_slice(&sample.to_le_bytes());
+        }
+
+        self.store
+            .set_fuel(FUEL_PER_CALL + FUEL_PER_FRAME * frames as u64)
+            .map_err(|_| TRAPPED)?;
+
+        self.process
+            .call(&mut self.store, frames as i32)
+            .map_err(|e| trap_error(&e))?;
+
+        // the plugin may have grown its memory in the meantime, but the buffer stays where it was
+        let bytes = &self.memory.data(&self.store)[self.buffer..self.buffer + block.len() * 4];
+
+        if bytes.chunks_exact(4).any(|slot| !f32::from_le_bytes([slot[0], slot[1], slot[2], slot[3]]).is_finite()) {
+            return Err(NON_FINITE_OUTPUT);
+        }
+
+        for (sample, slot) in block.iter_mut().zip(bytes.chunks_exact(4)) {
+            *sample = f32::from_le_bytes([slot[0], slot[1], slot[2], slot[3]]);
+        }
+
+        Ok(())
+    }
+}
+
+fn read_parameters(store: &mut Store<StoreLimits>, instance: &Instance, memory: Memory) -> Result<Vec<ParameterInfo>> {
+    let Ok(param_count) = instance.get_typed_func::<(), i32>(&mut *store, "param_count") else {
+        return Ok(Vec::new());
+    };
+
+    let param_name = instance.get_typed_func::<i32, i32>(&mut *store, "param_name")?;
+    let param_min = instance.get_typed_func::<i32, f32>(&mut *store, "param_min")?;
+    let param_max = instance.get_typed_func::<i32, f32>(&mut *store, "param_max")?;
+    let param_default = instance.get_typed_func::<i32, f32>(&mut *store, "param_default")?;
+
+    store.set_fuel(FUEL_PER_CALL)?;
+    let count = param_count.call(&mut *store, ())?;
+
+    (0..count)
+        .map(|index| {
+            store.set_fuel(FUEL_PER_CALL)?;
+
+            let name_ptr = param_name.call(&mut *store, index)? as u32 as usize;
+            let name_bytes = memory.data(&*store).get(name_ptr..).unwrap_or_default();
+
+            let name: Vec<u8> = name_bytes
+                .iter()
+                .take(MAX_PARAM_NAME_BYTES)
+                .take_while(|byte| **byte != 0)
+                .copied()
+                .collect();
+
+            let name = String::from_utf8_lossy(&name).to_string();
+
+            Ok(ParameterInfo {
+                id: Cow::Owned(name.clone()),
+                name: Cow::Owned(name),
+                min: param_min.call(&mut *store, index)?,
+                max: param_max.call(&mut *store, index)?,
+                default: param_default.call(&mut *store, index)?,
+            })
+        })
+        .collect()
+}
+
+impl AudioEffect for WasmEffect {
+    fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    fn prepare(&mut self, sample_rate: u32, max_block_frames: usize) {
+        if self.store.set_fuel(FUEL_PER_CALL).is_err() {
+            self.status.set_error(PREPARE_FAILED);
+            return;
+        }
+
+        let buffer = match self.prepare.call(&mut self.store, (sample_rate as i32, max_block_frames as i32)) {
+            Ok(buffer) => buffer as u32 as usize,
+            Err(e) => {
+                println!("error: {:?}", e);
+                self.status.set_error(PREPARE_FAILED);
+                return;
+            }
+        };
+
+        // a buffer pointing outside of its memory would have us panic on the audio thread
+        if buffer + max_block_frames * 2 * 4 > self.memory.data_size(&self.store) {
+            self.status.set_error(PREPARE_FAILED);
+            return;
+        }
+
+        self.buffer = buffer;
+        self.max_block_frames = max_block_frames;
+    }
+
+    fn process(&mut self, data: &mut [f32]) {
+        if self.status.failed() || self.max_block_frames == 0 {
+            return;
+        }
+
+        for block in data.chunks_mut(self.max_block_frames * 2) {
+            // the block keeps what it had, so a failing plugin just drops out of the chain
+            if let Err(error) = self.process_block(block) {
+                self.status.set_error(error);
+                return;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        let Some(reset) = &self.reset else {
+            return;
+        };
+
+        if self.status.failed() || self.store.set_fuel(FUEL_PER_CALL).is_err() {
+            return;
+        }
+
+        if let Err(e) = reset.call(&mut self.store, ()) {
+            self.status.set_error(trap_error(&e));
+        }
+    }
+
+    fn parameters(&self) -> &[ParameterInfo] {
+        &self.parameters
+    }
+
+    fn get_parameter(&self, index: usize) -> Option<f32> {
+        self.values.get(index).copied()
+    }
+
+    fn set_parameter(&mut self, index: usize, value: f32) {
+        let Some(parameter) = self.parameters.get(index) else {
+            return;
+        };
+
+        let value = clamp_to(parameter, value);
+        self.values[index] = value;
+
+        let Some(set_param) = &self.set_param else {
+            return;
+        };
+
+        if self.status.failed() || self.store.set_fuel(FUEL_PER_CALL).is_err() {
+            return;
+        }
+
+        if let Err(e) = set_param.call(&mut self.store, (index as i32, value)) {
+            self.status.set_error(trap_error(&e));
+        }
+    }
+
+    fn handle(&self) -> Option<Arc<dyn EffectHandle>> {
+        Some(self.status.clone())
+    }
+}
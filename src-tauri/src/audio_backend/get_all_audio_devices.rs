@@ -11,7 +11,7 @@ pub fn get_device_info() -> Result<Vec<String>, anyhow::Error> {
 
         let devices = host.devices()?;
 
-        for (_, device) in devices.enumerate() {
+        for device in devices {
 
             all_devices.push(device.name()?);
         }
@@ -1,3 +1,13 @@
 pub mod output;
 pub mod process;
-pub mod get_all_audio_devices;
\ No newline at end of file
+pub mod get_all_audio_devices;
+pub mod transport;
+pub mod playback_error;
+pub mod probe;
+pub mod network_stream;
+pub mod silence;
+pub mod crossfeed;
+pub mod convolution;
+pub mod effects;
+pub mod opus;
+pub mod track_decoder;
\ No newline at end of file
@@ -0,0 +1,681 @@
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use rtrb::{Consumer, Producer, RingBuffer};
+use serde::Serialize;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::{MediaSourceStream, ReadOnlySource};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::event_handler::ProcessToGuiMsg;
+use super::transport::secs_to_frames;
+use super::opus::codecs;
+
+// the ring buffer between the network thread and the audio thread, sized for the highest rate radio uses
+const RING_BUFFER_SECS: usize = 10;
+const MAX_SAMPLE_RATE: usize = 48_000;
+
+// how much has to be buffered before playback starts (and restarts after an underrun),
+// this is what evens out the network jitter
+const PREBUFFER_SECS: f64 = 2.0;
+
+const PUSH_CHUNK_FRAMES: usize = 4096;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const READ_TIMEOUT: Duration = Duration::from_secs(15);
+
+const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NowPlaying {
+    pub url: String,
+    // icy-name of the station, if the server sends one
+    pub station: Option<String>,
+    // the last StreamTitle from the icy metadata
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamStatus {
+    Connecting,
+    Playing,
+    Reconnecting,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamStatusPayload {
+    pub url: String,
+    pub status: StreamStatus,
+    pub attempt: u32,
+    pub message: Option<String>,
+}
+
+// the audio thread side of a network stream, the connection itself lives on its own thread
+// and keeps the ring buffer filled with interleaved stereo
+pub struct NetworkStream {
+    consumer: Consumer<f32>,
+    sample_rate: Arc<AtomicU32>,
+    shutdown: Arc<AtomicBool>,
+
+    // true until the jitter buffer has filled up, and again after every underrun
+    buffering: bool,
+    played_frames: usize,
+}
+
+impl NetworkStream {
+    pub fn open(url: String, to_gui_tx: Sender<ProcessToGuiMsg>) -> Self {
+        let (producer, consumer) = RingBuffer::<f32>::new(RING_BUFFER_SECS * MAX_SAMPLE_RATE * 2);
+
+        let sample_rate = Arc::new(AtomicU32::new(0));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let mut worker = StreamWorker {
+            url,
+            producer,
+            sample_rate: sample_rate.clone(),
+            shutdown: shutdown.clone(),
+            to_gui_tx,
+            got_audio: false,
+        };
+
+        thread::spawn(move || worker.run());
+
+        Self {
+            consumer,
+            sample_rate,
+            shutdown,
+            buffering: true,
+            played_frames: 0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate.load(Ordering::Relaxed)
+    }
+
+    pub fn is_buffering(&self) -> bool {
+        self.buffering
+    }
+
+    pub fn played_frames(&self) -> usize {
+        self.played_frames
+    }
+
+    // fills `data` (interleaved stereo) from the jitter buffer and returns how many frames were written,
+    // the rest is left for the caller to silence
+    pub fn read(&mut self, data: &mut [f32]) -> usize {
+        let sample_rate = self.sample_rate();
+
+        if self.buffering {
+            let prebuffer = (secs_to_frames(PREBUFFER_SECS, sample_rate) * 2).min(self.consumer.buffer().capacity());
+
+            if sample_rate == 0 || self.consumer.slots() < prebuffer {
+                return 0;
+            }
+
+            self.buffering = false;
+        }
+
+        let wanted = data.len() - data.len() % 2;
+        let available = self.consumer.slots() - self.consumer.slots() % 2;
+        let samples = wanted.min(available);
+
+        if let Ok(chunk) = self.consumer.read_chunk(samples) {
+            let (first, second) = chunk.as_slices();
+
+            data[..first.len()].copy_from_slice(first);
+            data[first.len()..samples].copy_from_slice(second);
+
+            chunk.commit_all();
+        }
+
+        if samples < wanted {
+            println!("network stream ran dry, buffering.");
+            self.buffering = true;
+        }
+
+        self.played_frames += samples / 2;
+
+        samples / 2
+    }
+}
+
+impl Drop for NetworkStream {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+    }
+}
+
+struct StreamWorker {
+    url: String,
+    producer: Producer<f32>,
+    sample_rate: Arc<AtomicU32>,
+    shutdown: Arc<AtomicBool>,
+    to_gui_tx: Sender<ProcessToGuiMsg>,
+
+    // whether the current connection has produced any audio yet
+    got_audio: bool,
+}
+
+impl StreamWorker {
+    fn run(&mut self) {
+        let mut failures: u32 = 0;
+
+        loop {
+            if self.is_shut_down() {
+                return;
+            }
+
+            let status = if failures == 0 { StreamStatus::Connecting } else { StreamStatus::Reconnecting };
+            self.send_status(status, failures, None);
+
+            self.got_audio = false;
+
+            let result = self.connect_and_decode();
+
+            if self.is_shut_down() {
+                return;
+            }
+
+            // a connection that played for a while before dropping starts the count over
+            if self.got_audio {
+                failures = 0;
+            }
+
+            failures += 1;
+
+            let message = match result {
+                Ok(()) => "connection closed by the server".to_string(),
+                Err(e) => e.to_string(),
+            };
+
+            println!("error: stream {} dropped: {}", self.url, message);
+
+            if failures > MAX_RECONNECT_ATTEMPTS {
+                self.send_status(StreamStatus::Failed, failures - 1, Some(message));
+                return;
+            }
+
+            let delay = Duration::from_secs(1 << (failures - 1).min(4)).min(MAX_RECONNECT_DELAY);
+
+            if !self.sleep_unless_shut_down(delay) {
+                return;
+            }
+        }
+    }
+
+    fn connect_and_decode(&mut self) -> Result<()> {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(CONNECT_TIMEOUT)
+            .timeout_read(READ_TIMEOUT)
+            .build();
+
+        // old shoutcast v1 servers answer with "ICY 200 OK" instead of an http status line, those won't connect
+        let response = agent.get(&self.url).set("Icy-MetaData", "1").call()?;
+
+        let station = response.header("icy-name").map(|name| name.trim().to_string());
+
+        let metaint = response
+            .header("icy-metaint")
+            .and_then(|metaint| metaint.trim().parse::<usize>().ok())
+            .filter(|&metaint| metaint > 0);
+
+        let mut hint = Hint::new();
+
+        if let Some(extension) = extension_for_content_type(response.content_type()) {
+            hint.with_extension(extension);
+        }
+
+        let _ = self.to_gui_tx.send(ProcessToGuiMsg::NowPlaying(NowPlaying {
+            url: self.url.clone(),
+            station: station.clone(),
+            title: None,
+        }));
+
+        let now_playing_tx = self.to_gui_tx.clone();
+        let url = self.url.clone();
+
+        let reader = IcyReader::new(response.into_reader(), metaint, Box::new(move |title| {
+            let _ = now_playing_tx.send(ProcessToGuiMsg::NowPlaying(NowPlaying {
+                url: url.clone(),
+                station: station.clone(),
+                title: Some(title),
+            }));
+        }));
+
+        let mss = MediaSourceStream::new(Box::new(ReadOnlySource::new(reader)), Default::default());
+
+        let probed = symphonia::default::get_probe().format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default()
+        )?;
+
+        let mut format = probed.format;
+
+        let track = format
+            .default_track()
+            .filter(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| anyhow::anyhow!("stream does not contain an audio track"))?;
+
+        let track_id = track.id;
+
+        let mut decoder = codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+        loop {
+            if self.is_shut_down() {
+                return Ok(());
+            }
+
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e.into()),
+            };
+
+            if packet.track_id() != track_id {
+                continue;
+            }
+
+            let decoded = match decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // a corrupt frame on the wire is not worth a reconnect, just drop it
+                Err(SymphoniaError::DecodeError(e)) => {
+                    println!("error: {}", e);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            if decoded.frames() == 0 {
+                continue;
+            }
+
+            let spec = *decoded.spec();
+
+            let mut sample_buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            sample_buffer.copy_interleaved_ref(decoded);
+
+            self.sample_rate.store(spec.rate, Ordering::Relaxed);
+
+            if !self.got_audio {
+                self.got_audio = true;
+                self.send_status(StreamStatus::Playing, 0, None);
+            }
+
+            if !self.push_frames(sample_buffer.samples(), spec.channels.count()) {
+                return Ok(());
+            }
+        }
+    }
+
+    // pushes whole stereo frames only so the audio thread never sees half a frame,
+    // returns false if the stream got dropped while waiting for room
+    fn push_frames(&mut self, samples: &[f32], num_channels: usize) -> bool {
+        if num_channels == 0 {
+            return true;
+        }
+
+        let num_frames = samples.len() / num_channels;
+        let mut pushed = 0;
+
+        while pushed < num_frames {
+            let frames = (num_frames - pushed).min(PUSH_CHUNK_FRAMES);
+
+            // the ring buffer is only full while paused or while the prebuffer is filling, so polling is fine
+            while self.producer.slots() < frames * 2 {
+                if !self.sleep_unless_shut_down(Duration::from_millis(10)) {
+                    return false;
+                }
+            }
+
+            if let Ok(chunk) = self.producer.write_chunk_uninit(frames * 2) {
+                // mono goes to both sides, anything past stereo is dropped
+                let stereo = samples[pushed * num_channels..(pushed + frames) * num_channels]
+                    .chunks(num_channels)
+                    .flat_map(|frame| [frame[0], frame[frame.len().min(2) - 1]]);
+
+                chunk.fill_from_iter(stereo);
+            }
+
+            pushed += frames;
+        }
+
+        true
+    }
+
+    fn send_status(&self, status: StreamStatus, attempt: u32, message: Option<String>) {
+        let _ = self.to_gui_tx.send(ProcessToGuiMsg::StreamStatus(StreamStatusPayload {
+            url: self.url.clone(),
+            status,
+            attempt,
+            message,
+        }));
+    }
+
+    fn is_shut_down(&self) -> bool {
+        self.shutdown.load(Ordering::Relaxed)
+    }
+
+    fn sleep_unless_shut_down(&self, duration: Duration) -> bool {
+        let step = Duration::from_millis(10);
+        let mut slept = Duration::ZERO;
+
+        while slept < duration {
+            if self.is_shut_down() {
+                return false;
+            }
+
+            thread::sleep(step);
+            slept += step;
+        }
+
+        !self.is_shut_down()
+    }
+}
+
+fn extension_for_content_type(content_type: &str) -> Option<&'static str> {
+    match content_type.to_lowercase().as_str() {
+        "audio/mpeg" | "audio/mp3" | "audio/mpeg3" => Some("mp3"),
+        "audio/aac" | "audio/aacp" | "audio/x-aac" => Some("aac"),
+        "audio/ogg" | "application/ogg" | "audio/vorbis" => Some("ogg"),
+        "audio/flac" | "audio/x-flac" => Some("flac"),
+        "audio/wav" | "audio/x-wav" | "audio/wave" => Some("wav"),
+        _ => None,
+    }
+}
+
+// called with every new StreamTitle
+pub type OnTitle = Box<dyn FnMut(String) + Send + Sync>;
+
+// strips the icy metadata blocks out of the audio data, the server puts one after every `metaint` bytes
+pub struct IcyReader<R> {
+    inner: R,
+    metaint: Option<usize>,
+    until_metadata: usize,
+    last_title: Option<String>,
+    on_title: OnTitle,
+}
+
+impl<R: Read> IcyReader<R> {
+    pub fn new(inner: R, metaint: Option<usize>, on_title: OnTitle) -> Self {
+        Self {
+            inner,
+            metaint,
+            until_metadata: metaint.unwrap_or(0),
+            last_title: None,
+            on_title,
+        }
+    }
+
+    fn read_metadata(&mut self) -> io::Result<()> {
+        let mut length = [0u8; 1];
+        self.inner.read_exact(&mut length)?;
+
+        // the length byte counts 16 byte blocks, 0 means the metadata didn't change
+        let size = length[0] as usize * 16;
+
+        if size == 0 {
+            return Ok(());
+        }
+
+        let mut block = vec![0u8; size];
+        self.inner.read_exact(&mut block)?;
+
+        if let Some(title) = parse_stream_title(&block) {
+            if self.last_title.as_ref() != Some(&title) {
+                self.last_title = Some(title.clone());
+                (self.on_title)(title);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for IcyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(metaint) = self.metaint else {
+            return self.inner.read(buf);
+        };
+
+        if self.until_metadata == 0 {
+            self.read_metadata()?;
+            self.until_metadata = metaint;
+        }
+
+        let max = buf.len().min(self.until_metadata);
+        let read = self.inner.read(&mut buf[..max])?;
+
+        self.until_metadata -= read;
+
+        Ok(read)
+    }
+}
+
+// metadata looks like `StreamTitle='Artist - Title';StreamUrl='...';` padded with zeroes
+pub fn parse_stream_title(block: &[u8]) -> Option<String> {
+    let end = block.iter().position(|&b| b == 0).unwrap_or(block.len());
+
+    // most servers send utf-8 these days, older ones latin-1
+    let text = match std::str::from_utf8(&block[..end]) {
+        Ok(text) => text.to_string(),
+        Err(_) => block[..end].iter().map(|&b| b as char).collect(),
+    };
+
+    let start = text.find("StreamTitle='")? + "StreamTitle='".len();
+    let rest = &text[start..];
+
+    // titles can contain quotes themselves, so only the quote followed by a semicolon ends it
+    let title = match rest.find("';") {
+        Some(end) => &rest[..end],
+        None => rest.strip_suffix('\'').unwrap_or(rest),
+    };
+
+    let title = title.trim();
+
+    if title.is_empty() {
+        None
+    } else {
+        Some(title.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::mpsc;
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    use super::*;
+    use crate::util::test_http::{response, serve};
+
+    // an icy metadata block: the length byte and the text padded to 16 byte blocks
+    fn metadata_block(text: &str) -> Vec<u8> {
+        let blocks = text.len().div_ceil(16);
+
+        let mut block = vec![blocks as u8];
+        block.extend_from_slice(text.as_bytes());
+        block.resize(1 + blocks * 16, 0);
+
+        block
+    }
+
+    // `audio` with a metadata block after every `metaint` bytes, the way a server sends it
+    fn with_metadata(audio: &[u8], metaint: usize, blocks: &[Vec<u8>]) -> Vec<u8> {
+        let mut stream = Vec::new();
+
+        for (index, chunk) in audio.chunks(metaint).enumerate() {
+            stream.extend_from_slice(chunk);
+
+            if chunk.len() == metaint {
+                stream.extend_from_slice(blocks.get(index).map(Vec::as_slice).unwrap_or(&[0]));
+            }
+        }
+
+        stream
+    }
+
+    // hands out at most one byte per read, like a slow connection
+    struct Trickle<R>(R);
+
+    impl<R: Read> Read for Trickle<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let max = buf.len().min(1);
+            self.0.read(&mut buf[..max])
+        }
+    }
+
+    fn collect_titles() -> (Arc<Mutex<Vec<String>>>, OnTitle) {
+        let titles = Arc::new(Mutex::new(Vec::new()));
+        let collected = titles.clone();
+
+        (titles, Box::new(move |title| collected.lock().unwrap().push(title)))
+    }
+
+    // half a second of 16 bit stereo silence
+    fn wav() -> Vec<u8> {
+        let sample_rate: u32 = 8000;
+        let data_len: u32 = sample_rate / 2 * 4;
+
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(sample_rate * 4).to_le_bytes());
+        wav.extend_from_slice(&4u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.resize(wav.len() + data_len as usize, 0);
+
+        wav
+    }
+
+    #[test]
+    fn stream_title_is_read_from_the_metadata() {
+        let block = b"StreamTitle='Artist - Title';StreamUrl='http://example.com';\0\0\0";
+
+        assert_eq!(parse_stream_title(block).as_deref(), Some("Artist - Title"));
+    }
+
+    #[test]
+    fn stream_title_can_contain_quotes() {
+        assert_eq!(parse_stream_title(b"StreamTitle='Rock 'n' Roll';").as_deref(), Some("Rock 'n' Roll"));
+        assert_eq!(parse_stream_title(b"StreamTitle='It's over'\0\0").as_deref(), Some("It's over"));
+    }
+
+    #[test]
+    fn stream_title_falls_back_to_latin1() {
+        assert_eq!(parse_stream_title(b"StreamTitle='Caf\xe9';").as_deref(), Some("Café"));
+    }
+
+    #[test]
+    fn empty_or_missing_stream_title_is_none() {
+        assert_eq!(parse_stream_title(b"StreamTitle='';"), None);
+        assert_eq!(parse_stream_title(b"StreamUrl='http://example.com';"), None);
+        assert_eq!(parse_stream_title(&[0; 16]), None);
+    }
+
+    #[test]
+    fn icy_reader_strips_metadata() {
+        let audio: Vec<u8> = (0..100).collect();
+        let blocks = [
+            metadata_block("StreamTitle='First';"),
+            // unchanged metadata is sent as a zero length
+            vec![0],
+            // the same title again isn't reported twice
+            metadata_block("StreamTitle='First';"),
+            metadata_block("StreamTitle='Second';"),
+        ];
+
+        let (titles, on_title) = collect_titles();
+        let mut reader = IcyReader::new(Cursor::new(with_metadata(&audio, 16, &blocks)), Some(16), on_title);
+
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+
+        assert_eq!(read, audio);
+        assert_eq!(*titles.lock().unwrap(), vec!["First", "Second"]);
+    }
+
+    #[test]
+    fn icy_reader_handles_metadata_split_across_reads() {
+        let audio: Vec<u8> = (0..64).collect();
+        let blocks = [metadata_block("StreamTitle='Artist - Title';")];
+
+        let (titles, on_title) = collect_titles();
+        let mut reader = IcyReader::new(Trickle(Cursor::new(with_metadata(&audio, 10, &blocks))), Some(10), on_title);
+
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+
+        assert_eq!(read, audio);
+        assert_eq!(*titles.lock().unwrap(), vec!["Artist - Title"]);
+    }
+
+    #[test]
+    fn icy_reader_without_metaint_passes_everything_through() {
+        let audio: Vec<u8> = (0..50).collect();
+
+        let (titles, on_title) = collect_titles();
+        let mut reader = IcyReader::new(Cursor::new(audio.clone()), None, on_title);
+
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+
+        assert_eq!(read, audio);
+        assert!(titles.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn stream_reconnects_after_the_server_closes_the_connection() {
+        let metaint = 1000;
+        let blocks = [metadata_block("StreamTitle='Artist - Title';")];
+        let body = with_metadata(&wav(), metaint, &blocks);
+
+        // every connection gets the whole file and is closed after it
+        let url = serve(move |_, _| response("audio/wav", &[("icy-metaint", &metaint.to_string())], &body));
+
+        let (to_gui_tx, from_stream_rx) = mpsc::channel();
+        let stream = NetworkStream::open(format!("{}/live", url), to_gui_tx);
+
+        let mut statuses = Vec::new();
+        let mut titles = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(20);
+
+        while statuses.iter().filter(|status| **status == StreamStatus::Playing).count() < 2 && Instant::now() < deadline {
+            match from_stream_rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(ProcessToGuiMsg::StreamStatus(payload)) => statuses.push(payload.status),
+                Ok(ProcessToGuiMsg::NowPlaying(now_playing)) => titles.extend(now_playing.title),
+                _ => {}
+            }
+        }
+
+        drop(stream);
+
+        assert_eq!(
+            statuses,
+            vec![StreamStatus::Connecting, StreamStatus::Playing, StreamStatus::Reconnecting, StreamStatus::Playing]
+        );
+        assert!(titles.iter().any(|title| title == "Artist - Title"));
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::Mutex;
+
+use audiopus::coder::Decoder as LibOpusDecoder;
+use audiopus::packet::Packet as OpusPacket;
+use audiopus::{Channels as OpusChannels, MutSignals, SampleRate};
+use symphonia::core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef, Signal, SignalSpec};
+use symphonia::core::codecs::{
+    CodecDescriptor, CodecParameters, CodecRegistry, Decoder, DecoderOptions, FinalizeResult, CODEC_TYPE_OPUS,
+};
+use symphonia::core::errors::{decode_error, unsupported_error, Result};
+use symphonia::core::formats::Packet;
+use symphonia::core::support_codec;
+
+// opus always decodes at 48khz, whatever the file was encoded from
+const OPUS_SAMPLE_RATE: u32 = 48_000;
+// the longest packet opus allows is 120ms
+const MAX_FRAMES_PER_PACKET: usize = 5760;
+
+lazy_static::lazy_static! {
+    // symphonia's own codecs plus opus, everything that opens a file for decoding goes through this
+    static ref CODECS: CodecRegistry = {
+        let mut registry = CodecRegistry::new();
+        symphonia::default::register_enabled_codecs(&mut registry);
+        registry.register_all::<OpusDecoder>();
+        registry
+    };
+}
+
+pub fn codecs() -> &'static CodecRegistry {
+    &CODECS
+}
+
+// symphonia only demuxes opus, the packets are decoded by libopus
+pub struct OpusDecoder {
+    params: CodecParameters,
+    // libopus' decoder is only Send, symphonia wants Sync as well
+    decoder: Mutex<LibOpusDecoder>,
+    channels: OpusChannels,
+    interleaved: Vec<f32>,
+    buffer: AudioBuffer<f32>,
+}
+
+fn new_decoder(channels: OpusChannels) -> Result<LibOpusDecoder> {
+    LibOpusDecoder::new(SampleRate::Hz48000, channels).or_else(|_| unsupported_error("opus: unable to create the decoder"))
+}
+
+impl Decoder for OpusDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        let Some(spec_channels) = params.channels else {
+            return unsupported_error("opus: unknown number of channels");
+        };
+
+        let channels = match spec_channels.count() {
+            1 => OpusChannels::Mono,
+            2 => OpusChannels::Stereo,
+            _ => return unsupported_error("opus: only mono and stereo are supported"),
+        };
+
+        Ok(Self {
+            params: params.clone(),
+            decoder: Mutex::new(new_decoder(channels)?),
+            channels,
+            interleaved: vec![0.0; MAX_FRAMES_PER_PACKET * spec_channels.count()],
+            buffer: AudioBuffer::new(MAX_FRAMES_PER_PACKET as u64, SignalSpec::new(OPUS_SAMPLE_RATE, spec_channels)),
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_OPUS, "opus", "Opus")]
+    }
+
+    // after a seek the old state would bleed into the new position
+    fn reset(&mut self) {
+        match new_decoder(self.channels) {
+            Ok(decoder) => self.decoder = Mutex::new(decoder),
+            Err(e) => println!("error: {:?}", e),
+        }
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        self.buffer.clear();
+
+        let num_frames = {
+            let Ok(input) = OpusPacket::try_from(&packet.data[..]) else {
+                return decode_error("opus: empty packet");
+            };
+            let Ok(output) = MutSignals::try_from(&mut self.interleaved[..]) else {
+                return decode_error("opus: no room to decode into");
+            };
+
+            match self.decoder.get_mut().unwrap().decode_float(Some(input), output, false) {
+                Ok(num_frames) => num_frames,
+                Err(_) => return decode_error("opus: invalid packet"),
+            }
+        };
+
+        let num_channels = self.buffer.spec().channels.count();
+        self.buffer.render_reserved(Some(num_frames));
+
+        for channel in 0..num_channels {
+            let samples = self.interleaved.iter().skip(channel).step_by(num_channels);
+
+            for (sample, decoded) in self.buffer.chan_mut(channel).iter_mut().zip(samples) {
+                *sample = *decoded;
+            }
+        }
+
+        // the pre-skip is what the encoder put in front of the audio, it's never meant to be heard. the ogg reader
+        // only reports it, packet timestamps start counting at the first decoded frame, pre-skip included
+        let pre_skip = u64::from(self.params.delay.unwrap_or(0)).saturating_sub(packet.ts).min(num_frames as u64) as usize;
+
+        // trim_start and trim_end are only set when the reader does gapless, and may cover the pre-skip already
+        self.buffer.trim((packet.trim_start as usize).max(pre_skip), packet.trim_end as usize);
+
+        Ok(self.buffer.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        FinalizeResult::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buffer.as_audio_buffer_ref()
+    }
+}
+
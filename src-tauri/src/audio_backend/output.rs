@@ -9,7 +9,7 @@ use crate::event_handler::{GuiToProcessMsg, ProcessToGuiMsg};
 pub fn spawn_cpal_stream(
     to_gui_tx: Sender<ProcessToGuiMsg>,
     from_gui_rx: Receiver<GuiToProcessMsg>
-) -> cpal::Stream {
+) -> (cpal::Stream, u32) {
     let host = cpal::default_host();
 
     let device = host
@@ -24,12 +24,12 @@ pub fn spawn_cpal_stream(
         buffer_size: cpal::BufferSize::Default,
     };
 
-    let mut process = Process::new(to_gui_tx, from_gui_rx);
+    let mut process = Process::new(to_gui_tx, from_gui_rx, sample_rate.0);
     
     let stream = device
         .build_output_stream(
             &config, 
-            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| process.process(data),
+            move |data: &mut [f32], info: &cpal::OutputCallbackInfo| process.process(data, info),
             move |err| {
                 eprintln!("{}", err);
             },
@@ -39,5 +39,5 @@ pub fn spawn_cpal_stream(
 
     stream.play().unwrap();
 
-    stream
+    (stream, sample_rate.0)
 }
\ No newline at end of file
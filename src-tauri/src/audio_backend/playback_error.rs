@@ -0,0 +1,126 @@
+use creek::read::{FatalReadError, ReadError};
+use creek::Decoder;
+use serde::{Deserialize, Serialize};
+
+use crate::library::TrackId;
+use crate::util::lmdb::settings::{get_setting, store_setting};
+use super::track_decoder::TrackDecoder;
+
+const ERROR_POLICY_SETTING: &str = "playback-error-policy";
+
+pub type StreamReadError = ReadError<<TrackDecoder as Decoder>::FatalError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackErrorKind {
+    // the file could not be opened or probed at all
+    Open,
+    // the decoder hit data it can't make sense of, the stream is dead
+    Decode,
+    // the io server behind the stream went away
+    StreamClosed,
+    // the file ended before the track did, e.g. a cue sheet that runs past a shortened image.
+    // that's the end of the track, not something to retry
+    EndOfFile,
+    // the stream was asked for something it can't do (a cache slot it doesn't have, a buffer of the wrong
+    // layout). it won't get better on its own, a fresh stream might
+    Misuse,
+    // the io server is busy, the next cycle gets another go
+    Transient,
+}
+
+impl PlaybackErrorKind {
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self, PlaybackErrorKind::Transient | PlaybackErrorKind::EndOfFile)
+    }
+}
+
+pub fn classify_read_error(e: &StreamReadError) -> PlaybackErrorKind {
+    match e {
+        ReadError::FatalError(FatalReadError::DecoderError(_)) => PlaybackErrorKind::Decode,
+        ReadError::FatalError(FatalReadError::StreamClosed) => PlaybackErrorKind::StreamClosed,
+        ReadError::EndOfFile => PlaybackErrorKind::EndOfFile,
+        ReadError::CacheIndexOutOfRange { .. } | ReadError::InvalidBuffer => PlaybackErrorKind::Misuse,
+        ReadError::IOServerChannelFull => PlaybackErrorKind::Transient,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorAction {
+    Retry,
+    Skip,
+    Stop,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ErrorPolicy {
+    // how often the same track is reopened (at the position it failed) before giving up on it
+    pub max_retries: u32,
+    pub skip_on_error: bool,
+}
+
+impl Default for ErrorPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 1,
+            skip_on_error: true,
+        }
+    }
+}
+
+impl ErrorPolicy {
+    pub fn load() -> Self {
+        match get_setting::<ErrorPolicy>(ERROR_POLICY_SETTING) {
+            Ok(Some(policy)) => policy,
+            Ok(None) => ErrorPolicy::default(),
+            Err(e) => {
+                println!("error: {:?}", e);
+                ErrorPolicy::default()
+            }
+        }
+    }
+
+    pub fn action_for(&self, kind: PlaybackErrorKind, attempt: u32) -> ErrorAction {
+        // the track is over, whatever the policy says about errors
+        if kind == PlaybackErrorKind::EndOfFile {
+            return ErrorAction::Skip;
+        }
+
+        // reopening a file that can't even be opened won't help
+        if kind != PlaybackErrorKind::Open && attempt <= self.max_retries {
+            ErrorAction::Retry
+        } else if self.skip_on_error {
+            ErrorAction::Skip
+        } else {
+            ErrorAction::Stop
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackErrorPayload {
+    pub kind: PlaybackErrorKind,
+    pub message: String,
+    pub path: Option<String>,
+    pub track_id: TrackId,
+    pub attempt: u32,
+    pub action: ErrorAction,
+}
+
+#[tauri::command]
+pub fn get_error_policy() -> Result<ErrorPolicy, String> {
+    Ok(ErrorPolicy::load())
+}
+
+#[tauri::command]
+pub fn set_error_policy(max_retries: u32, skip_on_error: bool) -> Result<(), String> {
+    let policy = ErrorPolicy {
+        max_retries,
+        skip_on_error,
+    };
+
+    store_setting(ERROR_POLICY_SETTING, &policy).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
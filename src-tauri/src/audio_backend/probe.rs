@@ -0,0 +1,120 @@
+use std::fs::File;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use super::opus::codecs;
+
+// extensions we expect to be audio, anything in here that fails to probe ends up in the report
+pub const AUDIO_EXTENSIONS: &[&str] = &[
+    "flac", "mp3", "wav", "wave", "aif", "aiff", "aifc", "ogg", "oga", "opus",
+    "m4a", "m4b", "mp4", "aac", "alac", "mka", "mkv", "webm", "caf",
+];
+
+// stuff that commonly lives next to music and is never worth opening
+const NON_AUDIO_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "bmp", "webp", "txt", "nfo", "log", "cue",
+    "m3u", "m3u8", "pls", "pdf", "db", "ini", "sfv", "md5", "accurip", "json", "xml",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnsupportedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+pub enum ProbeOutcome {
+    Playable { codec: String, sample_rate: Option<u32> },
+    Unsupported(String),
+    NotAudio,
+}
+
+fn extension_of(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase())
+}
+
+pub fn has_audio_extension(path: &Path) -> bool {
+    extension_of(path).is_some_and(|extension| AUDIO_EXTENSIONS.contains(&extension.as_str()))
+}
+
+pub fn should_probe(path: &Path) -> bool {
+    match extension_of(path) {
+        Some(extension) => !NON_AUDIO_EXTENSIONS.contains(&extension.as_str()),
+        None => true,
+    }
+}
+
+// does the same checks creek does when opening a stream, so anything Playable here
+// can actually be handed to ReadDiskStream later
+pub fn probe_file(path: &Path) -> ProbeOutcome {
+    let expected_audio = has_audio_extension(path);
+
+    let not_playable = |reason: String| {
+        if expected_audio {
+            ProbeOutcome::Unsupported(reason)
+        } else {
+            ProbeOutcome::NotAudio
+        }
+    };
+
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => return not_playable(format!("failed to open file: {}", e)),
+    };
+
+    let mut hint = Hint::new();
+
+    if let Some(extension) = extension_of(path) {
+        hint.with_extension(&extension);
+    }
+
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let probed = match symphonia::default::get_probe().format(
+        &hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default()
+    ) {
+        Ok(probed) => probed,
+        Err(e) => return not_playable(format!("unrecognized container: {}", e)),
+    };
+
+    // past this point the content is definitely some kind of media, so always report failures
+    let track = match probed.format.default_track() {
+        Some(track) if track.codec_params.codec != CODEC_TYPE_NULL => track,
+        _ => return ProbeOutcome::Unsupported("no audio track".to_string()),
+    };
+
+    let params = &track.codec_params;
+
+    if params.n_frames.is_none() {
+        return ProbeOutcome::Unsupported("stream does not report its length".to_string());
+    }
+
+    if let Some(channels) = params.channels {
+        if channels.count() > 2 {
+            return ProbeOutcome::Unsupported(format!("{} channels, only mono and stereo are supported", channels.count()));
+        }
+    }
+
+    let codecs = codecs();
+
+    if let Err(e) = codecs.make(params, &DecoderOptions::default()) {
+        return ProbeOutcome::Unsupported(format!("no decoder for codec {}: {}", params.codec, e));
+    }
+
+    let codec = codecs
+        .get_codec(params.codec)
+        .map(|descriptor| descriptor.short_name.to_string())
+        .unwrap_or_else(|| params.codec.to_string());
+
+    ProbeOutcome::Playable { codec, sample_rate: params.sample_rate }
+}
@@ -1,48 +1,96 @@
 
 
 
-use creek::read::ReadError;
-use creek::{Decoder, ReadDiskStream, SeekMode, SymphoniaDecoder};
+use creek::read::ReadData;
+use creek::{ReadDiskStream, SeekMode};
 
 use std::sync::mpsc::{Receiver, Sender};
+use std::time::Duration;
+
+use serde::Serialize;
 
 use crate::event_handler::{GuiToProcessMsg, ProcessToGuiMsg};
+use crate::library::TrackId;
+use super::network_stream::NetworkStream;
+use super::track_decoder::TrackDecoder;
+use super::silence::SilenceMode;
+use super::crossfeed::Crossfeed;
+use super::convolution::ConvolutionStage;
+use super::effects::chain::EffectChain;
+use super::playback_error::{classify_read_error, PlaybackErrorKind, StreamReadError};
+use super::transport::{frames_to_secs, secs_to_frames, TRANSPORT};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum PlaybackState {
+    Paused = 0, 
+    Playing = 1
+}
 
+// the track right after the current one when both are cut from the same file (cue sheets),
+// so we can keep reading instead of reopening the stream and leaving a gap
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub enum PlaybackState {
-    Paused, 
-    Playing
+pub struct GaplessNext {
+    pub track: TrackId,
+    pub start: usize,
+    pub end: Option<usize>,
 }
 
 pub struct Process {
-    read_disk_stream: Option<Box<ReadDiskStream<SymphoniaDecoder>>>,
+    read_disk_stream: Option<Box<ReadDiskStream<TrackDecoder>>>,
+    // set instead of read_disk_stream while a radio stream is playing
+    network_stream: Option<Box<NetworkStream>>,
 
     to_gui_tx: Sender<ProcessToGuiMsg>,
     from_gui_rx: Receiver<GuiToProcessMsg>,
-    
+
+    // rate the device runs at, which is what the dsp below has to be set up for
+    output_sample_rate: u32,
 
     playback_state: PlaybackState,
     repeat_state: bool,
     had_cache_miss_last_cycle: bool,
 
+    // the region of the file that belongs to the current track, the whole file unless it came from a cue sheet.
+    // with repeat on we wrap around inside it, otherwise playback ends at loop_end
     loop_start: usize,
     loop_end: usize,
 
-    index: usize,
+    track: TrackId,
+    gapless_next: Option<GaplessNext>,
 
-    fatal_error: bool
+    // time between the callback and the moment its first frame reaches the speakers
+    output_latency: Duration,
+    heard_pos: usize,
+
+    silence_mode: SilenceMode,
+    speed_factor: usize,
+    // (start, end) frames of the file to skip or speed through, already trimmed by the keep margin
+    silence_regions: Vec<(usize, usize)>,
+    // end of the next silent stretch, kept ready in cache 2 so skipping doesn't run into a cache miss
+    cached_skip_target: Option<usize>,
+    skipped_frames: usize,
+
+    effects: EffectChain,
+    crossfeed: Option<Crossfeed>,
+    convolution: ConvolutionStage,
 }
 
 impl Process {
     pub fn new(
         to_gui_tx: Sender<ProcessToGuiMsg>,
         from_gui_rx: Receiver<GuiToProcessMsg>,
+        output_sample_rate: u32,
     ) -> Self {
         Self {
             read_disk_stream: None,
+            network_stream: None,
             to_gui_tx,
             from_gui_rx,
 
+            output_sample_rate,
+
             playback_state: PlaybackState::Paused,
             repeat_state: false,
             had_cache_miss_last_cycle: false,
@@ -50,44 +98,109 @@ impl Process {
             loop_start: 0,
             loop_end: 0,
 
-            index: 0,
+            track: TrackId::default(),
+            gapless_next: None,
+
+            output_latency: Duration::ZERO,
+            heard_pos: 0,
 
-            fatal_error: false,
-            
+            silence_mode: SilenceMode::Off,
+            speed_factor: 1,
+            silence_regions: Vec::new(),
+            cached_skip_target: None,
+            skipped_frames: 0,
+
+            effects: EffectChain::default(),
+            crossfeed: None,
+            convolution: ConvolutionStage::default(),
         }
     }
 
-    pub fn process(&mut self, data: &mut [f32]) {
-        if self.fatal_error {
-            silence(data);
-            return;
+    pub fn process(&mut self, data: &mut [f32], info: &cpal::OutputCallbackInfo) {
+        let timestamp = info.timestamp();
+
+        if let Some(latency) = timestamp.playback.duration_since(&timestamp.callback) {
+            // the convolver holds back one partition on top of what the device buffers, and effects may hold back some more
+            let dsp_latency = Duration::from_secs_f64(frames_to_secs(
+                self.convolution.latency_frames() + self.effects.latency_frames(),
+                self.output_sample_rate
+            ));
+
+            self.output_latency = latency + dsp_latency;
+            TRANSPORT.set_output_latency(self.output_latency);
         }
 
         if let Err(e) = self.try_process(data) {
-            if matches!(e, ReadError::FatalError(_)) {
-                self.fatal_error = true;
+            let kind = classify_read_error(&e);
+
+            // reading past the end of the file ends the track like reaching its end frame does
+            if kind == PlaybackErrorKind::EndOfFile {
+                if let Some(finished_stream) = self.read_disk_stream.take() {
+                    let _ = self.to_gui_tx.send(ProcessToGuiMsg::DropOldStream(finished_stream));
+                    let _ = self.to_gui_tx.send(ProcessToGuiMsg::DropAndNext(self.track));
+                }
+            }
+
+            // a fatal error kills the stream for good, so hand it back and let the gui side
+            // decide whether to reopen it or move on instead of playing silence forever
+            if kind.is_fatal() {
+                if let Some(dead_stream) = self.read_disk_stream.take() {
+                    let _ = self.to_gui_tx.send(ProcessToGuiMsg::DropOldStream(dead_stream));
+                    let _ = self.to_gui_tx.send(ProcessToGuiMsg::PlaybackError {
+                        track: self.track,
+                        kind,
+                        message: e.to_string(),
+                        pos: self.heard_pos,
+                    });
+                }
+
+                self.playback_state = PlaybackState::Paused;
+                TRANSPORT.set_playback_state(PlaybackState::Paused);
             }
 
             println!("{:?}", e);
             silence(data);
         }
 
+        self.effects.process(data);
+
+        if let Some(crossfeed) = &mut self.crossfeed {
+            crossfeed.process(data);
+        }
+
+        if let Some(faded_out) = self.convolution.process(data) {
+            let _ = self.to_gui_tx.send(ProcessToGuiMsg::DropConvolver(faded_out));
+        }
+
     }
 
     fn try_process(
         &mut self,
         mut data: &mut [f32],
-    ) -> Result<(), ReadError<<SymphoniaDecoder as Decoder>::FatalError>> {
+    ) -> Result<(), StreamReadError> {
         // in the tauri version, maybe you shouldnt change how this works, but instead implement a middlewear which passes the calls from rtrb to tauri so it can return it to the front end and back
 
         while let Ok(msg) = self.from_gui_rx.try_recv() {
             match msg {
-                GuiToProcessMsg::UseStream((read_disk_stream, index)) => {
+                GuiToProcessMsg::UseStream((read_disk_stream, track)) => {
                     self.playback_state = PlaybackState::Paused;
                     self.loop_start = 0;
                     self.loop_end = 0;
 
-                    self.index = index;
+                    self.track = track;
+                    self.gapless_next = None;
+
+                    self.heard_pos = read_disk_stream.playhead();
+
+                    // the analysis of the new file arrives later through SetSilenceRegions
+                    self.silence_regions.clear();
+                    self.cached_skip_target = None;
+                    self.skipped_frames = 0;
+                    TRANSPORT.set_skipped_frames(0);
+
+                    TRANSPORT.set_total_frames(read_disk_stream.info().num_frames);
+                    TRANSPORT.set_sample_rate(stream_sample_rate(&read_disk_stream));
+                    TRANSPORT.set_pos_frames(0);
 
                     if let Some(old_stream) = self.read_disk_stream.take() {
                         let _= self
@@ -95,14 +208,54 @@ impl Process {
                             .send(ProcessToGuiMsg::DropOldStream(old_stream));
                     }
 
+                    if let Some(old_network_stream) = self.network_stream.take() {
+                        let _ = self.to_gui_tx.send(ProcessToGuiMsg::DropNetworkStream(old_network_stream));
+                    }
+
+                    self.effects.reset();
+
                     println!("set new stream!");
 
                     self.read_disk_stream = Some(read_disk_stream);
                 }
+                GuiToProcessMsg::UseNetworkStream(network_stream) => {
+                    self.playback_state = PlaybackState::Paused;
+                    self.loop_start = 0;
+                    self.loop_end = 0;
+                    self.gapless_next = None;
+                    self.heard_pos = 0;
+
+                    self.silence_regions.clear();
+                    self.cached_skip_target = None;
+                    self.skipped_frames = 0;
+                    TRANSPORT.set_skipped_frames(0);
+
+                    // a live stream has no length, the position is just how long it has been playing
+                    TRANSPORT.set_total_frames(0);
+                    TRANSPORT.set_sample_rate(network_stream.sample_rate());
+                    TRANSPORT.set_pos_frames(0);
+
+                    if let Some(old_stream) = self.read_disk_stream.take() {
+                        let _ = self.to_gui_tx.send(ProcessToGuiMsg::DropOldStream(old_stream));
+                    }
+
+                    if let Some(old_network_stream) = self.network_stream.take() {
+                        let _ = self.to_gui_tx.send(ProcessToGuiMsg::DropNetworkStream(old_network_stream));
+                    }
+
+                    self.effects.reset();
+
+                    self.network_stream = Some(network_stream);
+                }
                 GuiToProcessMsg::SetLoop { start, end } => {
+                    // a cue index past the end of a shortened image would put the start after the end
+                    let start = start.min(end);
+
                     self.loop_start = start;
                     self.loop_end = end;
 
+                    TRANSPORT.set_total_frames(end.saturating_sub(start));
+
                     if start != 0 {
                         if let Some(read_disk_stream) = &mut self.read_disk_stream {
                             read_disk_stream.cache(1, start)?;
@@ -125,12 +278,95 @@ impl Process {
                         read_disk_stream.seek(self.loop_start, SeekMode::Auto)?;
                     }
                 }
+                // positions coming from the gui are relative to the start of the track
                 GuiToProcessMsg::SeekTo(pos) => {
                     if let Some(read_disk_stream) = &mut self.read_disk_stream {
+                        let pos = (self.loop_start + pos).min(self.loop_end).max(self.loop_start);
+
                         read_disk_stream.seek(pos, SeekMode::Auto)?;
                     }
                 }
+                GuiToProcessMsg::SeekToSecs(secs) => {
+                    if let Some(read_disk_stream) = &mut self.read_disk_stream {
+                        let pos = self.loop_start + secs_to_frames(secs, stream_sample_rate(read_disk_stream));
+
+                        read_disk_stream.seek(pos.min(self.loop_end).max(self.loop_start), SeekMode::Auto)?;
+                    }
+                }
+                GuiToProcessMsg::SeekBy(delta_secs) => {
+                    if let Some(read_disk_stream) = &mut self.read_disk_stream {
+                        let sample_rate = stream_sample_rate(read_disk_stream);
+
+                        // relative to what the listener hears, not to where the decoder already is
+                        let heard_secs = frames_to_secs(self.heard_pos, sample_rate);
+                        let pos = secs_to_frames(heard_secs + delta_secs, sample_rate);
+
+                        read_disk_stream.seek(pos.max(self.loop_start).min(self.loop_end), SeekMode::Auto)?;
+                    }
+                }
+                GuiToProcessMsg::QueueGapless(next) => {
+                    self.gapless_next = next;
+                }
+                GuiToProcessMsg::SetSilenceMode { mode, speed_factor } => {
+                    self.silence_mode = mode;
+                    self.speed_factor = speed_factor.max(1);
+                    self.cached_skip_target = None;
+                }
+                GuiToProcessMsg::SetCrossfeed(preset) => {
+                    self.crossfeed = preset.map(|preset| Crossfeed::new(preset, self.output_sample_rate));
+                }
+                GuiToProcessMsg::SetConvolver(convolver) => {
+                    if let Some(interrupted) = self.convolution.swap(convolver) {
+                        let _ = self.to_gui_tx.send(ProcessToGuiMsg::DropConvolver(interrupted));
+                    }
+                }
+                GuiToProcessMsg::EffectChain(msg) => {
+                    if let Some(removed) = self.effects.handle(msg) {
+                        let _ = self.to_gui_tx.send(ProcessToGuiMsg::DropEffect(removed));
+                    }
+                }
+                GuiToProcessMsg::SetSilenceRegions { track, regions } => {
+                    // the analysis can take long enough for the track to have changed in the meantime
+                    // whichever vec isn't kept goes back to be freed, not here
+                    let unused = if track == self.track {
+                        self.cached_skip_target = None;
+                        std::mem::replace(&mut self.silence_regions, regions)
+                    } else {
+                        regions
+                    };
+
+                    let _ = self.to_gui_tx.send(ProcessToGuiMsg::DropSilenceRegions(unused));
+                }
+            }
+        }
+
+        if let Some(network_stream) = &mut self.network_stream {
+            let sample_rate = network_stream.sample_rate();
+
+            TRANSPORT.set_sample_rate(sample_rate);
+            TRANSPORT.set_playback_state(self.playback_state);
+
+            if let PlaybackState::Paused = self.playback_state {
+                silence(data);
+                return Ok(());
             }
+
+            let frames = network_stream.read(data);
+            silence(&mut data[frames * 2..]);
+
+            let latency_frames = secs_to_frames(self.output_latency.as_secs_f64(), sample_rate);
+
+            self.heard_pos = network_stream.played_frames().saturating_sub(latency_frames);
+            TRANSPORT.set_pos_frames(self.heard_pos);
+            TRANSPORT.set_buffering(network_stream.is_buffering());
+
+            if self.had_cache_miss_last_cycle {
+                fade_in(data);
+            }
+
+            self.had_cache_miss_last_cycle = network_stream.is_buffering();
+
+            return Ok(());
         }
 
         let mut cache_missed_this_cycle = false; 
@@ -139,94 +375,153 @@ impl Process {
         if let Some(read_disk_stream) = &mut self.read_disk_stream {
             if !read_disk_stream.is_ready()? {
                 cache_missed_this_cycle = true;
-
-                let _ = self.to_gui_tx.send(ProcessToGuiMsg::Buffering(true));
-            } else {
-                let _ = self.to_gui_tx.send(ProcessToGuiMsg::Buffering(false));
             }
 
+            TRANSPORT.set_buffering(cache_missed_this_cycle);
+            TRANSPORT.set_playback_state(self.playback_state);
+
             if let PlaybackState::Paused = self.playback_state {
+                // nothing is queued up in the device while paused, so there is no latency to make up for
+                self.heard_pos = read_disk_stream.playhead();
+                TRANSPORT.set_pos_frames(self.heard_pos.saturating_sub(self.loop_start));
                 silence(data);
                 return Ok(());
             }
 
             let num_frames = read_disk_stream.info().num_frames;
 
-            let _ = self.to_gui_tx.send(ProcessToGuiMsg::TotalFrames(num_frames));
+            let latency_frames = secs_to_frames(
+                self.output_latency.as_secs_f64(),
+                stream_sample_rate(read_disk_stream)
+            );
+            let buffer_start = read_disk_stream.playhead();
 
             let num_channels = usize::from(read_disk_stream.info().num_channels);
 
+            if self.silence_mode == SilenceMode::Skip {
+                let next_silence_end = self.silence_regions
+                    .iter()
+                    .find(|(_, end)| *end > buffer_start)
+                    .map(|(_, end)| *end);
+
+                if let Some(next_silence_end) = next_silence_end {
+                    if self.cached_skip_target != Some(next_silence_end) {
+                        read_disk_stream.cache(2, next_silence_end)?;
+                        self.cached_skip_target = Some(next_silence_end);
+                    }
+                }
+            }
+
             while data.len() >= num_channels {
                 let read_frames = data.len() / 2;
 
                 let mut playhead = read_disk_stream.playhead();
 
-                let loop_end =if playhead < self.loop_end {
+                // 0 until the gui sets the region of the track, the whole file until then
+                let loop_end = if self.loop_end > 0 {
                     self.loop_end
                 } else {
                     num_frames
                 };
 
-                let read_data = read_disk_stream.read(read_frames)?;
-
-                playhead += read_data.num_frames();
+                // seeks and silence skipping can leave the playhead right on the end of the track,
+                // that is the end of it just like reading up to there
                 if playhead >= loop_end {
-                    let to_end_of_loop = read_data.num_frames() - (playhead - loop_end);
+                    if self.repeat_state && self.loop_start < loop_end {
+                        read_disk_stream.seek(self.loop_start, SeekMode::Auto)?;
+                        continue;
+                    }
 
-                    if read_data.num_channels() == 1 {
-                        let ch = read_data.read_channel(0);
+                    let hands_off = playhead == loop_end
+                        && !self.repeat_state
+                        && self.gapless_next.is_some_and(|next| next.start == loop_end);
 
-                        for i in 0..to_end_of_loop {
-                            data[i * 2] = ch[i];
-                            data[i * 2 + 1] = ch[i];
+                    if !hands_off {
+                        drop_stream = true;
+                        break;
+                    }
+                }
+
+                let silence_end = self.silence_regions
+                    .iter()
+                    .find(|(start, end)| *start <= playhead && playhead < *end)
+                    .map(|(_, end)| (*end).min(loop_end))
+                    .filter(|end| *end > playhead);
+
+                if let Some(silence_end) = silence_end {
+                    match self.silence_mode {
+                        SilenceMode::Skip => {
+                            read_disk_stream.seek(silence_end, SeekMode::Auto)?;
+                            self.skipped_frames += silence_end - playhead;
+                            continue;
                         }
-                    } else if read_data.num_channels() == 2{
-                        let ch1 = read_data.read_channel(0);
-                        let ch2 = read_data.read_channel(1);
+                        SilenceMode::SpeedUp => {
+                            let to_read = (read_frames * self.speed_factor).min(silence_end - playhead);
+                            let read_data = read_disk_stream.read(to_read)?;
+
+                            let written = write_frames_decimated(&read_data, self.speed_factor, data);
+                            self.skipped_frames += read_data.num_frames() - written;
 
-                        for i in 0..to_end_of_loop {
-                            data[i * 2] = ch1[i];
-                            data[i * 2 + 1] = ch2[i];
+                            data = &mut data[written * 2..];
+                            continue;
                         }
+                        SilenceMode::Off => {}
                     }
+                }
 
-                    read_disk_stream.seek(self.loop_start, SeekMode::Auto)?;
+                let read_data = read_disk_stream.read(read_frames)?;
 
+                playhead += read_data.num_frames();
+                if playhead >= loop_end {
+                    let to_end_of_loop = read_data.num_frames().saturating_sub(playhead - loop_end);
+
+                    write_frames(&read_data, 0, to_end_of_loop, data);
                     data = &mut data[to_end_of_loop * 2.. ];
-                } else {
-                    if read_data.num_channels() == 1 {
-                        let ch = read_data.read_channel(0);
 
-                        for i in 0..read_data.num_frames() {
-                            data[i * 2] = ch[i];
-                            data[i * 2 + 1] = ch[i];
-                        }
-                    } else if read_data.num_channels() == 2 {
-                        let ch1 = read_data.read_channel(0);
-                        let ch2 = read_data.read_channel(1);
+                    if self.repeat_state {
+                        read_disk_stream.seek(self.loop_start, SeekMode::Auto)?;
+                        continue;
+                    }
+
+                    match self.gapless_next.take() {
+                        // the next track picks up exactly where this one stops, so the rest of
+                        // what we just read already belongs to it
+                        Some(next) if next.start == loop_end => {
+                            self.track = next.track;
+                            self.loop_start = next.start;
+                            self.loop_end = next.end.unwrap_or(num_frames);
+
+                            TRANSPORT.set_total_frames(self.loop_end - self.loop_start);
+                            self.skipped_frames = 0;
+                            let _ = self.to_gui_tx.send(ProcessToGuiMsg::TrackAdvanced(next.track));
+
+                            let remaining = read_data.num_frames() - to_end_of_loop;
 
-                        for i in 0..read_data.num_frames() {
-                            data[i * 2] = ch1[i];
-                            data[i * 2 + 1] = ch2[i];
+                            write_frames(&read_data, to_end_of_loop, remaining, data);
+                            data = &mut data[remaining * 2..];
+                        }
+                        _ => {
+                            drop_stream = true;
+                            break;
                         }
                     }
+                } else {
+                    write_frames(&read_data, 0, read_data.num_frames(), data);
 
                     data = &mut data[read_data.num_frames() * 2..];
                 }
             }
 
-            if read_disk_stream.playhead() >= num_frames - 500 {
-                println!("end of file reached.");
-                if !self.repeat_state {
-                    drop_stream = true;
-                }
+            if drop_stream {
+                println!("end of track reached.");
+                silence(data);
             }
 
-
-            let _ =  self
-                .to_gui_tx
-                .send(ProcessToGuiMsg::PlaybackPos(read_disk_stream.playhead()));
+            self.heard_pos = buffer_start.saturating_sub(latency_frames);
+            TRANSPORT.set_pos_frames(self.heard_pos.saturating_sub(self.loop_start));
+            TRANSPORT.set_skipped_frames(self.skipped_frames);
         } else {
+            TRANSPORT.set_playback_state(PlaybackState::Paused);
             silence(data);
         }
 
@@ -235,15 +530,12 @@ impl Process {
         if drop_stream {
             if let Some(dropped_stream) = self.read_disk_stream.take() {
                 let _ = self.to_gui_tx.send(ProcessToGuiMsg::DropOldStream(dropped_stream));
-                let _ = self.to_gui_tx.send(ProcessToGuiMsg::DropAndNext(self.index));
+                let _ = self.to_gui_tx.send(ProcessToGuiMsg::DropAndNext(self.track));
             }
         }
 
         if self.had_cache_miss_last_cycle {
-            let buffer_size = data.len() as f32;
-            for (i, sample) in data.iter_mut().enumerate() {
-                *sample *= i as f32 / buffer_size;
-            }
+            fade_in(data);
         }
 
         self.had_cache_miss_last_cycle = cache_missed_this_cycle;
@@ -264,6 +556,52 @@ impl Drop for Process {
     }
 }
 
+// copies `frames` frames starting at `offset` into the interleaved stereo output, mono gets duplicated to both sides
+fn write_frames(read_data: &ReadData<f32>, offset: usize, frames: usize, data: &mut [f32]) {
+    if read_data.num_channels() == 1 {
+        let ch = read_data.read_channel(0);
+
+        for i in 0..frames {
+            data[i * 2] = ch[offset + i];
+            data[i * 2 + 1] = ch[offset + i];
+        }
+    } else if read_data.num_channels() == 2 {
+        let ch1 = read_data.read_channel(0);
+        let ch2 = read_data.read_channel(1);
+
+        for i in 0..frames {
+            data[i * 2] = ch1[offset + i];
+            data[i * 2 + 1] = ch2[offset + i];
+        }
+    }
+}
+
+// like write_frames but only keeps every `step`th frame, returns how many frames were written
+fn write_frames_decimated(read_data: &ReadData<f32>, step: usize, data: &mut [f32]) -> usize {
+    let frames = read_data.num_frames().div_ceil(step);
+
+    let ch1 = read_data.read_channel(0);
+    let ch2 = if read_data.num_channels() > 1 { read_data.read_channel(1) } else { ch1 };
+
+    for i in 0..frames {
+        data[i * 2] = ch1[i * step];
+        data[i * 2 + 1] = ch2[i * step];
+    }
+
+    frames
+}
+
+fn stream_sample_rate(read_disk_stream: &ReadDiskStream<TrackDecoder>) -> u32 {
+    read_disk_stream.info().sample_rate.unwrap_or(0)
+}
+
+fn fade_in(data: &mut [f32]) {
+    let buffer_size = data.len() as f32;
+    for (i, sample) in data.iter_mut().enumerate() {
+        *sample *= i as f32 / buffer_size;
+    }
+}
+
 fn silence(data: &mut [f32]) {
     for sample in data.iter_mut() {
         *sample = 0.0;
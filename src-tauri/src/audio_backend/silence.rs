@@ -0,0 +1,316 @@
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::event_handler::CURRENT_SILENCE;
+use crate::util::hash::fnv1a_64;
+use crate::util::lmdb::records::{get_record, store_record};
+use crate::util::lmdb::settings::{get_setting, store_setting};
+use super::transport::{frames_to_secs, secs_to_frames};
+use super::opus::codecs;
+
+const SILENCE_SETTINGS: &str = "silence-settings";
+const SILENCE_TABLE: &str = "silence";
+
+// loudness is judged per window, short enough to not swallow the gap between two words
+const WINDOW_SECS: f64 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SilenceMode {
+    Off,
+    // jump straight to the end of every silent stretch
+    Skip,
+    // play silent stretches at `speed_factor` times the speed
+    SpeedUp,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SilenceSettings {
+    pub mode: SilenceMode,
+    // peak level below which a window counts as silent
+    pub threshold_db: f32,
+    // shorter pauses are part of the speech and are left alone
+    pub min_duration_secs: f64,
+    // how much of each silent stretch is still played at both ends, so cuts don't sound abrupt
+    pub keep_secs: f64,
+    pub speed_factor: usize,
+}
+
+impl Default for SilenceSettings {
+    fn default() -> Self {
+        Self {
+            mode: SilenceMode::Off,
+            threshold_db: -50.0,
+            min_duration_secs: 1.0,
+            keep_secs: 0.25,
+            speed_factor: 4,
+        }
+    }
+}
+
+impl SilenceSettings {
+    pub fn load() -> Self {
+        match get_setting::<SilenceSettings>(SILENCE_SETTINGS) {
+            Ok(Some(settings)) => settings,
+            Ok(None) => SilenceSettings::default(),
+            Err(e) => {
+                println!("error: {:?}", e);
+                SilenceSettings::default()
+            }
+        }
+    }
+
+    pub fn store(&self) -> Result<()> {
+        store_setting(SILENCE_SETTINGS, self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SilenceKind {
+    Leading,
+    Trailing,
+    Internal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SilenceRegion {
+    pub kind: SilenceKind,
+    // frames of the whole file, not of a cue track inside it
+    pub start_frame: usize,
+    pub end_frame: usize,
+    pub duration_secs: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SilenceAnalysis {
+    pub path: String,
+    pub sample_rate: u32,
+    pub total_frames: usize,
+    pub regions: Vec<SilenceRegion>,
+    pub total_silence_secs: f64,
+}
+
+// the settings that change the result are part of the key, and so is the file's mtime/size
+// so a re-encoded file gets analyzed again
+fn analysis_key(path: &Path, settings: &SilenceSettings) -> Result<String> {
+    let metadata = fs::metadata(path)?;
+
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs())
+        .unwrap_or(0);
+
+    let key = format!(
+        "{}#{}#{}#{}#{}",
+        path.to_string_lossy(),
+        modified,
+        metadata.len(),
+        settings.threshold_db,
+        settings.min_duration_secs
+    );
+
+    Ok(format!("{:016x}", fnv1a_64(key.as_bytes())))
+}
+
+// decodes the whole file once and finds every stretch that stays below the threshold for long enough
+pub fn analyze_silence(path: &Path, threshold_db: f32, min_duration_secs: f64) -> Result<SilenceAnalysis> {
+    let file = File::open(path)?;
+
+    let mut hint = Hint::new();
+
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default()
+    )?;
+
+    let mut format = probed.format;
+
+    let track = format
+        .default_track()
+        .filter(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| anyhow::anyhow!("{} does not contain an audio track", path.display()))?;
+
+    let track_id = track.id;
+    let sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+
+    let mut decoder = codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let threshold = 10f32.powf(threshold_db / 20.0);
+    let window_frames = secs_to_frames(WINDOW_SECS, sample_rate).max(1);
+    let min_frames = secs_to_frames(min_duration_secs, sample_rate);
+
+    let mut regions: Vec<(usize, usize)> = Vec::new();
+    let mut silence_start: Option<usize> = None;
+
+    let mut frame = 0;
+    let mut window_peak = 0f32;
+    let mut window_fill = 0;
+
+    let mut close_window = |window_start: usize, peak: f32| {
+        match (peak < threshold, silence_start) {
+            (true, None) => silence_start = Some(window_start),
+            (false, Some(start)) => {
+                if window_start - start >= min_frames {
+                    regions.push((start, window_start));
+                }
+                silence_start = None;
+            }
+            _ => {}
+        }
+    };
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(SymphoniaError::DecodeError(e)) => {
+                println!("error: {}", e);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let spec = *decoded.spec();
+        let num_channels = spec.channels.count().max(1);
+
+        let mut sample_buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        sample_buffer.copy_interleaved_ref(decoded);
+
+        for samples in sample_buffer.samples().chunks(num_channels) {
+            let peak = samples.iter().fold(0f32, |peak, sample| peak.max(sample.abs()));
+
+            window_peak = window_peak.max(peak);
+            window_fill += 1;
+            frame += 1;
+
+            if window_fill == window_frames {
+                close_window(frame - window_fill, window_peak);
+
+                window_peak = 0.0;
+                window_fill = 0;
+            }
+        }
+    }
+
+    if window_fill > 0 {
+        close_window(frame - window_fill, window_peak);
+    }
+
+    // still silent when the file ended
+    if let Some(start) = silence_start {
+        if frame - start >= min_frames {
+            regions.push((start, frame));
+        }
+    }
+
+    let regions: Vec<SilenceRegion> = regions
+        .into_iter()
+        .map(|(start_frame, end_frame)| SilenceRegion {
+            kind: if start_frame == 0 {
+                SilenceKind::Leading
+            } else if end_frame == frame {
+                SilenceKind::Trailing
+            } else {
+                SilenceKind::Internal
+            },
+            start_frame,
+            end_frame,
+            duration_secs: frames_to_secs(end_frame - start_frame, sample_rate),
+        })
+        .collect();
+
+    let total_silence_secs = regions.iter().map(|region| region.duration_secs).sum();
+
+    Ok(SilenceAnalysis {
+        path: path.to_string_lossy().to_string(),
+        sample_rate,
+        total_frames: frame,
+        regions,
+        total_silence_secs,
+    })
+}
+
+// decoding a whole podcast takes a while, so results are kept in lmdb
+pub fn load_or_analyze(path: &Path, settings: &SilenceSettings) -> Result<SilenceAnalysis> {
+    let key = analysis_key(path, settings)?;
+
+    if let Some(analysis) = get_record::<SilenceAnalysis>(SILENCE_TABLE, &key)? {
+        return Ok(analysis);
+    }
+
+    let analysis = analyze_silence(path, settings.threshold_db, settings.min_duration_secs)?;
+
+    store_record(SILENCE_TABLE, &key, &analysis)?;
+
+    Ok(analysis)
+}
+
+// the part of every region `Process` actually skips, with `keep_secs` left over at both ends
+pub fn playback_regions(analysis: &SilenceAnalysis, keep_secs: f64) -> Vec<(usize, usize)> {
+    let keep = secs_to_frames(keep_secs, analysis.sample_rate);
+
+    analysis
+        .regions
+        .iter()
+        .filter_map(|region| {
+            // nothing before the first sound or after the last one needs to be kept
+            let start = if region.kind == SilenceKind::Leading { 0 } else { region.start_frame + keep };
+            let end = if region.kind == SilenceKind::Trailing { region.end_frame } else { region.end_frame.saturating_sub(keep) };
+
+            (start < end).then_some((start, end))
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_silence_settings() -> Result<SilenceSettings, String> {
+    Ok(SilenceSettings::load())
+}
+
+// the mode itself is switched through the "silence_mode" event so it applies right away,
+// the rest takes effect with the next track
+#[tauri::command]
+pub fn set_silence_settings(settings: SilenceSettings) -> Result<(), String> {
+    if settings.speed_factor < 2 {
+        return Err("speed factor must be at least 2".to_string());
+    }
+
+    settings.store().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_silence_analysis() -> Result<Option<SilenceAnalysis>, String> {
+    let silence_guard = CURRENT_SILENCE.lock().unwrap();
+
+    Ok(silence_guard.clone())
+}
@@ -0,0 +1,217 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use creek::{DataBlock, Decoder, FileInfo, OpenError, SymphoniaDecoderInfo};
+use symphonia::core::audio::AudioBuffer;
+use symphonia::core::codecs::{Decoder as CodecDecoder, DecoderOptions};
+use symphonia::core::errors::Error;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::Time;
+
+use super::opus::codecs;
+
+// creek's SymphoniaDecoder, but the codec comes from our registry so opus files play as well
+pub struct TrackDecoder {
+    reader: Box<dyn FormatReader>,
+    decoder: Box<dyn CodecDecoder>,
+
+    decode_buffer: AudioBuffer<f32>,
+    decode_buffer_len: usize,
+    curr_decode_buffer_frame: usize,
+
+    num_frames: usize,
+    sample_rate: u32,
+    block_size: usize,
+
+    playhead_frame: usize,
+    reset_decode_buffer: bool,
+}
+
+fn frame_to_time(frame: u64, sample_rate: u32) -> Time {
+    // whole seconds and the rest separately, f64 loses frames on long files
+    let seconds = frame / u64::from(sample_rate);
+    let frac = (frame % u64::from(sample_rate)) as f64 / f64::from(sample_rate);
+
+    Time { seconds, frac }
+}
+
+impl TrackDecoder {
+    // the next decoded packet, decode errors only lose that packet. None at the end of the file
+    fn next_decoded(&mut self) -> Result<Option<()>, Error> {
+        loop {
+            let packet = match self.reader.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e),
+            };
+
+            match self.decoder.decode(&packet) {
+                Ok(decoded) => {
+                    self.decode_buffer_len = decoded.frames();
+
+                    if self.decode_buffer.capacity() < decoded.capacity() {
+                        self.decode_buffer = AudioBuffer::new(decoded.capacity() as u64, *decoded.spec());
+                    }
+
+                    decoded.convert(&mut self.decode_buffer);
+                    self.curr_decode_buffer_frame = 0;
+
+                    return Ok(Some(()));
+                }
+                Err(Error::DecodeError(e)) => println!("error: {}", e),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Decoder for TrackDecoder {
+    type T = f32;
+    type FileParams = SymphoniaDecoderInfo;
+    type OpenError = OpenError;
+    type FatalError = Error;
+    type AdditionalOpts = ();
+
+    const DEFAULT_BLOCK_SIZE: usize = 16384;
+    const DEFAULT_NUM_CACHE_BLOCKS: usize = 0;
+    const DEFAULT_NUM_LOOK_AHEAD_BLOCKS: usize = 8;
+
+    fn new(
+        file: PathBuf,
+        start_frame: usize,
+        block_size: usize,
+        _additional_opts: Self::AdditionalOpts,
+    ) -> Result<(Self, FileInfo<Self::FileParams>), Self::OpenError> {
+        let mut hint = Hint::new();
+
+        if let Some(extension) = file.extension().and_then(|extension| extension.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let source = MediaSourceStream::new(Box::new(File::open(file)?), Default::default());
+        let probed = symphonia::default::get_probe().format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())?;
+        let reader = probed.format;
+
+        let params = reader.default_track().ok_or(OpenError::NoDefaultTrack)?.codec_params.clone();
+        let num_frames = params.n_frames.ok_or(OpenError::NoNumFrames)? as usize;
+
+        let decoder = codecs().make(&params, &DecoderOptions::default())?;
+
+        let mut track_decoder = Self {
+            reader,
+            decoder,
+
+            decode_buffer: AudioBuffer::unused(),
+            decode_buffer_len: 0,
+            curr_decode_buffer_frame: 0,
+
+            num_frames,
+            // filled in below, once it's known for sure
+            sample_rate: 0,
+            block_size,
+
+            playhead_frame: 0,
+            reset_decode_buffer: false,
+        };
+
+        // mp4 and friends don't always say how many channels there are or the rate, the first packet does
+        if track_decoder.next_decoded()?.is_none() {
+            return Err(OpenError::NoNumChannels);
+        }
+
+        let decoded_spec = *track_decoder.decode_buffer.spec();
+
+        // positions are frames at this rate everywhere, a guessed one would play and seek at the wrong speed
+        let sample_rate = params
+            .sample_rate
+            .or(Some(decoded_spec.rate).filter(|&rate| rate > 0))
+            .ok_or(OpenError::Format(Error::Unsupported("the file doesn't say what its sample rate is")))?;
+        track_decoder.sample_rate = sample_rate;
+
+        if start_frame != 0 {
+            track_decoder.seek(start_frame)?;
+        }
+
+        let num_channels = params.channels.unwrap_or(decoded_spec.channels).count();
+        let metadata = track_decoder.reader.metadata().skip_to_latest().cloned();
+
+        let file_info = FileInfo {
+            params: SymphoniaDecoderInfo { codec_params: params, metadata },
+            num_frames,
+            num_channels: num_channels as u16,
+            sample_rate: Some(sample_rate),
+        };
+
+        Ok((track_decoder, file_info))
+    }
+
+    fn seek(&mut self, frame: usize) -> Result<(), Self::FatalError> {
+        if frame >= self.num_frames {
+            self.playhead_frame = self.num_frames;
+            return Ok(());
+        }
+
+        self.playhead_frame = frame;
+
+        self.reader.seek(
+            SeekMode::Accurate,
+            SeekTo::Time { time: frame_to_time(frame as u64, self.sample_rate), track_id: None },
+        )?;
+
+        self.decoder.reset();
+        self.reset_decode_buffer = true;
+        self.curr_decode_buffer_frame = 0;
+
+        Ok(())
+    }
+
+    fn decode(&mut self, data_block: &mut DataBlock<Self::T>) -> Result<(), Self::FatalError> {
+        if self.playhead_frame >= self.num_frames {
+            return Ok(());
+        }
+
+        let mut block_frame = 0;
+
+        while block_frame < self.block_size {
+            if self.reset_decode_buffer {
+                self.reset_decode_buffer = false;
+
+                if self.next_decoded()?.is_none() {
+                    self.playhead_frame = self.num_frames;
+                    return Ok(());
+                }
+            }
+
+            let num_frames = (self.block_size - block_frame).min(self.decode_buffer_len - self.curr_decode_buffer_frame);
+            let from = self.curr_decode_buffer_frame;
+
+            for (block_channel, decoded_channel) in data_block.block.iter_mut().zip(self.decode_buffer.planes().planes()) {
+                block_channel.extend_from_slice(&decoded_channel[from..from + num_frames]);
+            }
+
+            block_frame += num_frames;
+            self.curr_decode_buffer_frame += num_frames;
+
+            if self.curr_decode_buffer_frame >= self.decode_buffer_len {
+                self.reset_decode_buffer = true;
+            }
+        }
+
+        self.playhead_frame += self.block_size;
+
+        Ok(())
+    }
+
+    fn current_frame(&self) -> usize {
+        self.playhead_frame
+    }
+}
+
+impl Drop for TrackDecoder {
+    fn drop(&mut self) {
+        let _ = self.decoder.finalize();
+    }
+}
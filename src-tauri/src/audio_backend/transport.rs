@@ -0,0 +1,179 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::process::PlaybackState;
+use crate::util::lmdb::settings::{get_setting, store_setting};
+
+const PUBLISH_RATE_SETTING: &str = "transport-publish-rate";
+const DEFAULT_PUBLISH_RATE_HZ: u32 = 20;
+const MAX_PUBLISH_RATE_HZ: u32 = 120;
+
+// written by the audio thread every callback, read by the publisher at its own pace.
+// everything is a plain atomic so the audio thread never blocks or allocates for this
+pub struct TransportState {
+    // position the listener actually hears, i.e. the decoder playhead minus the output latency
+    pos_frames: AtomicUsize,
+    total_frames: AtomicUsize,
+    sample_rate: AtomicU32,
+    output_latency_us: AtomicU64,
+    buffering: AtomicBool,
+    playback_state: AtomicU8,
+    // how much silence was skipped (or sped through) in the current track
+    skipped_frames: AtomicUsize,
+
+    publish_interval_ms: AtomicU64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TransportSnapshot {
+    pub pos_frames: usize,
+    pub total_frames: usize,
+    pub sample_rate: u32,
+    pub pos_secs: f64,
+    pub duration_secs: f64,
+    pub pos_display: String,
+    pub duration_display: String,
+    pub output_latency_ms: f64,
+    pub buffering: bool,
+    pub playback_state: PlaybackState,
+    pub skipped_secs: f64,
+}
+
+pub static TRANSPORT: TransportState = TransportState::new();
+
+impl TransportState {
+    const fn new() -> Self {
+        Self {
+            pos_frames: AtomicUsize::new(0),
+            total_frames: AtomicUsize::new(0),
+            sample_rate: AtomicU32::new(0),
+            output_latency_us: AtomicU64::new(0),
+            buffering: AtomicBool::new(false),
+            playback_state: AtomicU8::new(PlaybackState::Paused as u8),
+            skipped_frames: AtomicUsize::new(0),
+
+            publish_interval_ms: AtomicU64::new(1000 / DEFAULT_PUBLISH_RATE_HZ as u64),
+        }
+    }
+
+    pub fn set_pos_frames(&self, pos: usize) {
+        self.pos_frames.store(pos, Ordering::Relaxed);
+    }
+
+    pub fn set_total_frames(&self, frames: usize) {
+        self.total_frames.store(frames, Ordering::Relaxed);
+    }
+
+    pub fn set_sample_rate(&self, sample_rate: u32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    pub fn set_output_latency(&self, latency: Duration) {
+        self.output_latency_us.store(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn set_buffering(&self, buffering: bool) {
+        self.buffering.store(buffering, Ordering::Relaxed);
+    }
+
+    pub fn set_playback_state(&self, state: PlaybackState) {
+        self.playback_state.store(state as u8, Ordering::Relaxed);
+    }
+
+    pub fn set_skipped_frames(&self, frames: usize) {
+        self.skipped_frames.store(frames, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> TransportSnapshot {
+        let playback_state = if self.playback_state.load(Ordering::Relaxed) == PlaybackState::Playing as u8 {
+            PlaybackState::Playing
+        } else {
+            PlaybackState::Paused
+        };
+
+        let pos_frames = self.pos_frames.load(Ordering::Relaxed);
+        let total_frames = self.total_frames.load(Ordering::Relaxed);
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed);
+
+        let pos_secs = frames_to_secs(pos_frames, sample_rate);
+        let duration_secs = frames_to_secs(total_frames, sample_rate);
+
+        TransportSnapshot {
+            pos_frames,
+            total_frames,
+            sample_rate,
+            pos_secs,
+            duration_secs,
+            pos_display: format_time(pos_secs),
+            duration_display: format_time(duration_secs),
+            output_latency_ms: self.output_latency_us.load(Ordering::Relaxed) as f64 / 1000.0,
+            buffering: self.buffering.load(Ordering::Relaxed),
+            playback_state,
+            skipped_secs: frames_to_secs(self.skipped_frames.load(Ordering::Relaxed), sample_rate),
+        }
+    }
+
+    pub fn publish_interval(&self) -> Duration {
+        Duration::from_millis(self.publish_interval_ms.load(Ordering::Relaxed))
+    }
+
+    fn set_publish_rate(&self, rate_hz: u32) {
+        let rate_hz = rate_hz.clamp(1, MAX_PUBLISH_RATE_HZ);
+
+        self.publish_interval_ms.store(1000 / rate_hz as u64, Ordering::Relaxed);
+    }
+}
+
+pub fn frames_to_secs(frames: usize, sample_rate: u32) -> f64 {
+    if sample_rate == 0 {
+        return 0.0;
+    }
+
+    frames as f64 / sample_rate as f64
+}
+
+pub fn secs_to_frames(secs: f64, sample_rate: u32) -> usize {
+    (secs.max(0.0) * sample_rate as f64).round() as usize
+}
+
+// mm:ss, or h:mm:ss once we are past the hour mark
+pub fn format_time(secs: f64) -> String {
+    let total_secs = secs.max(0.0) as u64;
+
+    let hours = total_secs / 3600;
+    let minutes = (total_secs % 3600) / 60;
+    let seconds = total_secs % 60;
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
+
+pub fn load_publish_rate() {
+    match get_setting::<u32>(PUBLISH_RATE_SETTING) {
+        Ok(Some(rate_hz)) => TRANSPORT.set_publish_rate(rate_hz),
+        Ok(None) => {}
+        Err(e) => println!("error: {:?}", e),
+    }
+}
+
+#[tauri::command]
+pub fn set_transport_publish_rate(rate_hz: u32) -> Result<(), String> {
+    if rate_hz == 0 {
+        return Err("publish rate must be at least 1 hz".to_string());
+    }
+
+    TRANSPORT.set_publish_rate(rate_hz);
+    store_setting(PUBLISH_RATE_SETTING, &rate_hz).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_transport_state() -> Result<TransportSnapshot, String> {
+    Ok(TRANSPORT.snapshot())
+}
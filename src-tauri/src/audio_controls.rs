@@ -1,6 +1,8 @@
 use std::sync::mpsc::Sender;
 use crate::event_handler::GuiToProcessMsg;
-use crate::event_handler::CURRENT_TRACK_INDEX;
+use crate::event_handler::{CURRENT_TRACK, PLAY_QUEUE, SONGS};
+use crate::library::song_by_id;
+use crate::song_dir::Song;
 
 pub fn handle_play(to_player_tx: &mut Sender<GuiToProcessMsg>)  {
 
@@ -27,15 +29,30 @@ pub fn handle_seek(to_player_tx: &mut Sender<GuiToProcessMsg>, frame: usize) {
 
 }
 
+pub fn handle_seek_secs(to_player_tx: &mut Sender<GuiToProcessMsg>, secs: f64) {
+    to_player_tx.send(GuiToProcessMsg::SeekToSecs(secs)).unwrap();
+}
+
+pub fn handle_seek_relative(to_player_tx: &mut Sender<GuiToProcessMsg>, delta_secs: f64) {
+    to_player_tx.send(GuiToProcessMsg::SeekBy(delta_secs)).unwrap();
+}
+
 pub fn handle_repeat(to_player_tx: &mut Sender<GuiToProcessMsg>, state: bool) {
     to_player_tx.send(GuiToProcessMsg::Repeat(state)).unwrap();
 }
 
 #[tauri::command]
-pub fn get_current_index() -> Result<Option<(usize, usize)>, String> {
-    let index_guard = CURRENT_TRACK_INDEX.lock().unwrap();
+pub fn get_current_track() -> Result<Option<Song>, String> {
+    let current_track = CURRENT_TRACK.lock().unwrap().clone();
 
-    let current_index = *index_guard;
+    Ok(current_track)
+}
+
+// what's left of the queue, tracks that left the library are skipped
+#[tauri::command]
+pub fn get_play_queue() -> Result<Vec<Song>, String> {
+    let queue: Vec<_> = PLAY_QUEUE.lock().unwrap().iter().copied().collect();
+    let songs = SONGS.lock().unwrap();
 
-    Ok(current_index)
-}
\ No newline at end of file
+    Ok(queue.into_iter().filter_map(|track| song_by_id(&songs, track).cloned()).collect())
+}
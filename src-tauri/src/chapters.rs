@@ -0,0 +1,124 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::audio_backend::transport::secs_to_frames;
+use crate::event_handler::CURRENT_CHAPTERS;
+
+const MP4_EXTENSIONS: &[&str] = &["m4a", "m4b", "mp4", "aac", "alac"];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Chapter {
+    pub index: usize,
+    pub title: String,
+    pub start_frame: usize,
+    // None for the last chapter, which runs to the end of the file
+    pub end_frame: Option<usize>,
+    pub start_secs: f64,
+}
+
+// (start, title) pairs in whatever order the tag had them, turned into a sorted chapter list
+fn build_chapters(mut raw: Vec<(Duration, String)>, sample_rate: u32) -> Vec<Chapter> {
+    raw.sort_by_key(|(start, _)| *start);
+
+    let starts: Vec<usize> = raw
+        .iter()
+        .map(|(start, _)| secs_to_frames(start.as_secs_f64(), sample_rate))
+        .collect();
+
+    raw.into_iter()
+        .enumerate()
+        .map(|(index, (start, title))| Chapter {
+            index,
+            title: if title.is_empty() { format!("Chapter {}", index + 1) } else { title },
+            start_frame: starts[index],
+            end_frame: starts.get(index + 1).copied(),
+            start_secs: start.as_secs_f64(),
+        })
+        .collect()
+}
+
+fn read_mp4_chapters(path: &Path) -> Result<Vec<(Duration, String)>> {
+    let tag = mp4ameta::Tag::read_from_path(path)?;
+
+    // audiobooks made with different tools carry either a chapter track or a nero chapter list, sometimes both
+    let chapters = if !tag.chapter_track().is_empty() {
+        tag.chapter_track()
+    } else {
+        tag.chapter_list()
+    };
+
+    Ok(chapters
+        .iter()
+        .map(|chapter| (chapter.start, chapter.title.clone()))
+        .collect())
+}
+
+fn read_id3_chapters(path: &Path) -> Result<Vec<(Duration, String)>> {
+    let tag = match id3::Tag::read_from_path(path) {
+        Ok(tag) => tag,
+        Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    // a top level CTOC gives the intended order and lets us skip chapters that aren't part of it
+    let toc_elements: Option<Vec<String>> = tag
+        .tables_of_contents()
+        .find(|toc| toc.top_level)
+        .map(|toc| toc.elements.clone());
+
+    let chapters = tag
+        .chapters()
+        .filter(|chapter| {
+            toc_elements
+                .as_ref()
+                .is_none_or(|elements| elements.contains(&chapter.element_id))
+        })
+        .map(|chapter| {
+            let title = chapter
+                .frames
+                .iter()
+                .find(|frame| frame.id() == "TIT2")
+                .and_then(|frame| frame.content().text())
+                .unwrap_or_default()
+                .to_string();
+
+            (Duration::from_millis(chapter.start_time as u64), title)
+        })
+        .collect();
+
+    Ok(chapters)
+}
+
+pub fn read_chapters(path: &Path, sample_rate: u32) -> Result<Vec<Chapter>> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase())
+        .unwrap_or_default();
+
+    let raw = if MP4_EXTENSIONS.contains(&extension.as_str()) {
+        read_mp4_chapters(path)?
+    } else {
+        // CHAP frames are mostly found in mp3s but id3v2 can be prepended to other formats too
+        read_id3_chapters(path)?
+    };
+
+    Ok(build_chapters(raw, sample_rate))
+}
+
+pub fn chapter_at(chapters: &[Chapter], pos_frames: usize) -> Option<&Chapter> {
+    chapters
+        .iter()
+        .rev()
+        .find(|chapter| chapter.start_frame <= pos_frames)
+}
+
+#[tauri::command]
+pub fn get_chapters() -> Result<Vec<Chapter>, String> {
+    let chapters_guard = CURRENT_CHAPTERS.lock().unwrap();
+
+    Ok(chapters_guard.clone())
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Result;
+use image::ImageFormat;
+use lofty::file::TaggedFileExt;
+use lofty::picture::PictureType;
+use serde::{Deserialize, Serialize};
+use tauri::http::{Request as HttpRequest, Response as HttpResponse, ResponseBuilder};
+use url::Url;
+
+use crate::event_handler::SONGS;
+use crate::library::{song_by_id, TrackId};
+use crate::util::hash::fnv1a_64;
+use crate::util::setup_lmdb::lmdb_data_folder;
+
+pub const COVER_PROTOCOL: &str = "cover";
+
+const COVER_CACHE_FOLDER: &str = "cover_cache";
+
+// checked in this order, so a cover.jpg wins over a folder.jpg next to it
+const FOLDER_COVER_NAMES: [&str; 4] = ["cover", "folder", "front", "album"];
+const FOLDER_COVER_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+lazy_static::lazy_static! {
+    // audio file -> where its cover comes from, so a list of albums doesn't read every file's tags each time.
+    // only valid as long as the file and its folder haven't changed
+    static ref COVER_SOURCES: Mutex<HashMap<PathBuf, (FileStamps, Option<CoverSource>)>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl ThumbSize {
+    fn pixels(self) -> u32 {
+        match self {
+            ThumbSize::Small => 64,
+            ThumbSize::Medium => 256,
+            ThumbSize::Large => 600,
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "small" => Some(ThumbSize::Small),
+            "medium" => Some(ThumbSize::Medium),
+            "large" => Some(ThumbSize::Large),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ThumbSize::Small => "small",
+            ThumbSize::Medium => "medium",
+            ThumbSize::Large => "large",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum CoverSource {
+    // a picture in the tags of the audio file itself
+    Embedded(PathBuf),
+    // cover.jpg and friends next to it
+    File(PathBuf),
+}
+
+// (audio file, its folder). a cover.jpg showing up changes the folder
+type FileStamps = (u64, u64);
+
+fn modified_secs(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|modified| modified.as_secs())
+        .unwrap_or(0)
+}
+
+fn stamps(path: &Path) -> FileStamps {
+    (modified_secs(path), path.parent().map(modified_secs).unwrap_or(0))
+}
+
+// the front cover if it's tagged as such, otherwise whatever picture comes first
+fn embedded_picture(path: &Path) -> Result<Option<Vec<u8>>> {
+    let tagged_file = lofty::read_from_path(path)?;
+
+    let pictures: Vec<_> = tagged_file.tags().iter().flat_map(|tag| tag.pictures()).collect();
+
+    let picture = pictures
+        .iter()
+        .find(|picture| picture.pic_type() == PictureType::CoverFront)
+        .or_else(|| pictures.first());
+
+    Ok(picture.map(|picture| picture.data().to_vec()))
+}
+
+fn folder_cover(dir: &Path) -> Option<PathBuf> {
+    let files: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+
+    FOLDER_COVER_NAMES.iter().find_map(|name| {
+        files.iter().find(|path| {
+            let stem_matches = path.file_stem().is_some_and(|stem| stem.to_string_lossy().eq_ignore_ascii_case(name));
+            let extension_matches = path.extension().is_some_and(|extension| {
+                FOLDER_COVER_EXTENSIONS.iter().any(|known| extension.to_string_lossy().eq_ignore_ascii_case(known))
+            });
+
+            stem_matches && extension_matches
+        })
+        .cloned()
+    })
+}
+
+fn find_cover(path: &Path) -> Option<CoverSource> {
+    match embedded_picture(path) {
+        Ok(Some(_)) => return Some(CoverSource::Embedded(path.to_path_buf())),
+        Ok(None) => {}
+        Err(e) => println!("error reading pictures of {}: {:?}", path.display(), e),
+    }
+
+    path.parent().and_then(folder_cover).map(CoverSource::File)
+}
+
+fn cover_source(path: &Path) -> Option<CoverSource> {
+    let stamps = stamps(path);
+
+    if let Some((known_stamps, source)) = COVER_SOURCES.lock().unwrap().get(path) {
+        if *known_stamps == stamps {
+            return source.clone();
+        }
+    }
+
+    let source = find_cover(path);
+    COVER_SOURCES.lock().unwrap().insert(path.to_path_buf(), (stamps, source.clone()));
+
+    source
+}
+
+pub fn has_cover(path: &Path) -> bool {
+    cover_source(path).is_some()
+}
+
+// next to the library index, it's just as disposable
+pub fn cover_cache_folder() -> Result<PathBuf> {
+    let folder = Path::new(&lmdb_data_folder()).join(COVER_CACHE_FOLDER);
+
+    fs::create_dir_all(&folder)?;
+
+    Ok(folder)
+}
+
+// tracks of an album share their cover.jpg, so they share the thumbnails as well
+fn cache_key(source: &CoverSource) -> String {
+    let (kind, path) = match source {
+        CoverSource::Embedded(path) => ("embedded", path),
+        CoverSource::File(path) => ("file", path),
+    };
+
+    let key = format!("{}:{}:{}", kind, path.to_string_lossy(), modified_secs(path));
+
+    format!("{:016x}", fnv1a_64(key.as_bytes()))
+}
+
+fn make_thumbnail(source: &CoverSource, size: ThumbSize) -> Result<Vec<u8>> {
+    let original = match source {
+        CoverSource::Embedded(path) => embedded_picture(path)?.ok_or_else(|| anyhow::anyhow!("the picture is gone"))?,
+        CoverSource::File(path) => fs::read(path)?,
+    };
+
+    let pixels = size.pixels();
+    let image = image::load_from_memory(&original)?;
+
+    // smaller covers aren't blown up, only shrunk to fit with their aspect ratio kept.
+    // thumbnail() is a lot quicker than a proper filter and looks the same at these sizes
+    let image = if image.width() > pixels || image.height() > pixels {
+        image.thumbnail(pixels, pixels)
+    } else {
+        image
+    };
+
+    let mut thumbnail = Vec::new();
+    image.to_rgb8().write_to(&mut Cursor::new(&mut thumbnail), ImageFormat::Jpeg)?;
+
+    Ok(thumbnail)
+}
+
+// a jpeg of the cover of the file at `path`, made once and read from the cache after that
+pub fn cover_thumbnail(path: &Path, size: ThumbSize) -> Result<Option<Vec<u8>>> {
+    let Some(source) = cover_source(path) else {
+        return Ok(None);
+    };
+
+    let cached = cover_cache_folder()?.join(format!("{}-{}.jpg", cache_key(&source), size.name()));
+
+    if let Ok(thumbnail) = fs::read(&cached) {
+        return Ok(Some(thumbnail));
+    }
+
+    let thumbnail = make_thumbnail(&source, size)?;
+
+    if let Err(e) = fs::write(&cached, &thumbnail) {
+        println!("error: {:?}", e);
+    }
+
+    Ok(Some(thumbnail))
+}
+
+// makes the thumbnail the player shows ahead of time, so the webview finds it in the cache
+// instead of decoding and shrinking the cover while it waits
+pub fn warm_thumbnail(path: &Path) {
+    if let Err(e) = cover_thumbnail(path, ThumbSize::Medium) {
+        println!("error making the cover thumbnail of {}: {:?}", path.display(), e);
+    }
+}
+
+// what the webview loads the cover from, windows only allows custom protocols as https://<name>.localhost
+pub fn cover_url(track: TrackId, size: ThumbSize) -> String {
+    if cfg!(windows) {
+        format!("https://{}.localhost/{}?size={}", COVER_PROTOCOL, track, size.name())
+    } else {
+        format!("{}://localhost/{}?size={}", COVER_PROTOCOL, track, size.name())
+    }
+}
+
+fn track_path(track: TrackId) -> Option<String> {
+    song_by_id(&SONGS.lock().unwrap(), track).map(|song| song.path.clone())
+}
+
+fn empty_response(status: u16) -> Result<HttpResponse, Box<dyn Error>> {
+    ResponseBuilder::new().status(status).body(Vec::new())
+}
+
+// cover://localhost/<track id>?size=small|medium|large
+// runs on the webview's thread, scans warm the cache so a miss here should be rare
+pub fn cover_protocol(request: &HttpRequest) -> Result<HttpResponse, Box<dyn Error>> {
+    let url = Url::parse(request.uri())?;
+
+    let track = url.path().trim_start_matches('/').parse::<TrackId>();
+    let size = url
+        .query_pairs()
+        .find(|(key, _)| key == "size")
+        .and_then(|(_, size)| ThumbSize::parse(&size))
+        .unwrap_or_default();
+
+    let Some(path) = track.ok().and_then(track_path) else {
+        return empty_response(404);
+    };
+
+    match cover_thumbnail(Path::new(&path), size) {
+        Ok(Some(thumbnail)) => ResponseBuilder::new().status(200).mimetype("image/jpeg").body(thumbnail),
+        Ok(None) => empty_response(404),
+        Err(e) => {
+            println!("error: {:?}", e);
+            empty_response(500)
+        }
+    }
+}
+
+// None when the track has no cover, embedded or in its folder
+#[tauri::command]
+pub fn get_cover_art(track_id: TrackId, size: Option<ThumbSize>) -> Result<Option<String>, String> {
+    let path = track_path(track_id).ok_or_else(|| "that track is not in the library".to_string())?;
+
+    Ok(has_cover(Path::new(&path)).then(|| cover_url(track_id, size.unwrap_or_default())))
+}
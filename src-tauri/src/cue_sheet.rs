@@ -0,0 +1,194 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::audio_backend::probe::AUDIO_EXTENSIONS;
+
+// cue sheet timestamps are mm:ss:ff with 75 frames ("sectors") per second
+const CUE_FRAMES_PER_SEC: u64 = 75;
+
+#[derive(Debug, Clone)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    // INDEX 01 of the track, as (seconds, cue frames)
+    pub start: (u64, u64),
+}
+
+#[derive(Debug, Clone)]
+pub struct CueFile {
+    pub path: PathBuf,
+    pub tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub files: Vec<CueFile>,
+}
+
+impl CueTrack {
+    pub fn start_frame(&self, sample_rate: u32) -> usize {
+        let (secs, cue_frames) = self.start;
+
+        (secs * sample_rate as u64 + cue_frames * sample_rate as u64 / CUE_FRAMES_PER_SEC) as usize
+    }
+}
+
+// a lot of rippers write cue sheets in the local codepage instead of utf-8,
+// falling back to latin-1 at least keeps the ascii parts intact
+fn decode_cue_text(bytes: Vec<u8>) -> String {
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => e.into_bytes().iter().map(|&b| b as char).collect(),
+    };
+
+    text.trim_start_matches('\u{feff}').to_string()
+}
+
+// splits a cue line into its command and arguments, keeping quoted arguments together
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<(u64, u64)> {
+    let mut parts = timestamp.split(':');
+
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: u64 = parts.next()?.parse().ok()?;
+    let frames: u64 = parts.next()?.parse().ok()?;
+
+    if parts.next().is_some() || seconds >= 60 || frames >= CUE_FRAMES_PER_SEC {
+        return None;
+    }
+
+    Some((minutes * 60 + seconds, frames))
+}
+
+// the FILE line frequently points at the wav the cd was ripped to, while the
+// actual file next to it has been converted to flac since
+fn resolve_audio_file(cue_dir: &Path, file_name: &str) -> PathBuf {
+    let referenced = cue_dir.join(file_name.replace('\\', "/"));
+
+    if referenced.exists() {
+        return referenced;
+    }
+
+    for extension in AUDIO_EXTENSIONS {
+        let candidate = referenced.with_extension(extension);
+
+        if candidate.exists() {
+            return candidate;
+        }
+    }
+
+    referenced
+}
+
+pub fn parse_cue_sheet(text: &str, cue_dir: &Path) -> Result<CueSheet> {
+    let mut sheet = CueSheet::default();
+
+    for (line_number, line) in text.lines().enumerate() {
+        let tokens = tokenize(line);
+
+        let Some(command) = tokens.first() else {
+            continue;
+        };
+
+        let argument = tokens.get(1).cloned();
+
+        match command.to_uppercase().as_str() {
+            "FILE" => {
+                let file_name = argument
+                    .ok_or_else(|| anyhow::anyhow!("line {}: FILE without a file name", line_number + 1))?;
+
+                sheet.files.push(CueFile {
+                    path: resolve_audio_file(cue_dir, &file_name),
+                    tracks: Vec::new(),
+                });
+            }
+            "TRACK" => {
+                let file = sheet.files.last_mut()
+                    .ok_or_else(|| anyhow::anyhow!("line {}: TRACK before any FILE", line_number + 1))?;
+
+                let number = argument
+                    .and_then(|number| number.parse().ok())
+                    .ok_or_else(|| anyhow::anyhow!("line {}: invalid track number", line_number + 1))?;
+
+                // data tracks on enhanced cds have nothing we could play
+                if tokens.get(2).is_some_and(|track_type| track_type.eq_ignore_ascii_case("AUDIO")) {
+                    file.tracks.push(CueTrack {
+                        number,
+                        title: None,
+                        performer: None,
+                        start: (0, 0),
+                    });
+                }
+            }
+            "INDEX" => {
+                // INDEX 00 is the pregap, playback starts at INDEX 01
+                if tokens.get(1).map(String::as_str) != Some("01") {
+                    continue;
+                }
+
+                let start = tokens.get(2)
+                    .and_then(|timestamp| parse_timestamp(timestamp))
+                    .ok_or_else(|| anyhow::anyhow!("line {}: invalid INDEX timestamp", line_number + 1))?;
+
+                if let Some(track) = sheet.files.last_mut().and_then(|file| file.tracks.last_mut()) {
+                    track.start = start;
+                }
+            }
+            "TITLE" | "PERFORMER" => {
+                let current_track = sheet.files.last_mut().and_then(|file| file.tracks.last_mut());
+
+                match (command.to_uppercase().as_str(), current_track) {
+                    ("TITLE", Some(track)) => track.title = argument,
+                    ("PERFORMER", Some(track)) => track.performer = argument,
+                    ("TITLE", None) => sheet.title = argument,
+                    _ => sheet.performer = argument,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    sheet.files.retain(|file| !file.tracks.is_empty());
+
+    if sheet.files.is_empty() {
+        return Err(anyhow::anyhow!("cue sheet does not contain any audio tracks"));
+    }
+
+    Ok(sheet)
+}
+
+pub fn read_cue_sheet(cue_path: &Path) -> Result<CueSheet> {
+    let text = decode_cue_text(fs::read(cue_path)?);
+
+    let cue_dir = cue_path.parent().unwrap_or(Path::new("."));
+
+    parse_cue_sheet(&text, cue_dir)
+}
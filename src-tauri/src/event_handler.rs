@@ -1,51 +1,129 @@
 
 
 use tauri::Manager;
-use creek::{ReadDiskStream, ReadStreamOptions, SymphoniaDecoder};
+use creek::{ReadDiskStream, ReadStreamOptions};
 
 use std::path::Path;
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
 use tokio::time::sleep as tsleep;
 use std::time::Duration;
-use std::sync::{mpsc, Mutex};
-use tokio::sync::mpsc as tmpsc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{mpsc, Arc, Mutex};
+use serde::Serialize;
 
-use crate::song_dir::scan_dir;
+use crate::library::watch::start_library_watcher;
+use crate::cover_art::{cover_url, has_cover, ThumbSize};
+use crate::library::browse::folder_tracks;
+use crate::library::catalog::album_tracks;
+use crate::library::{next_song, prev_song, song_by_id, TrackId};
 
-use crate::audio_controls::{handle_pause, handle_play, handle_repeat, handle_restart, handle_seek};
+use crate::audio_controls::{handle_pause, handle_play, handle_repeat, handle_restart, handle_seek, handle_seek_relative, handle_seek_secs};
 use crate::audio_backend::output;
+use crate::audio_backend::process::{GaplessNext, PlaybackState};
+use crate::audio_backend::probe::UnsupportedFile;
+use crate::audio_backend::track_decoder::TrackDecoder;
+use crate::audio_backend::silence::{load_or_analyze, playback_regions, SilenceAnalysis, SilenceMode, SilenceSettings};
+use crate::audio_backend::crossfeed::{CrossfeedPreset, CrossfeedSettings};
+use crate::audio_backend::convolution::{ConvolutionInfo, ConvolutionSettings, ConvolutionStatePayload, Convolver};
+use crate::audio_backend::effects::{AudioEffect, EffectHandle};
+use crate::audio_backend::effects::chain::{build_slot, save_effect_states, EffectChainConfig, EffectChainEdit, EffectChainMsg};
+use crate::audio_backend::network_stream::{NetworkStream, NowPlaying, StreamStatus, StreamStatusPayload};
+use crate::audio_backend::playback_error::{classify_read_error, ErrorAction, ErrorPolicy, PlaybackErrorKind, PlaybackErrorPayload};
+use crate::audio_backend::transport::{load_publish_rate, secs_to_frames, TransportSnapshot, TRANSPORT};
 use crate::audio_backend::get_all_audio_devices::get_device_info;
-use crate::util::lmdb::audio_files_dir::get_songs_directory;
 
 use crate::song_dir::Song;
+use crate::chapters::{chapter_at, read_chapters, Chapter};
+use crate::resume::{resume_frame_for, save_resume_position, ResumeSettings};
+use crate::radio::resolve_stream_url;
 
 const PLAY: &str = "play";
 const PAUSE: &str = "pause";
 const RESTART: &str = "restart";
 //const LOOPING_POS: &str = "looping-pos";
 const SEEK: &str = "seek";
+const SEEK_SECS: &str = "seek_secs";
+const SEEK_RELATIVE: &str = "seek_relative";
 const REPEAT: &str = "repeat";
 const SWITCH_TRACK: &str = "switch_track";
 const SKIP_TO_NEXT: &str = "skip_to_next";
 const SKIP_TO_PREV: &str = "skip_to_prev";
+const NEXT_CHAPTER: &str = "next_chapter";
+const PREV_CHAPTER: &str = "prev_chapter";
+const PLAY_STREAM: &str = "play_stream";
+const PLAY_FOLDER: &str = "play_folder";
+const ENQUEUE_FOLDER: &str = "enqueue_folder";
+const PLAY_ALBUM: &str = "play_album";
+const ENQUEUE_ALBUM: &str = "enqueue_album";
+const SILENCE_MODE: &str = "silence_mode";
+const CROSSFEED: &str = "crossfeed";
+const CONVOLUTION: &str = "convolution";
+const EDIT_EFFECT_CHAIN: &str = "edit_effect_chain";
+
+const TRANSPORT_STATE: &str = "transport-state";
+const PLAYBACK_ERROR: &str = "playback-error";
+const TRACK_CHANGED: &str = "track-changed";
+const CHAPTER_CHANGED: &str = "chapter-changed";
+const NOW_PLAYING: &str = "now-playing";
+const STREAM_STATUS: &str = "stream-status";
+const CONVOLUTION_STATE: &str = "convolution-state";
+const EFFECT_CHAIN_CHANGED: &str = "effect-chain";
+
+// "previous chapter" only goes back a chapter when pressed right after a chapter started,
+// otherwise it jumps to the start of the current one
+const PREV_CHAPTER_RESTART_SECS: f64 = 3.0;
 
 pub enum GuiToProcessMsg {
-    UseStream((Box<ReadDiskStream<SymphoniaDecoder>>, usize)),
+    UseStream((Box<ReadDiskStream<TrackDecoder>>, TrackId)),
+    UseNetworkStream(Box<NetworkStream>),
     SetLoop { start: usize, end: usize },
     PlayResume,
     Pause,
     Repeat(bool),
     Restart,
-    SeekTo(usize)
+    SeekTo(usize),
+    SeekToSecs(f64),
+    SeekBy(f64),
+    QueueGapless(Option<GaplessNext>),
+    SetSilenceMode { mode: SilenceMode, speed_factor: usize },
+    SetSilenceRegions { track: TrackId, regions: Vec<(usize, usize)> },
+    SetCrossfeed(Option<CrossfeedPreset>),
+    SetConvolver(Option<Box<Convolver>>),
+    EffectChain(EffectChainMsg),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackChangedPayload {
+    pub track_id: TrackId,
+    // None when the track has no cover art
+    pub cover: Option<String>,
+}
+
+fn track_changed_payload(track: TrackId) -> TrackChangedPayload {
+    let path = song_by_id(&SONGS.lock().unwrap(), track).map(|song| song.path.clone());
+
+    TrackChangedPayload {
+        track_id: track,
+        cover: path.filter(|path| has_cover(Path::new(path))).map(|_| cover_url(track, ThumbSize::Medium)),
+    }
 }
 
 pub enum ProcessToGuiMsg {
-    PlaybackPos(usize),
-    Buffering(bool),
-    TotalFrames(usize),
-    DropOldStream(Box<ReadDiskStream<SymphoniaDecoder>>),
-    DropAndNext(usize),
+    DropOldStream(Box<ReadDiskStream<TrackDecoder>>),
+    DropNetworkStream(Box<NetworkStream>),
+    DropConvolver(Box<Convolver>),
+    ConvolutionState(ConvolutionStatePayload),
+    DropEffect(Box<dyn AudioEffect>),
+    DropSilenceRegions(Vec<(usize, usize)>),
+    EffectChainChanged(EffectChainConfig),
+    NowPlaying(NowPlaying),
+    StreamStatus(StreamStatusPayload),
+    DropAndNext(TrackId),
+    // a track was opened from the start or a position, not through gapless playback
+    TrackStarted(TrackId),
+    TrackAdvanced(TrackId),
+    PlaybackError { track: TrackId, kind: PlaybackErrorKind, message: String, pos: usize },
 }
 
 pub enum ControlMessage {
@@ -54,14 +132,131 @@ pub enum ControlMessage {
     Restart,
     Repeat(bool),
     Seek(usize),
-    SetTrack(usize),
+    SeekSecs(f64),
+    SeekRelative(f64),
+    SetTrack(TrackId),
+    RetryTrack { track: TrackId, pos: usize },
+    QueueGaplessAfter(TrackId),
+    // a folder or an album, in the order they should play
+    PlayTracks(Vec<TrackId>),
+    EnqueueTracks(Vec<TrackId>),
     SkipToNext,
     SkipToPrev,
+    NextChapter,
+    PrevChapter,
+    PlayStream(String),
+    SilenceMode(SilenceMode),
+    Crossfeed(CrossfeedSettings),
+    Convolution(ConvolutionSettings),
+    EditEffectChain(EffectChainEdit),
 }
 
 lazy_static::lazy_static! {
     pub static ref SONGS: Mutex<Vec<Song>> = Mutex::new(Vec::new());
-    pub static ref CURRENT_TRACK_INDEX: Mutex<Option<(usize, usize)>> = Mutex::new(None);
+    pub static ref UNSUPPORTED_FILES: Mutex<Vec<UnsupportedFile>> = Mutex::new(Vec::new());
+    // kept as it was when it started playing, the library may lose it in the meantime
+    pub static ref CURRENT_TRACK: Mutex<Option<Song>> = Mutex::new(None);
+    // played before carrying on in library order
+    pub static ref PLAY_QUEUE: Mutex<VecDeque<TrackId>> = Mutex::new(VecDeque::new());
+    pub static ref CURRENT_CHAPTERS: Mutex<Vec<Chapter>> = Mutex::new(Vec::new());
+    pub static ref CURRENT_SILENCE: Mutex<Option<SilenceAnalysis>> = Mutex::new(None);
+    pub static ref CURRENT_CONVOLUTION: Mutex<Option<ConvolutionInfo>> = Mutex::new(None);
+    pub static ref EFFECT_CHAIN: Mutex<EffectChainConfig> = Mutex::new(EffectChainConfig::default());
+    pub static ref EFFECT_HANDLES: Mutex<HashMap<u64, Arc<dyn EffectHandle>>> = Mutex::new(HashMap::new());
+}
+
+// the queue comes first, then the library order, wrapping around at the end
+fn next_track(songs: &[Song], current: &Song) -> Option<TrackId> {
+    let mut queue = PLAY_QUEUE.lock().unwrap();
+
+    // tracks that left the library since they were queued are dropped
+    while let Some(queued) = queue.front() {
+        if song_by_id(songs, *queued).is_some() {
+            return Some(*queued);
+        }
+
+        queue.pop_front();
+    }
+
+    next_song(songs, current).or_else(|| songs.first()).map(|next| next.id)
+}
+
+fn set_current_track(song: Song) {
+    {
+        let mut queue = PLAY_QUEUE.lock().unwrap();
+
+        if queue.front() == Some(&song.id) {
+            queue.pop_front();
+        }
+    }
+
+    *CURRENT_TRACK.lock().unwrap() = Some(song);
+}
+
+// cue tracks of the same file that follow each other without a gap are played back as one stream
+fn gapless_next_after(songs: &[Song], current: &Song) -> Option<GaplessNext> {
+    let next = next_song(songs, current)?;
+
+    if next_track(songs, current) != Some(next.id) {
+        return None;
+    }
+
+    if next.path != current.path || current.end_frame.is_none() || next.start_frame != current.end_frame {
+        return None;
+    }
+
+    Some(GaplessNext {
+        track: next.id,
+        start: next.start_frame?,
+        end: next.end_frame,
+    })
+}
+
+// remembers where we are in the current track, called periodically and right before switching tracks
+fn save_current_position(settings: &ResumeSettings) {
+    let Some(song) = CURRENT_TRACK.lock().unwrap().clone() else {
+        return;
+    };
+
+    let snapshot = TRANSPORT.snapshot();
+
+    if let Err(e) = save_resume_position(&song, snapshot.pos_frames, snapshot.total_frames, snapshot.sample_rate, settings) {
+        println!("error: {:?}", e);
+    }
+}
+
+// analyzing means decoding the whole file, so it runs on its own thread and the regions
+// are handed to the process whenever they are ready
+fn spawn_silence_analysis(track: TrackId, path: String, to_process_tx: Sender<GuiToProcessMsg>) {
+    let settings = SilenceSettings::load();
+
+    if settings.mode == SilenceMode::Off {
+        return;
+    }
+
+    thread::spawn(move || {
+        let analysis = match load_or_analyze(Path::new(&path), &settings) {
+            Ok(analysis) => analysis,
+            Err(e) => {
+                println!("error: {:?}", e);
+                return;
+            }
+        };
+
+        let still_current = CURRENT_TRACK.lock().unwrap().as_ref().map(|song| song.id) == Some(track);
+
+        if !still_current {
+            return;
+        }
+
+        let regions = playback_regions(&analysis, settings.keep_secs);
+
+        println!("{} silent stretches, {:.1}s in total", analysis.regions.len(), analysis.total_silence_secs);
+
+        *CURRENT_SILENCE.lock().unwrap() = Some(analysis);
+
+        let _ = to_process_tx.send(GuiToProcessMsg::SetSilenceRegions { track, regions });
+    });
 }
 
 fn start_playing_thread(
@@ -75,53 +270,182 @@ fn start_playing_thread(
 
     std::thread::spawn(move || {
         let to_process_tx_clone = to_process_tx.clone();
+        let error_tx = to_gui_tx.clone();
+
+        let (switch_track_tx, switch_track_rx) = mpsc::channel::<(TrackId, usize)>();
+        let (play_stream_tx, play_stream_rx) = mpsc::channel::<String>();
+
+        let stream_to_process_tx = to_process_tx.clone();
+
+        let silence_settings = SilenceSettings::load();
+        to_process_tx.send(GuiToProcessMsg::SetSilenceMode {
+            mode: silence_settings.mode,
+            speed_factor: silence_settings.speed_factor,
+        }).unwrap();
 
-        let (switch_track_tx, switch_track_rx) = mpsc::channel();
+        let crossfeed_settings = CrossfeedSettings::load();
+        to_process_tx.send(GuiToProcessMsg::SetCrossfeed(
+            crossfeed_settings.enabled.then_some(crossfeed_settings.preset)
+        )).unwrap();
+        let stream_to_gui_tx = to_gui_tx.clone();
         
-        let _cpal_stream: cpal::Stream = output::spawn_cpal_stream(to_gui_tx, from_gui_rx);
+        let (convolution_tx, convolution_rx) = mpsc::channel::<ConvolutionSettings>();
+        let convolution_to_process_tx = to_process_tx.clone();
+        let convolution_to_gui_tx = to_gui_tx.clone();
+        let effects_to_gui_tx = to_gui_tx.clone();
+
+        let (_cpal_stream, output_sample_rate): (cpal::Stream, u32) = output::spawn_cpal_stream(to_gui_tx, from_gui_rx);
+
+        // effects need the device rate to be prepared, so the saved chain can only be rebuilt from here on.
+        // whatever fails to build (e.g. a kind that no longer exists) is left out of the chain
+        let mut effect_chain = EffectChainConfig::load();
+
+        effect_chain.effects.retain_mut(|config| match build_slot(config, output_sample_rate) {
+            Ok(slot) => {
+                to_process_tx.send(GuiToProcessMsg::EffectChain(EffectChainMsg::Insert {
+                    position: usize::MAX,
+                    slot,
+                })).unwrap();
+                true
+            }
+            Err(e) => {
+                println!("error: {:?}", e);
+                false
+            }
+        });
+
+        *EFFECT_CHAIN.lock().unwrap() = effect_chain;
+
+        // loading an ir means decoding, resampling and a few hundred ffts, none of which should
+        // hold up the controls. the process crossfades to the new one once it's ready
+        std::thread::spawn(move || {
+            while let Ok(settings) = convolution_rx.recv() {
+                let loaded = match (&settings.ir_path, settings.enabled) {
+                    (Some(ir_path), true) => {
+                        Convolver::load(Path::new(ir_path), settings.partition_size, output_sample_rate).map(Some)
+                    }
+                    _ => Ok(None),
+                };
+
+                let payload = match loaded {
+                    Ok(convolver) => {
+                        let info = convolver.as_ref().map(|convolver| convolver.info().clone());
+
+                        *CURRENT_CONVOLUTION.lock().unwrap() = info.clone();
+                        convolution_to_process_tx.send(GuiToProcessMsg::SetConvolver(convolver.map(Box::new))).unwrap();
+
+                        ConvolutionStatePayload { info, error: None }
+                    }
+                    // keep whatever is playing right now
+                    Err(e) => {
+                        println!("error: {:?}", e);
+
+                        ConvolutionStatePayload {
+                            info: CURRENT_CONVOLUTION.lock().unwrap().clone(),
+                            error: Some(e.to_string()),
+                        }
+                    }
+                };
+
+                let _ = convolution_to_gui_tx.send(ProcessToGuiMsg::ConvolutionState(payload));
+            }
+        });
+
+        convolution_tx.send(ConvolutionSettings::load()).unwrap();
 
         std::thread::spawn(move || {
 
             let to_process_tx_clone = to_process_tx_clone.clone();
          
-            while let Ok(track_index) = switch_track_rx.recv() {
-                
-                let songs_vec = SONGS.lock().unwrap();
+            while let Ok((track_id, start_frame)) = switch_track_rx.recv() {
+
+                // not held while the stream opens, that can take a while and the library shouldn't wait for it
+                let song = song_by_id(&SONGS.lock().unwrap(), track_id).cloned();
+
+                if let Some(song) = song {
 
-                let songs_len = songs_vec.len();
-                if track_index < songs_len {
+                    save_current_position(&ResumeSettings::load());
 
-                    let opts: ReadStreamOptions<SymphoniaDecoder> = ReadStreamOptions {
+                    let opts: ReadStreamOptions<TrackDecoder> = ReadStreamOptions {
                         num_cache_blocks: 20,
-                        num_caches: 2,
+                        // 0: where playback starts, 1: the loop start, 2: the end of the next silence to skip
+                        num_caches: 3,
                         ..Default::default()
                     };
 
-                    let song: &Song = &songs_vec[track_index];
+                    // 0 means "from the start of the track" (or from where we left off last time),
+                    // which isn't the start of the file for cue tracks
+                    let track_start = song.start_frame.unwrap_or(0);
 
-                    
-                    let mut current_track_index = CURRENT_TRACK_INDEX.lock().unwrap();
+                    let start_frame = if start_frame == 0 {
+                        track_start + resume_frame_for(&song).unwrap_or(0)
+                    } else {
+                        start_frame.max(track_start)
+                    };
 
-                    *current_track_index = Some((track_index, songs_len));
 
-                    let mut read_stream = match ReadDiskStream::<SymphoniaDecoder>::new(&song.path, 0, opts) {
+                    set_current_track(song.clone());
+
+                    let mut read_stream = match ReadDiskStream::<TrackDecoder>::new(&song.path, start_frame, opts) {
                         Ok(stream) => stream,
                         Err(e) => {
                             eprintln!("error: {}", e);
+                            let _ = error_tx.send(ProcessToGuiMsg::PlaybackError {
+                                track: track_id,
+                                kind: PlaybackErrorKind::Open,
+                                message: e.to_string(),
+                                pos: start_frame,
+                            });
                             continue;
                         }
                     };
 
-                    let _ = read_stream.cache(0, 0);
-                    read_stream.seek(0, Default::default()).unwrap();
-                    read_stream.block_until_ready().unwrap();
+                    let _ = read_stream.cache(0, start_frame);
+
+                    let ready = read_stream
+                        .seek(start_frame, Default::default())
+                        .and_then(|_| read_stream.block_until_ready());
+
+                    if let Err(e) = ready {
+                        eprintln!("error: {}", e);
+                        let _ = error_tx.send(ProcessToGuiMsg::PlaybackError {
+                            track: track_id,
+                            kind: classify_read_error(&e),
+                            message: e.to_string(),
+                            pos: start_frame,
+                        });
+                        continue;
+                    }
          
                     let num_frames = read_stream.info().num_frames;
+
+                    // cue tracks are chapters of their file already, so only whole files get their chapters read
+                    let chapters = match (song.start_frame, read_stream.info().sample_rate) {
+                        (None, Some(sample_rate)) => read_chapters(Path::new(&song.path), sample_rate).unwrap_or_else(|e| {
+                            println!("error: {:?}", e);
+                            Vec::new()
+                        }),
+                        _ => Vec::new(),
+                    };
+
+                    *CURRENT_CHAPTERS.lock().unwrap() = chapters;
         
-                    to_process_tx_clone.send(GuiToProcessMsg::UseStream((Box::new(read_stream), track_index))).unwrap();
-                    to_process_tx_clone.send(GuiToProcessMsg::SetLoop { start: 0, end: num_frames }).unwrap();
+                    to_process_tx_clone.send(GuiToProcessMsg::UseStream((Box::new(read_stream), track_id))).unwrap();
+                    let _ = error_tx.send(ProcessToGuiMsg::TrackStarted(track_id));
+                    // both ends inside the file, a cue sheet can point past the end of an image that was cut short
+                    let loop_end = song.end_frame.unwrap_or(num_frames).min(num_frames);
+                    to_process_tx_clone.send(GuiToProcessMsg::SetLoop {
+                        start: song.start_frame.unwrap_or(0).min(loop_end),
+                        end: loop_end,
+                    }).unwrap();
+                    to_process_tx_clone.send(GuiToProcessMsg::QueueGapless(gapless_next_after(&SONGS.lock().unwrap(), &song))).unwrap();
 
                     to_process_tx_clone.send(GuiToProcessMsg::PlayResume).unwrap();
+
+                    *CURRENT_SILENCE.lock().unwrap() = None;
+                    spawn_silence_analysis(track_id, song.path.clone(), to_process_tx_clone.clone());
+                } else {
+                    println!("track {} is not in the library anymore", track_id);
                 }
 
 
@@ -131,6 +455,36 @@ fn start_playing_thread(
             
         });
 
+        // resolving playlists and connecting can take a while, so radio streams get their own thread
+        std::thread::spawn(move || {
+            while let Ok(url) = play_stream_rx.recv() {
+                let stream_url = match resolve_stream_url(&url) {
+                    Ok(stream_url) => stream_url,
+                    Err(e) => {
+                        println!("error: {:?}", e);
+                        let _ = stream_to_gui_tx.send(ProcessToGuiMsg::StreamStatus(StreamStatusPayload {
+                            url,
+                            status: StreamStatus::Failed,
+                            attempt: 0,
+                            message: Some(e.to_string()),
+                        }));
+                        continue;
+                    }
+                };
+
+                save_current_position(&ResumeSettings::load());
+
+                // no track from the library is playing anymore
+                *CURRENT_TRACK.lock().unwrap() = None;
+                CURRENT_CHAPTERS.lock().unwrap().clear();
+
+                let network_stream = NetworkStream::open(stream_url, stream_to_gui_tx.clone());
+
+                stream_to_process_tx.send(GuiToProcessMsg::UseNetworkStream(Box::new(network_stream))).unwrap();
+                stream_to_process_tx.send(GuiToProcessMsg::PlayResume).unwrap();
+            }
+        });
+
         std::thread::spawn(move || {            
             while let Ok(msg) = rx.recv() {
                 match msg {
@@ -149,27 +503,144 @@ fn start_playing_thread(
                     ControlMessage::Seek(seek_to) => {
                         handle_seek(&mut to_process_tx.clone(), seek_to);
                     }
-                    ControlMessage::SetTrack(track_index) => {
-                        switch_track_tx.send(track_index).unwrap();
+                    ControlMessage::SeekSecs(secs) => {
+                        handle_seek_secs(&mut to_process_tx.clone(), secs);
+                    }
+                    ControlMessage::SeekRelative(delta_secs) => {
+                        handle_seek_relative(&mut to_process_tx.clone(), delta_secs);
+                    }
+                    ControlMessage::SetTrack(track) => {
+                        switch_track_tx.send((track, 0)).unwrap();
+                    }
+                    ControlMessage::RetryTrack { track, pos } => {
+                        switch_track_tx.send((track, pos)).unwrap();
+                    }
+                    ControlMessage::QueueGaplessAfter(track) => {
+                        let songs = SONGS.lock().unwrap();
+                        let next = song_by_id(&songs, track).and_then(|current| gapless_next_after(&songs, current));
+                        to_process_tx.send(GuiToProcessMsg::QueueGapless(next)).unwrap();
+                    }
+                    ControlMessage::PlayTracks(tracks) => {
+                        if let Some((first, rest)) = tracks.split_first() {
+                            *PLAY_QUEUE.lock().unwrap() = rest.iter().copied().collect();
+                            switch_track_tx.send((*first, 0)).unwrap();
+                        } else {
+                            println!("nothing to play");
+                        }
+                    }
+                    ControlMessage::EnqueueTracks(tracks) => {
+                        let current = CURRENT_TRACK.lock().unwrap().clone();
+                        let songs = SONGS.lock().unwrap();
+
+                        PLAY_QUEUE.lock().unwrap().extend(tracks);
+
+                        // the next cue track may already be lined up, and the queue comes first now
+                        if let Some(current) = current {
+                            to_process_tx.send(GuiToProcessMsg::QueueGapless(gapless_next_after(&songs, &current))).unwrap();
+                        }
                     }
                     ControlMessage::SkipToNext => {
-                        let track_index_guard = CURRENT_TRACK_INDEX.lock().unwrap();
-                        if let Some((current_track_index, song_len)) = *track_index_guard {
-                            if current_track_index < song_len {
-                                switch_track_tx.send(current_track_index + 1).unwrap();
-                            } else {
-                                switch_track_tx.send(0).unwrap();
+                        let current = CURRENT_TRACK.lock().unwrap().clone();
+
+                        if let Some(current) = current {
+                            if let Some(next) = next_track(&SONGS.lock().unwrap(), &current) {
+                                switch_track_tx.send((next, 0)).unwrap();
                             }
                         }
                     }           
-                    ControlMessage::SkipToPrev => {
-                        let track_index_guard = CURRENT_TRACK_INDEX.lock().unwrap();
-                        if let Some((current_track_index, _song_len)) = *track_index_guard {
-                            if current_track_index > 0 {
-                                switch_track_tx.send(current_track_index - 1).unwrap();
+                    ControlMessage::NextChapter => {
+                        let pos = TRANSPORT.snapshot().pos_frames;
+                        let chapters = CURRENT_CHAPTERS.lock().unwrap();
+
+                        if let Some(next_chapter) = chapters.iter().find(|chapter| chapter.start_frame > pos) {
+                            handle_seek(&mut to_process_tx.clone(), next_chapter.start_frame);
+                        }
+                    }
+                    ControlMessage::PrevChapter => {
+                        let snapshot = TRANSPORT.snapshot();
+                        let chapters = CURRENT_CHAPTERS.lock().unwrap();
+
+                        if let Some(current_chapter) = chapter_at(&chapters, snapshot.pos_frames) {
+                            let restart_window = secs_to_frames(PREV_CHAPTER_RESTART_SECS, snapshot.sample_rate);
+                            let into_chapter = snapshot.pos_frames - current_chapter.start_frame;
+
+                            let target = if into_chapter > restart_window || current_chapter.index == 0 {
+                                current_chapter.start_frame
                             } else {
-                                switch_track_tx.send(0).unwrap();
+                                chapters[current_chapter.index - 1].start_frame
+                            };
+
+                            handle_seek(&mut to_process_tx.clone(), target);
+                        }
+                    }
+                    ControlMessage::SilenceMode(mode) => {
+                        let mut settings = SilenceSettings::load();
+                        settings.mode = mode;
+
+                        if let Err(e) = settings.store() {
+                            println!("error: {:?}", e);
+                        }
+
+                        to_process_tx.send(GuiToProcessMsg::SetSilenceMode {
+                            mode,
+                            speed_factor: settings.speed_factor,
+                        }).unwrap();
+
+                        // turned on in the middle of a track that was never analyzed
+                        let current = CURRENT_TRACK.lock().unwrap().clone();
+
+                        if let Some(current) = current {
+                            if CURRENT_SILENCE.lock().unwrap().is_none() {
+                                spawn_silence_analysis(current.id, current.path, to_process_tx.clone());
+                            }
+                        }
+                    }
+                    ControlMessage::Crossfeed(settings) => {
+                        if let Err(e) = settings.store() {
+                            println!("error: {:?}", e);
+                        }
+
+                        to_process_tx.send(GuiToProcessMsg::SetCrossfeed(settings.enabled.then_some(settings.preset))).unwrap();
+                    }
+                    ControlMessage::Convolution(settings) => {
+                        if let Err(e) = settings.store() {
+                            println!("error: {:?}", e);
+                        }
+
+                        convolution_tx.send(settings).unwrap();
+                    }
+                    ControlMessage::EditEffectChain(edit) => {
+                        let mut chain_guard = EFFECT_CHAIN.lock().unwrap();
+
+                        match chain_guard.apply(edit, output_sample_rate) {
+                            Ok(msg) => {
+                                if let Err(e) = chain_guard.store() {
+                                    println!("error: {:?}", e);
+                                }
+
+                                // plugin state is saved when the chain changes and on exit, not on every parameter tweak
+                                if !matches!(msg, EffectChainMsg::SetParameter { .. }) {
+                                    save_effect_states();
+                                }
+
+                                to_process_tx.send(GuiToProcessMsg::EffectChain(msg)).unwrap();
+                                let _ = effects_to_gui_tx.send(ProcessToGuiMsg::EffectChainChanged(chain_guard.with_errors()));
                             }
+                            Err(e) => println!("error: {:?}", e),
+                        }
+                    }
+                    ControlMessage::PlayStream(url) => {
+                        play_stream_tx.send(url).unwrap();
+                    }
+                    ControlMessage::SkipToPrev => {
+                        let current = CURRENT_TRACK.lock().unwrap().clone();
+
+                        if let Some(current) = current {
+                            let songs = SONGS.lock().unwrap();
+
+                            // the first track starts over
+                            let prev = prev_song(&songs, &current).map(|prev| prev.id).unwrap_or(current.id);
+                            switch_track_tx.send((prev, 0)).unwrap();
                         }
                     }       
                     
@@ -196,39 +667,9 @@ pub async fn event_handler(app_handle: tauri::AppHandle) {
 
     let (tx, rx) = mpsc::channel();
 
-    tokio::task::spawn(async move {
-        
-        let mut files: Vec<Song> = Vec::new();
+    start_library_watcher(app_handle.clone());
 
-        loop {
-            match get_songs_directory() {
-                Ok(songs_dir) => {
-                    match songs_dir {
-                        Some(song_dir) => {
-                            let song_dir_p = Path::new(&song_dir);
-    
-                            files = scan_dir(song_dir_p).unwrap();
-    
-                            if !files.is_empty() {
-                                let mut songs_vec: std::sync::MutexGuard<Vec<Song>> = SONGS.lock().unwrap();
-                                *songs_vec = files;
-                                drop(songs_vec);
-                            }
-                            
-                        }
-                        None => {
-                            println!("none");
-                        }
-                    }
-                },
-                Err(e) => {
-                    println!("error: {:?}", e);
-                }
-            };
-    
-            tokio::time::sleep(Duration::from_secs(5)).await;
-        }
-    });
+    load_publish_rate();
 
     start_playing_thread(from_gui_rx, to_gui_tx, to_process_tx, rx);
     
@@ -276,16 +717,41 @@ pub async fn event_handler(app_handle: tauri::AppHandle) {
             println!("event : {:?}", pos);
         }
     });
-    app_handle.listen_global(SWITCH_TRACK, {
+    app_handle.listen_global(SEEK_SECS, {
         let tx = tx.clone();
         move |event| {
             let event_payload = event.payload().unwrap();
 
             let json_event: serde_json::Value = serde_json::from_str(event_payload).unwrap();
 
-            let track_index = json_event.get("track_index").unwrap().as_u64().unwrap();
+            let secs = json_event.get("secs").unwrap().as_f64().unwrap();
 
-            tx.send(ControlMessage::SetTrack(track_index as usize)).unwrap();
+            tx.send(ControlMessage::SeekSecs(secs)).unwrap();
+        }
+    });
+    app_handle.listen_global(SEEK_RELATIVE, {
+        let tx = tx.clone();
+        move |event| {
+            let event_payload = event.payload().unwrap();
+
+            let json_event: serde_json::Value = serde_json::from_str(event_payload).unwrap();
+
+            let secs = json_event.get("secs").unwrap().as_f64().unwrap();
+
+            tx.send(ControlMessage::SeekRelative(secs)).unwrap();
+        }
+    });
+    app_handle.listen_global(SWITCH_TRACK, {
+        let tx = tx.clone();
+        move |event| {
+            let event_payload = event.payload().unwrap();
+
+            let json_event: serde_json::Value = serde_json::from_str(event_payload).unwrap();
+
+            match serde_json::from_value::<TrackId>(json_event["track_id"].clone()) {
+                Ok(track) => tx.send(ControlMessage::SetTrack(track)).unwrap(),
+                Err(e) => println!("error: {:?}", e),
+            }
         }
     });
     app_handle.listen_global(SKIP_TO_NEXT, {
@@ -301,68 +767,321 @@ pub async fn event_handler(app_handle: tauri::AppHandle) {
             tx.send(ControlMessage::SkipToPrev).unwrap();
         }
     });
+    app_handle.listen_global(NEXT_CHAPTER, {
+        let tx = tx.clone();
+        move |_event| {
+            tx.send(ControlMessage::NextChapter).unwrap();
+        }
+    });
+    app_handle.listen_global(PREV_CHAPTER, {
+        let tx = tx.clone();
+        move |_event| {
+            tx.send(ControlMessage::PrevChapter).unwrap();
+        }
+    });
+    app_handle.listen_global(SILENCE_MODE, {
+        let tx = tx.clone();
+        move |event| {
+            let event_payload = event.payload().unwrap();
+
+            let json_event: serde_json::Value = serde_json::from_str(event_payload).unwrap();
+
+            let mode: SilenceMode = serde_json::from_value(json_event.get("mode").unwrap().clone()).unwrap();
+
+            tx.send(ControlMessage::SilenceMode(mode)).unwrap();
+        }
+    });
+    app_handle.listen_global(CROSSFEED, {
+        let tx = tx.clone();
+        move |event| {
+            let event_payload = event.payload().unwrap();
 
+            let settings: CrossfeedSettings = serde_json::from_str(event_payload).unwrap();
 
-    //yea intentions behind these variable names: t is just a short for tokio xd | yea and same for tmpsc as you can see
+            tx.send(ControlMessage::Crossfeed(settings)).unwrap();
+        }
+    });
+    app_handle.listen_global(CONVOLUTION, {
+        let tx = tx.clone();
+        move |event| {
+            let event_payload = event.payload().unwrap();
 
-    let (ttx, mut trx) = tmpsc::channel(100);
+            let settings: ConvolutionSettings = serde_json::from_str(event_payload).unwrap();
 
-    std::thread::spawn(move || {
-        loop {
-            while let Ok(msg) = from_process_rx.try_recv() {
+            tx.send(ControlMessage::Convolution(settings)).unwrap();
+        }
+    });
+    app_handle.listen_global(EDIT_EFFECT_CHAIN, {
+        let tx = tx.clone();
+        move |event| {
+            let event_payload = event.payload().unwrap();
+
+            match serde_json::from_str::<EffectChainEdit>(event_payload) {
+                Ok(edit) => tx.send(ControlMessage::EditEffectChain(edit)).unwrap(),
+                Err(e) => println!("error: {:?}", e),
+            }
+        }
+    });
+    app_handle.listen_global(PLAY_STREAM, {
+        let tx = tx.clone();
+        move |event| {
+            let event_payload = event.payload().unwrap();
+
+            let json_event: serde_json::Value = serde_json::from_str(event_payload).unwrap();
+
+            let url = json_event.get("url").unwrap().as_str().unwrap();
+
+            tx.send(ControlMessage::PlayStream(url.to_string())).unwrap();
+        }
+    });
+    app_handle.listen_global(PLAY_FOLDER, {
+        let tx = tx.clone();
+        move |event| {
+            let event_payload = event.payload().unwrap();
+
+            let json_event: serde_json::Value = serde_json::from_str(event_payload).unwrap();
+
+            let path = json_event.get("path").unwrap().as_str().unwrap();
+            let tracks = folder_tracks(&SONGS.lock().unwrap(), Path::new(path));
+
+            tx.send(ControlMessage::PlayTracks(tracks)).unwrap();
+        }
+    });
+    app_handle.listen_global(ENQUEUE_FOLDER, {
+        let tx = tx.clone();
+        move |event| {
+            let event_payload = event.payload().unwrap();
+
+            let json_event: serde_json::Value = serde_json::from_str(event_payload).unwrap();
+
+            let path = json_event.get("path").unwrap().as_str().unwrap();
+            let tracks = folder_tracks(&SONGS.lock().unwrap(), Path::new(path));
+
+            tx.send(ControlMessage::EnqueueTracks(tracks)).unwrap();
+        }
+    });
+    app_handle.listen_global(PLAY_ALBUM, {
+        let tx = tx.clone();
+        move |event| {
+            let event_payload = event.payload().unwrap();
+
+            let json_event: serde_json::Value = serde_json::from_str(event_payload).unwrap();
+
+            let album_id = json_event.get("album_id").unwrap().as_str().unwrap();
+
+            tx.send(ControlMessage::PlayTracks(album_tracks(album_id))).unwrap();
+        }
+    });
+    app_handle.listen_global(ENQUEUE_ALBUM, {
+        let tx = tx.clone();
+        move |event| {
+            let event_payload = event.payload().unwrap();
+
+            let json_event: serde_json::Value = serde_json::from_str(event_payload).unwrap();
+
+            let album_id = json_event.get("album_id").unwrap().as_str().unwrap();
+
+            tx.send(ControlMessage::EnqueueTracks(album_tracks(album_id))).unwrap();
+        }
+    });
+
+
+    {
+        let app_handle = app_handle.clone();
+
+        let tx = tx.clone();
+
+        // only the rare messages still go through the channel, position/buffering/etc. is read from TRANSPORT below
+        std::thread::spawn(move || {
+            // (track, attempts) of the track that failed last, so retries don't go on forever
+            let mut last_failure: Option<(TrackId, u32)> = None;
+            let mut consecutive_skips: usize = 0;
+
+            while let Ok(msg) = from_process_rx.recv() {
                 match msg {
-                    ProcessToGuiMsg::PlaybackPos(pos) => {
-                        ttx.try_send(ProcessToGuiMsg::PlaybackPos(pos)).unwrap();
+                    ProcessToGuiMsg::PlaybackError { track, kind, message, pos } => {
+                        let attempt = match last_failure {
+                            Some((failed_track, attempts)) if failed_track == track => attempts + 1,
+                            _ => 1,
+                        };
+                        last_failure = Some((track, attempt));
+
+                        let failed = CURRENT_TRACK.lock().unwrap().clone().filter(|song| song.id == track);
+
+                        let (next, songs_len) = {
+                            let songs_vec = SONGS.lock().unwrap();
+                            let next = match &failed {
+                                Some(failed) => next_track(&songs_vec, failed),
+                                None => songs_vec.first().map(|first| first.id),
+                            };
+
+                            (next, songs_vec.len())
+                        };
+
+                        let mut action = ErrorPolicy::load().action_for(kind, attempt);
+
+                        // every single track failed in a row, there is nothing left to skip to
+                        if action == ErrorAction::Skip && consecutive_skips + 1 >= songs_len {
+                            action = ErrorAction::Stop;
+                        }
+
+                        println!("playback error on track {} ({:?}): {}", track, action, message);
+
+                        let payload = PlaybackErrorPayload {
+                            kind,
+                            message,
+                            path: failed.map(|song| song.path),
+                            track_id: track,
+                            attempt,
+                            action,
+                        };
+
+                        if let Err(e) = app_handle.emit_all(PLAYBACK_ERROR, payload) {
+                            println!("error: {:?}", e);
+                        }
+
+                        match action {
+                            ErrorAction::Retry => {
+                                let _ = tx.send(ControlMessage::RetryTrack { track, pos });
+                            }
+                            ErrorAction::Skip => {
+                                consecutive_skips += 1;
+                                last_failure = None;
+                                if let Some(next) = next {
+                                    let _ = tx.send(ControlMessage::SetTrack(next));
+                                }
+                            }
+                            ErrorAction::Stop => {
+                                consecutive_skips = 0;
+                                last_failure = None;
+                            }
+                        }
+                    }
+                    ProcessToGuiMsg::TrackAdvanced(track) => {
+                        let song = song_by_id(&SONGS.lock().unwrap(), track).cloned();
+
+                        if let Some(song) = song {
+                            set_current_track(song);
+                        }
+
+                        let _ = tx.send(ControlMessage::QueueGaplessAfter(track));
+
+                        if let Err(e) = app_handle.emit_all(TRACK_CHANGED, track_changed_payload(track)) {
+                            println!("error: {:?}", e);
+                        }
                     }
-                    ProcessToGuiMsg::TotalFrames(frames) => {
-                        ttx.try_send(ProcessToGuiMsg::TotalFrames(frames)).unwrap();
+                    ProcessToGuiMsg::TrackStarted(track) => {
+                        if let Err(e) = app_handle.emit_all(TRACK_CHANGED, track_changed_payload(track)) {
+                            println!("error: {:?}", e);
+                        }
+                    }
+                    ProcessToGuiMsg::DropAndNext(finished) => {
+                        consecutive_skips = 0;
+                        last_failure = None;
+
+                        // the track that finished may have been removed from the library while it played,
+                        // the one after it is still where it would have been
+                        let current = CURRENT_TRACK.lock().unwrap().clone();
+                        let next = current.filter(|song| song.id == finished).and_then(|finished| next_track(&SONGS.lock().unwrap(), &finished));
+
+                        println!("dropped stream, finished track {}", finished);
+                        if let Err(e) = app_handle.emit_all("drop-and-next", next) {
+                            println!("error: {:?}", e);
+                        }
+                    }
+                    ProcessToGuiMsg::NowPlaying(now_playing) => {
+                        if let Err(e) = app_handle.emit_all(NOW_PLAYING, now_playing) {
+                            println!("error: {:?}", e);
+                        }
+                    }
+                    ProcessToGuiMsg::StreamStatus(status) => {
+                        if let Err(e) = app_handle.emit_all(STREAM_STATUS, status) {
+                            println!("error: {:?}", e);
+                        }
+                    }
+                    // dropping the old stream here keeps the deallocation off the audio thread
+                    ProcessToGuiMsg::DropOldStream(old_stream) => {
+                        drop(old_stream);
                     }
-                    ProcessToGuiMsg::Buffering(buffering) => {
-                        ttx.try_send(ProcessToGuiMsg::Buffering(buffering)).unwrap();
+                    ProcessToGuiMsg::DropNetworkStream(old_stream) => {
+                        drop(old_stream);
                     }
-                    ProcessToGuiMsg::DropAndNext(last_index) => {
-                        println!("drop and next song u slut. last index was: {}", last_index);
-                        ttx.try_send(ProcessToGuiMsg::DropAndNext(last_index)).unwrap();
+                    ProcessToGuiMsg::DropConvolver(old_convolver) => {
+                        drop(old_convolver);
+                    }
+                    ProcessToGuiMsg::ConvolutionState(state) => {
+                        if let Err(e) = app_handle.emit_all(CONVOLUTION_STATE, state) {
+                            println!("error: {:?}", e);
+                        }
+                    }
+                    ProcessToGuiMsg::DropEffect(old_effect) => {
+                        drop(old_effect);
+                    }
+                    ProcessToGuiMsg::DropSilenceRegions(old_regions) => {
+                        drop(old_regions);
+                    }
+                    ProcessToGuiMsg::EffectChainChanged(chain) => {
+                        if let Err(e) = app_handle.emit_all(EFFECT_CHAIN_CHANGED, chain) {
+                            println!("error: {:?}", e);
+                        }
                     }
-                    _ => {}
                 }
             }
-            //always remember to add some delay here, because else we will send too much events at once to the receiver which causes that the ui gets overwhelemed, and probably several other issues.
-            // test for yourself which delay works for your system best here 
-            std::thread::sleep(Duration::from_millis(10));
-        }
-    });
+        });
+    }
 
     {
         let app_handle = app_handle.clone();
 
         tokio::spawn(async move {
+            let mut last_snapshot: Option<TransportSnapshot> = None;
+            // (track, chapter index) we last told the gui about
+            let mut last_chapter: Option<(Option<TrackId>, usize)> = None;
+
             loop {
-                while let Some(msg) = trx.recv().await {
-                    match msg {
-                        ProcessToGuiMsg::PlaybackPos(pos) => {
-                            app_handle.emit_all("pos-frames", pos).unwrap();
-                        }
-                        ProcessToGuiMsg::TotalFrames(frames) => {
-                            app_handle.emit_all("total-frames", frames).unwrap()
-                        }
-                        ProcessToGuiMsg::Buffering(status) => {
-                            app_handle.emit_all("buffer-status", status).unwrap();
-                        }
-                        ProcessToGuiMsg::DropAndNext(last_index) => {
-                            app_handle.emit_all("drop-and-next", last_index).unwrap();
+                let snapshot = TRANSPORT.snapshot();
+
+                let current_chapter = {
+                    let chapters = CURRENT_CHAPTERS.lock().unwrap();
+                    chapter_at(&chapters, snapshot.pos_frames).cloned()
+                };
+
+                if let Some(chapter) = current_chapter {
+                    let track = CURRENT_TRACK.lock().unwrap().as_ref().map(|song| song.id);
+
+                    if last_chapter != Some((track, chapter.index)) {
+                        last_chapter = Some((track, chapter.index));
+
+                        if let Err(e) = app_handle.emit_all(CHAPTER_CHANGED, &chapter) {
+                            println!("error: {:?}", e);
                         }
-                        _ => {}
                     }
                 }
-                tokio::time::sleep(Duration::from_millis(1)).await;
 
-                
+                if last_snapshot.as_ref() != Some(&snapshot) {
+                    if let Err(e) = app_handle.emit_all(TRANSPORT_STATE, &snapshot) {
+                        println!("error: {:?}", e);
+                    }
+                    last_snapshot = Some(snapshot);
+                }
+
+                tsleep(TRANSPORT.publish_interval()).await;
             }
-    
         });
     }
 
+    tokio::task::spawn(async move {
+        loop {
+            let settings = ResumeSettings::load();
+
+            if settings.enabled && TRANSPORT.snapshot().playback_state == PlaybackState::Playing {
+                save_current_position(&settings);
+            }
+
+            tsleep(Duration::from_secs(settings.save_interval_secs.max(1))).await;
+        }
+    });
+
     {
 
         let app_handle = app_handle.clone();
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::event_handler::SONGS;
+use crate::song_dir::{path_to_string, Song};
+use crate::tags::TrackTags;
+use super::roots::{load_roots, root_for, LibraryRoot};
+use super::TrackId;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BrowseEntry {
+    #[serde(flatten)]
+    pub song: Song,
+    // every track below a folder, not just the ones directly in it. 1 for a track
+    pub track_count: usize,
+    pub duration_secs: f64,
+}
+
+impl BrowseEntry {
+    fn folder(path: &Path) -> Self {
+        let path_string = path_to_string(path);
+
+        Self {
+            song: Song {
+                id: TrackId::of(&path_string),
+                name: path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| path_string.clone()),
+                path: path_string,
+                is_directory: true,
+                codec: None,
+                start_frame: None,
+                end_frame: None,
+                tags: TrackTags::default(),
+            },
+            track_count: 0,
+            duration_secs: 0.0,
+        }
+    }
+
+    fn add(&mut self, song: &Song) {
+        self.track_count += 1;
+        self.duration_secs += song.tags.duration_secs.unwrap_or(0.0);
+    }
+}
+
+fn is_in(song: &Song, folder: &Path) -> bool {
+    Path::new(&song.path).starts_with(folder)
+}
+
+// the tracks below `folder` in library order, subfolders included
+pub fn folder_tracks(songs: &[Song], folder: &Path) -> Vec<TrackId> {
+    songs.iter().filter(|song| is_in(song, folder)).map(|song| song.id).collect()
+}
+
+// the active roots that aren't inside another one, they're what the library starts out with
+fn top_folders(songs: &[Song], roots: &[LibraryRoot]) -> Vec<BrowseEntry> {
+    let dirs: Vec<PathBuf> = roots.iter().filter(|root| root.is_active()).map(|root| root.dir()).collect();
+
+    dirs.iter()
+        .filter(|dir| !dirs.iter().any(|other| other != *dir && dir.starts_with(other)))
+        .map(|dir| {
+            let mut entry = BrowseEntry::folder(dir);
+            songs.iter().filter(|song| is_in(song, dir)).for_each(|song| entry.add(song));
+            entry
+        })
+        .collect()
+}
+
+// the subfolders and tracks directly inside `folder`. folders only show up once there's a track somewhere below them
+fn folder_children(songs: &[Song], folder: &Path) -> Vec<BrowseEntry> {
+    let mut folders: BTreeMap<String, BrowseEntry> = BTreeMap::new();
+    let mut tracks = Vec::new();
+
+    for song in songs {
+        let Ok(relative) = Path::new(&song.path).strip_prefix(folder) else {
+            continue;
+        };
+
+        let mut components = relative.components();
+        let Some(first) = components.next() else {
+            continue;
+        };
+
+        if components.next().is_none() {
+            tracks.push(BrowseEntry { song: song.clone(), track_count: 1, duration_secs: song.tags.duration_secs.unwrap_or(0.0) });
+            continue;
+        }
+
+        let name = first.as_os_str().to_string_lossy().to_string();
+
+        folders
+            .entry(name.clone())
+            .or_insert_with(|| BrowseEntry::folder(&folder.join(&name)))
+            .add(song);
+    }
+
+    folders.into_values().chain(tracks).collect()
+}
+
+// an empty path lists the library roots
+#[tauri::command]
+pub fn browse(path: String) -> Result<Vec<BrowseEntry>, String> {
+    let roots = load_roots().map_err(|e| e.to_string())?;
+
+    if path.trim().is_empty() {
+        return Ok(top_folders(&SONGS.lock().unwrap(), &roots));
+    }
+
+    let folder = PathBuf::from(&path);
+
+    if root_for(&roots, &folder).is_none() {
+        return Err("that folder is not in the library".to_string());
+    }
+
+    Ok(folder_children(&SONGS.lock().unwrap(), &folder))
+}
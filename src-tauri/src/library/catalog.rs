@@ -0,0 +1,373 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::Mutex;
+
+use deunicode::deunicode;
+use serde::{Deserialize, Serialize};
+
+use crate::event_handler::SONGS;
+use crate::song_dir::Song;
+use crate::util::hash::fnv1a_64;
+use super::{song_by_id, TrackId};
+
+const VARIOUS_ARTISTS: &str = "Various Artists";
+const UNKNOWN_ARTIST: &str = "Unknown Artist";
+const DEFAULT_LIMIT: usize = 100;
+
+lazy_static::lazy_static! {
+    // rebuilt every time the library is published, like the search index
+    static ref CATALOG: Mutex<Catalog> = Mutex::new(Catalog::default());
+}
+
+// "Ólafur Arnalds" sorts with the o's
+fn sort_key(text: &str) -> String {
+    deunicode(text).to_lowercase()
+}
+
+fn group_key(text: &str) -> String {
+    text.trim().to_lowercase()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CatalogSort {
+    #[default]
+    Name,
+    Artist,
+    Year,
+    TrackCount,
+    Duration,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListOptions {
+    pub sort: CatalogSort,
+    pub descending: bool,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlbumFilter {
+    // the album artist, or anyone with a track on the album
+    pub artist: Option<String>,
+    pub genre: Option<String>,
+    pub year: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    // before limit and offset
+    pub total: usize,
+    pub items: Vec<T>,
+}
+
+// an artist, genre or year
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CatalogGroup {
+    pub name: String,
+    pub album_count: usize,
+    pub track_count: usize,
+    pub duration_secs: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AlbumSummary {
+    pub id: String,
+    pub title: String,
+    pub artist: String,
+    pub year: Option<u32>,
+    pub genres: Vec<String>,
+    pub track_count: usize,
+    pub disc_count: usize,
+    pub duration_secs: f64,
+    pub compilation: bool,
+    // the folder of the first track
+    pub folder: String,
+}
+
+#[derive(Debug)]
+struct Album {
+    summary: AlbumSummary,
+    // the album artist and everyone on a track, lowercase
+    artists: BTreeSet<String>,
+    // in disc and track order
+    tracks: Vec<TrackId>,
+}
+
+#[derive(Debug, Default)]
+struct Catalog {
+    albums: Vec<Album>,
+    artists: Vec<CatalogGroup>,
+    genres: Vec<CatalogGroup>,
+    years: Vec<CatalogGroup>,
+}
+
+// disc 1 is assumed for tracks without one, tracks without a number go last
+fn album_order(song: &Song) -> (u32, u32, &str, Option<usize>) {
+    (
+        song.tags.disc_number.unwrap_or(1),
+        song.tags.track_number.unwrap_or(u32::MAX),
+        &song.path,
+        song.start_frame,
+    )
+}
+
+fn folder_of(song: &Song) -> String {
+    Path::new(&song.path).parent().map(|dir| dir.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+// with an album artist that's what the album is filed under. without one, tracks of the same album name
+// in the same folder belong together, so a compilation isn't split up into one album per track artist
+fn album_key(song: &Song, album: &str) -> String {
+    match &song.tags.album_artist {
+        Some(album_artist) if !song.tags.compilation => format!("{}\u{0}{}", group_key(album_artist), group_key(album)),
+        _ => format!("{}\u{0}{}", group_key(album), folder_of(song)),
+    }
+}
+
+fn build_album(songs: Vec<&Song>) -> Album {
+    let mut songs = songs;
+    songs.sort_by(|a, b| album_order(a).cmp(&album_order(b)));
+
+    let first = songs[0];
+
+    let track_artists: BTreeSet<String> = songs.iter().filter_map(|song| song.tags.artist.as_deref()).map(group_key).collect();
+    let compilation = songs.iter().any(|song| song.tags.compilation);
+
+    let artist = match songs.iter().find_map(|song| song.tags.album_artist.clone()) {
+        Some(album_artist) => album_artist,
+        None if compilation || track_artists.len() > 1 => VARIOUS_ARTISTS.to_string(),
+        None => first.tags.artist.clone().unwrap_or_else(|| UNKNOWN_ARTIST.to_string()),
+    };
+
+    let title = first.tags.album.clone().unwrap_or_default();
+
+    let mut genres: Vec<String> = Vec::new();
+    for genre in songs.iter().filter_map(|song| song.tags.genre.as_ref()) {
+        if !genres.iter().any(|known| group_key(known) == group_key(genre)) {
+            genres.push(genre.clone());
+        }
+    }
+
+    let mut artists = track_artists;
+    artists.insert(group_key(&artist));
+
+    let summary = AlbumSummary {
+        id: format!("{:016x}", fnv1a_64(album_key(first, &title).as_bytes())),
+        year: songs.iter().filter_map(|song| song.tags.year).min(),
+        track_count: songs.len(),
+        disc_count: songs.iter().map(|song| song.tags.disc_number.unwrap_or(1)).collect::<BTreeSet<_>>().len(),
+        duration_secs: songs.iter().filter_map(|song| song.tags.duration_secs).sum(),
+        folder: folder_of(first),
+        title,
+        artist,
+        genres,
+        compilation,
+    };
+
+    Album { summary, artists, tracks: songs.iter().map(|song| song.id).collect() }
+}
+
+#[derive(Default)]
+struct GroupBuilder {
+    groups: HashMap<String, (CatalogGroup, BTreeSet<String>)>,
+}
+
+impl GroupBuilder {
+    fn add(&mut self, name: &str, song: &Song, album_id: Option<&str>) {
+        let (group, albums) = self.groups.entry(group_key(name)).or_insert_with(|| {
+            (CatalogGroup { name: name.trim().to_string(), ..Default::default() }, BTreeSet::new())
+        });
+
+        group.track_count += 1;
+        group.duration_secs += song.tags.duration_secs.unwrap_or(0.0);
+
+        if let Some(album_id) = album_id {
+            albums.insert(album_id.to_string());
+        }
+    }
+
+    fn build(self) -> Vec<CatalogGroup> {
+        self.groups
+            .into_values()
+            .map(|(mut group, albums)| {
+                group.album_count = albums.len();
+                group
+            })
+            .collect()
+    }
+}
+
+impl Catalog {
+    fn build(songs: &[Song]) -> Self {
+        let mut by_album: HashMap<String, Vec<&Song>> = HashMap::new();
+
+        for song in songs {
+            if let Some(album) = &song.tags.album {
+                by_album.entry(album_key(song, album)).or_default().push(song);
+            }
+        }
+
+        let albums: Vec<Album> = by_album.into_values().map(build_album).collect();
+
+        let album_of: HashMap<TrackId, &Album> = albums
+            .iter()
+            .flat_map(|album| album.tracks.iter().map(move |track| (*track, album)))
+            .collect();
+
+        let mut artists = GroupBuilder::default();
+        let mut genres = GroupBuilder::default();
+        let mut years = GroupBuilder::default();
+
+        for song in songs {
+            let album = album_of.get(&song.id);
+            let album_id = album.map(|album| album.summary.id.as_str());
+
+            // grouped by album artist, so a compilation shows up once under various artists
+            let artist = album
+                .map(|album| album.summary.artist.as_str())
+                .or(song.tags.artist.as_deref())
+                .unwrap_or(UNKNOWN_ARTIST);
+            artists.add(artist, song, album_id);
+
+            if let Some(genre) = &song.tags.genre {
+                genres.add(genre, song, album_id);
+            }
+
+            if let Some(year) = song.tags.year {
+                years.add(&year.to_string(), song, album_id);
+            }
+        }
+
+        Self {
+            albums,
+            artists: artists.build(),
+            genres: genres.build(),
+            years: years.build(),
+        }
+    }
+}
+
+pub fn rebuild_catalog(songs: &[Song]) {
+    let catalog = Catalog::build(songs);
+
+    *CATALOG.lock().unwrap() = catalog;
+}
+
+fn page<T: Clone>(mut items: Vec<&T>, options: &ListOptions, compare: impl Fn(&T, &T) -> Ordering) -> Page<T> {
+    items.sort_by(|a, b| {
+        let ordering = compare(a, b);
+        if options.descending { ordering.reverse() } else { ordering }
+    });
+
+    Page {
+        total: items.len(),
+        items: items
+            .into_iter()
+            .skip(options.offset)
+            .take(options.limit.unwrap_or(DEFAULT_LIMIT))
+            .cloned()
+            .collect(),
+    }
+}
+
+fn compare_groups(sort: CatalogSort, a: &CatalogGroup, b: &CatalogGroup) -> Ordering {
+    let by_name = || sort_key(&a.name).cmp(&sort_key(&b.name));
+
+    match sort {
+        CatalogSort::TrackCount => a.track_count.cmp(&b.track_count).then_with(by_name),
+        CatalogSort::Duration => a.duration_secs.total_cmp(&b.duration_secs).then_with(by_name),
+        // years are named after themselves, all four digits, so the name is the year order as well
+        CatalogSort::Name | CatalogSort::Artist | CatalogSort::Year => by_name(),
+    }
+}
+
+fn list_groups(groups: impl Fn(&Catalog) -> &Vec<CatalogGroup>, options: Option<ListOptions>) -> Page<CatalogGroup> {
+    let options = options.unwrap_or_default();
+    let catalog = CATALOG.lock().unwrap();
+
+    page(groups(&catalog).iter().collect(), &options, |a, b| compare_groups(options.sort, a, b))
+}
+
+fn compare_albums(sort: CatalogSort, a: &AlbumSummary, b: &AlbumSummary) -> Ordering {
+    let by_title = || sort_key(&a.title).cmp(&sort_key(&b.title));
+    let by_artist = || sort_key(&a.artist).cmp(&sort_key(&b.artist));
+    // albums without a year go last
+    let by_year = || a.year.unwrap_or(u32::MAX).cmp(&b.year.unwrap_or(u32::MAX));
+
+    match sort {
+        CatalogSort::Name => by_title().then_with(by_artist),
+        CatalogSort::Artist => by_artist().then_with(by_year).then_with(by_title),
+        CatalogSort::Year => by_year().then_with(by_artist).then_with(by_title),
+        CatalogSort::TrackCount => a.track_count.cmp(&b.track_count).then_with(by_title),
+        CatalogSort::Duration => a.duration_secs.total_cmp(&b.duration_secs).then_with(by_title),
+    }
+}
+
+impl Album {
+    fn matches(&self, filter: &AlbumFilter) -> bool {
+        let artist_ok = filter.artist.as_deref().is_none_or(|artist| self.artists.contains(&group_key(artist)));
+        let genre_ok = filter
+            .genre
+            .as_deref()
+            .is_none_or(|genre| self.summary.genres.iter().any(|known| group_key(known) == group_key(genre)));
+        let year_ok = filter.year.is_none_or(|year| self.summary.year == Some(year));
+
+        artist_ok && genre_ok && year_ok
+    }
+}
+
+// the tracks of an album in disc and track order
+pub fn album_tracks(album_id: &str) -> Vec<TrackId> {
+    CATALOG
+        .lock()
+        .unwrap()
+        .albums
+        .iter()
+        .find(|album| album.summary.id == album_id)
+        .map(|album| album.tracks.clone())
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn list_artists(options: Option<ListOptions>) -> Result<Page<CatalogGroup>, String> {
+    Ok(list_groups(|catalog| &catalog.artists, options))
+}
+
+#[tauri::command]
+pub fn list_genres(options: Option<ListOptions>) -> Result<Page<CatalogGroup>, String> {
+    Ok(list_groups(|catalog| &catalog.genres, options))
+}
+
+#[tauri::command]
+pub fn list_years(options: Option<ListOptions>) -> Result<Page<CatalogGroup>, String> {
+    Ok(list_groups(|catalog| &catalog.years, options))
+}
+
+#[tauri::command]
+pub fn list_albums(filter: Option<AlbumFilter>, options: Option<ListOptions>) -> Result<Page<AlbumSummary>, String> {
+    let filter = filter.unwrap_or_default();
+    let options = options.unwrap_or_default();
+    let catalog = CATALOG.lock().unwrap();
+
+    let albums = catalog.albums.iter().filter(|album| album.matches(&filter)).map(|album| &album.summary).collect();
+
+    Ok(page(albums, &options, |a, b| compare_albums(options.sort, a, b)))
+}
+
+#[tauri::command]
+pub fn get_album_tracks(album_id: String) -> Result<Vec<Song>, String> {
+    let tracks = album_tracks(&album_id);
+
+    if tracks.is_empty() {
+        return Err("that album is not in the library".to_string());
+    }
+
+    let songs = SONGS.lock().unwrap();
+
+    Ok(tracks.into_iter().filter_map(|track| song_by_id(&songs, track).cloned()).collect())
+}
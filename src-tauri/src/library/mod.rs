@@ -0,0 +1,520 @@
+pub mod browse;
+pub mod catalog;
+pub mod roots;
+pub mod scan;
+pub mod search;
+pub mod watch;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use rayon::prelude::*;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::audio_backend::probe::UnsupportedFile;
+use crate::event_handler::{SONGS, UNSUPPORTED_FILES};
+use crate::song_dir::{path_to_string, scan_source, walk_dir, walk_dir_shallow, ScanSource, ScanWarning, Song, WalkOptions, WalkResult};
+use crate::util::hash::fnv1a_64;
+use crate::cover_art::warm_thumbnail;
+use roots::{root_for, LibraryRoot};
+use scan::{ScanSettings, ScanTracker};
+use catalog::rebuild_catalog;
+use search::rebuild_search_index;
+use crate::util::lmdb::records::{delete_records, list_records, store_records};
+
+const TRACK_TABLE: &str = "library-track";
+const SOURCE_TABLE: &str = "library-source";
+// bumped whenever a scan reads something new out of the files, like the compilation flag, or track ids
+// are made differently, so sources indexed before that are probed again even though they haven't changed
+const INDEX_VERSION: u32 = 2;
+
+// how often the folder is checked for changes when it can't be watched, which only reads file metadata
+pub const RESCAN_INTERVAL: Duration = Duration::from_secs(60);
+
+// stays the same for as long as the file stays where it is, whatever else happens to the library
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TrackId(pub u64);
+
+impl TrackId {
+    pub fn of(path: &str) -> Self {
+        TrackId(fnv1a_64(path.as_bytes()))
+    }
+
+    // cue tracks share their file, the start frame tells them apart. two sheets can point at the same file,
+    // so the sheet is part of it as well
+    pub fn of_cue_track(cue_path: &str, path: &str, start_frame: usize) -> Self {
+        TrackId(fnv1a_64(format!("{}|{}#{}", cue_path, path, start_frame).as_bytes()))
+    }
+}
+
+impl fmt::Display for TrackId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for TrackId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16).map(TrackId)
+    }
+}
+
+// javascript numbers can't hold all 64 bits, so ids are hex strings outside of rust
+impl Serialize for TrackId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TrackId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedTrack {
+    pub id: TrackId,
+    // the audio file or cue sheet the track was found through
+    pub source: String,
+    pub size: u64,
+    pub modified: u64,
+    pub song: Song,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedSource {
+    pub path: String,
+    pub size: u64,
+    pub modified: u64,
+    pub track_ids: Vec<TrackId>,
+    pub unsupported: Vec<UnsupportedFile>,
+    // INDEX_VERSION when it was scanned, 0 for anything from before versions existed
+    #[serde(default)]
+    pub version: u32,
+}
+
+// what a rescan changed, track by track
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct LibraryDiff {
+    pub added: Vec<IndexedTrack>,
+    pub updated: Vec<IndexedTrack>,
+    pub removed: Vec<TrackId>,
+}
+
+impl LibraryDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+// what's in lmdb, kept in memory so a rescan doesn't have to read the whole index back first
+#[derive(Default)]
+struct LibraryIndex {
+    // by path
+    sources: HashMap<String, IndexedSource>,
+    // by track id
+    tracks: HashMap<TrackId, IndexedTrack>,
+    // roots that are disabled or not plugged in, their tracks stay indexed but aren't listed
+    hidden_roots: Vec<PathBuf>,
+    // from the last scan of each folder
+    warnings: Vec<ScanWarning>,
+}
+
+lazy_static::lazy_static! {
+    static ref INDEX: Mutex<LibraryIndex> = Mutex::new(LibraryIndex::default());
+    // where each track sits in SONGS, only ever replaced together with it while SONGS is locked
+    static ref SONG_POSITIONS: Mutex<HashMap<TrackId, usize>> = Mutex::new(HashMap::new());
+}
+
+fn source_key(path: &str) -> String {
+    format!("{:016x}", fnv1a_64(path.as_bytes()))
+}
+
+// the library is sorted by this, see publish. the id only matters for cue tracks from different sheets
+fn library_order(song: &Song) -> (&str, Option<usize>, TrackId) {
+    (&song.path, song.start_frame, song.id)
+}
+
+// where `song` is or would be in the library, so next and previous still work for a track
+// that was removed while it was playing
+fn library_position(songs: &[Song], song: &Song) -> Result<usize, usize> {
+    songs.binary_search_by(|probe| library_order(probe).cmp(&library_order(song)))
+}
+
+// `songs` is what's in SONGS, the positions are only right for that
+pub fn song_by_id(songs: &[Song], id: TrackId) -> Option<&Song> {
+    let position = *SONG_POSITIONS.lock().unwrap().get(&id)?;
+
+    songs.get(position).filter(|song| song.id == id)
+}
+
+pub fn next_song<'a>(songs: &'a [Song], current: &Song) -> Option<&'a Song> {
+    let position = match library_position(songs, current) {
+        Ok(position) => position + 1,
+        Err(position) => position,
+    };
+
+    songs.get(position)
+}
+
+pub fn prev_song<'a>(songs: &'a [Song], current: &Song) -> Option<&'a Song> {
+    let (Ok(position) | Err(position)) = library_position(songs, current);
+
+    position.checked_sub(1).and_then(|position| songs.get(position))
+}
+
+fn is_hidden(index: &LibraryIndex, path: &str) -> bool {
+    index.hidden_roots.iter().any(|dir| Path::new(path).starts_with(dir))
+}
+
+fn publish(index: &LibraryIndex) {
+    let mut songs: Vec<&IndexedTrack> = index
+        .tracks
+        .values()
+        .filter(|track| !is_hidden(index, &track.source))
+        .collect();
+    songs.sort_by(|a, b| library_order(&a.song).cmp(&library_order(&b.song)));
+
+    let mut unsupported: Vec<UnsupportedFile> = index
+        .sources
+        .values()
+        .filter(|source| !is_hidden(index, &source.path))
+        .flat_map(|source| source.unsupported.iter().cloned())
+        .collect();
+    unsupported.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let songs: Vec<Song> = songs.into_iter().map(|track| track.song.clone()).collect();
+    rebuild_search_index(&songs);
+    rebuild_catalog(&songs);
+
+    let positions = songs.iter().enumerate().map(|(position, song)| (song.id, position)).collect();
+
+    // SONGS is locked first, like everywhere song_by_id is called, so nobody sees one without the other
+    let mut songs_guard = SONGS.lock().unwrap();
+    *SONG_POSITIONS.lock().unwrap() = positions;
+    *songs_guard = songs;
+    drop(songs_guard);
+
+    *UNSUPPORTED_FILES.lock().unwrap() = unsupported;
+}
+
+// whatever was indexed last time is there right away, before anything on disk is looked at
+pub fn load_index(roots: &[LibraryRoot]) -> Result<()> {
+    let sources = list_records::<IndexedSource>(SOURCE_TABLE)?;
+    let tracks = list_records::<IndexedTrack>(TRACK_TABLE)?;
+
+    let mut index = INDEX.lock().unwrap();
+
+    index.sources = sources.into_iter().map(|(_, source)| (source.path.clone(), source)).collect();
+    // songs indexed before they had ids get theirs from the record
+    index.tracks = tracks
+        .into_iter()
+        .map(|(_, mut track)| {
+            track.song.id = track.id;
+            (track.id, track)
+        })
+        .collect();
+    index.hidden_roots = roots.iter().filter(|root| !root.is_active()).map(|root| root.dir()).collect();
+
+    println!("loaded {} tracks from the library index", index.tracks.len());
+
+    publish(&index);
+
+    Ok(())
+}
+
+fn is_stale(index: &LibraryIndex, source: &ScanSource) -> bool {
+    match index.sources.get(&path_to_string(&source.path)) {
+        Some(indexed) => indexed.version != INDEX_VERSION || indexed.size != source.size || indexed.modified != source.modified,
+        None => true,
+    }
+}
+
+// walks every active root and only probes what's new or changed since it was indexed
+pub fn update_index(roots: &[LibraryRoot], tracker: &ScanTracker) -> Result<LibraryDiff> {
+    ScanSettings::load().thread_pool()?.install(|| update_index_on_pool(roots, tracker))
+}
+
+fn update_index_on_pool(roots: &[LibraryRoot], tracker: &ScanTracker) -> Result<LibraryDiff> {
+    let mut walk = WalkResult::default();
+    let mut walked: Vec<PathBuf> = Vec::new();
+
+    for root in roots.iter().filter(|root| root.is_active()) {
+        match root.walk_options() {
+            Ok(options) => {
+                walk.extend(walk_dir(&root.dir(), &options, tracker));
+                walked.push(root.dir());
+            }
+            Err(e) => walk.warnings.push(ScanWarning {
+                path: root.path.clone(),
+                message: format!("not scanned: {}", e),
+            }),
+        }
+    }
+
+    // an unplugged drive looks like an empty folder, which would throw all of its tracks away.
+    // those and disabled roots keep their tracks indexed, hidden until they're back
+    let hidden: Vec<PathBuf> = roots
+        .iter()
+        .map(|root| root.dir())
+        .filter(|dir| !walked.contains(dir))
+        .collect();
+
+    // half a walk can't tell which files are gone
+    if tracker.is_cancelled() {
+        return Ok(LibraryDiff::default());
+    }
+
+    dedup_sources(&mut walk.sources);
+
+    // anything indexed that isn't under any root anymore goes too, apart from what's in folders that couldn't be read
+    let unreadable = walk.unreadable;
+    let diff = apply_walk(
+        walk.sources,
+        |path| !hidden.iter().any(|dir| path.starts_with(dir)) && !unreadable.iter().any(|dir| path.starts_with(dir)),
+        tracker,
+    )?;
+    warm_covers(&diff, tracker);
+
+    let mut index = INDEX.lock().unwrap();
+    index.hidden_roots = hidden;
+    index.warnings = walk.warnings;
+    publish(&index);
+
+    Ok(diff)
+}
+
+// nested roots and a folder changing along with the one above it both find the same files twice
+fn dedup_sources(sources: &mut Vec<ScanSource>) {
+    let mut seen = HashSet::new();
+    sources.retain(|source| seen.insert(source.path.clone()));
+}
+
+// like update_index, but only looks at the folders the changed paths are in
+pub fn update_paths(roots: &[LibraryRoot], changed: &HashSet<PathBuf>, tracker: &ScanTracker) -> Result<LibraryDiff> {
+    ScanSettings::load().thread_pool()?.install(|| update_paths_on_pool(roots, changed, tracker))
+}
+
+fn update_paths_on_pool(roots: &[LibraryRoot], changed: &HashSet<PathBuf>, tracker: &ScanTracker) -> Result<LibraryDiff> {
+    // by root folder, building the exclude globs once per change instead of once per path
+    let mut options: HashMap<PathBuf, WalkOptions> = HashMap::new();
+    // directories that appeared or disappeared, everything below them is checked
+    let mut subtrees: Vec<(PathBuf, PathBuf)> = Vec::new();
+    // directories where only the files directly inside are checked
+    let mut folders: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+    for path in changed {
+        let Some(root) = root_for(roots, path) else {
+            continue;
+        };
+
+        let root_dir = root.dir();
+
+        if !options.contains_key(&root_dir) {
+            options.insert(root_dir.clone(), root.walk_options()?);
+        }
+
+        // never indexed, so there's nothing to add or remove
+        if options[&root_dir].excludes(path) {
+            continue;
+        }
+
+        if path.is_dir() {
+            subtrees.push((path.clone(), root_dir));
+            continue;
+        }
+
+        // a removed path could have been a whole folder
+        if !path.exists() {
+            subtrees.push((path.clone(), root_dir.clone()));
+        }
+
+        if let Some(parent) = path.parent() {
+            folders.push((parent.to_path_buf(), root_dir));
+        }
+    }
+
+    folders.sort_by(|a, b| a.0.cmp(&b.0));
+    folders.dedup_by(|a, b| a.0 == b.0);
+
+    let mut walk = WalkResult::default();
+
+    for (dir, root_dir) in subtrees.iter().filter(|(dir, _)| dir.is_dir()) {
+        walk.extend(walk_dir(dir, &options[root_dir], tracker));
+    }
+
+    for (dir, root_dir) in folders.iter().filter(|(dir, _)| dir.is_dir()) {
+        walk.extend(walk_dir_shallow(dir, &options[root_dir], tracker));
+    }
+
+    if tracker.is_cancelled() {
+        return Ok(LibraryDiff::default());
+    }
+
+    dedup_sources(&mut walk.sources);
+
+    let unreadable = walk.unreadable;
+    let in_scope = |path: &Path| {
+        let changed = subtrees.iter().any(|(dir, _)| path.starts_with(dir))
+            || folders.iter().any(|(dir, _)| path.parent() == Some(dir.as_path()));
+
+        changed && !unreadable.iter().any(|dir| path.starts_with(dir))
+    };
+
+    let diff = apply_walk(walk.sources, in_scope, tracker)?;
+    warm_covers(&diff, tracker);
+
+    let mut index = INDEX.lock().unwrap();
+    index.warnings.retain(|warning| !in_scope(Path::new(&warning.path)));
+    index.warnings.extend(walk.warnings);
+    publish(&index);
+
+    Ok(diff)
+}
+
+// cover thumbnails of what was just scanned, while we're on the pool anyway
+fn warm_covers(diff: &LibraryDiff, tracker: &ScanTracker) {
+    let paths: HashSet<&str> = diff.added.iter().chain(&diff.updated).map(|track| track.song.path.as_str()).collect();
+
+    paths.into_par_iter().for_each(|path| {
+        if !tracker.is_cancelled() {
+            warm_thumbnail(Path::new(path));
+        }
+    });
+}
+
+// brings the part of the index `in_scope` accepts in line with what was found on disk there
+fn apply_walk(on_disk: Vec<ScanSource>, in_scope: impl Fn(&Path) -> bool, tracker: &ScanTracker) -> Result<LibraryDiff> {
+    let (stale, gone) = {
+        let index = INDEX.lock().unwrap();
+
+        let stale: Vec<&ScanSource> = on_disk.iter().filter(|source| is_stale(&index, source)).collect();
+
+        let seen: HashSet<String> = on_disk.iter().map(|source| path_to_string(&source.path)).collect();
+        let gone: Vec<String> = index
+            .sources
+            .keys()
+            .filter(|path| in_scope(Path::new(path)) && !seen.contains(*path))
+            .cloned()
+            .collect();
+
+        (stale, gone)
+    };
+
+    let mut diff = LibraryDiff::default();
+
+    if stale.is_empty() && gone.is_empty() {
+        return Ok(diff);
+    }
+
+    tracker.start_processing(stale.len());
+
+    // the expensive part, spread over the pool and done without holding the index so the library stays readable.
+    // a cancelled scan still keeps what was read up to then, the rest is picked up by the next one
+    let scanned: Vec<(&ScanSource, _)> = stale
+        .into_par_iter()
+        .filter_map(|source| {
+            if tracker.is_cancelled() {
+                return None;
+            }
+
+            let result = scan_source(source);
+            tracker.processed(&source.path);
+
+            Some((source, result))
+        })
+        .collect();
+
+    let mut index = INDEX.lock().unwrap();
+
+    let mut deleted_tracks: Vec<String> = Vec::new();
+    let mut deleted_sources: Vec<String> = Vec::new();
+    let mut stored_tracks: Vec<(String, IndexedTrack)> = Vec::new();
+    let mut stored_sources: Vec<(String, IndexedSource)> = Vec::new();
+
+    for path in gone {
+        if let Some(source) = index.sources.remove(&path) {
+            for id in source.track_ids {
+                index.tracks.remove(&id);
+                deleted_tracks.push(id.to_string());
+                diff.removed.push(id);
+            }
+
+            deleted_sources.push(source_key(&path));
+        }
+    }
+
+    for (source, result) in scanned {
+        let path = path_to_string(&source.path);
+
+        let previous_ids: HashSet<TrackId> = index
+            .sources
+            .remove(&path)
+            .map(|previous| previous.track_ids.into_iter().collect())
+            .unwrap_or_default();
+
+        let tracks: Vec<IndexedTrack> = result
+            .songs
+            .into_iter()
+            .map(|song| IndexedTrack {
+                id: song.id,
+                source: path.clone(),
+                size: source.size,
+                modified: source.modified,
+                song,
+            })
+            .collect();
+
+        let track_ids: Vec<TrackId> = tracks.iter().map(|track| track.id).collect();
+
+        for id in previous_ids.iter().filter(|id| !track_ids.contains(id)) {
+            index.tracks.remove(id);
+            deleted_tracks.push(id.to_string());
+            diff.removed.push(*id);
+        }
+
+        for track in tracks {
+            stored_tracks.push((track.id.to_string(), track.clone()));
+
+            if previous_ids.contains(&track.id) {
+                diff.updated.push(track.clone());
+            } else {
+                diff.added.push(track.clone());
+            }
+
+            index.tracks.insert(track.id, track);
+        }
+
+        let indexed = IndexedSource {
+            path: path.clone(),
+            size: source.size,
+            modified: source.modified,
+            track_ids,
+            unsupported: result.unsupported,
+            version: INDEX_VERSION,
+        };
+
+        stored_sources.push((source_key(&path), indexed.clone()));
+        index.sources.insert(path, indexed);
+    }
+
+    delete_records(TRACK_TABLE, &deleted_tracks)?;
+    delete_records(SOURCE_TABLE, &deleted_sources)?;
+    store_records(TRACK_TABLE, &stored_tracks)?;
+    store_records(SOURCE_TABLE, &stored_sources)?;
+
+    Ok(diff)
+}
+
+#[tauri::command]
+pub fn get_scan_warnings() -> Result<Vec<ScanWarning>, String> {
+    Ok(INDEX.lock().unwrap().warnings.clone())
+}
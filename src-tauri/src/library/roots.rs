@@ -0,0 +1,183 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::song_dir::{path_to_string, WalkOptions};
+use crate::util::hash::fnv1a_64;
+use crate::util::lmdb::audio_files_dir::get_songs_directory;
+use crate::util::lmdb::records::{delete_record, get_record, list_records, store_record};
+use crate::util::lmdb::settings::{get_setting, store_setting};
+
+const ROOTS_TABLE: &str = "library-root";
+const ROOTS_MIGRATED: &str = "library-roots-migrated";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LibraryRoot {
+    pub id: String,
+    pub path: String,
+    pub enabled: bool,
+    // folders below the root that are still looked into, None for no limit and 0 for only the root itself
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    // globs relative to the root, e.g. "**/Samples/**" or "*.tmp.flac"
+    pub exclude: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryRootStatus {
+    #[serde(flatten)]
+    pub root: LibraryRoot,
+    // false while the folder isn't there, e.g. an external drive that isn't plugged in
+    pub available: bool,
+}
+
+fn root_id(path: &str) -> String {
+    format!("{:016x}", fnv1a_64(path.as_bytes()))
+}
+
+fn exclude_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+
+    Ok(builder.build()?)
+}
+
+impl LibraryRoot {
+    pub fn new(path: &str) -> Self {
+        let path = path_to_string(Path::new(path.trim()));
+
+        Self {
+            id: root_id(&path),
+            path,
+            enabled: true,
+            max_depth: None,
+            follow_symlinks: false,
+            exclude: Vec::new(),
+        }
+    }
+
+    pub fn dir(&self) -> PathBuf {
+        PathBuf::from(&self.path)
+    }
+
+    // a drive that isn't mounted leaves an empty folder behind, which would look like every track was deleted.
+    // a root with nothing in it at all is treated as unplugged, its tracks are kept until something shows up
+    pub fn is_available(&self) -> bool {
+        fs::read_dir(self.dir()).is_ok_and(|mut entries| entries.next().is_some())
+    }
+
+    // disabled and unplugged folders keep their tracks in the index, they just aren't scanned or shown
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.is_available()
+    }
+
+    pub fn walk_options(&self) -> Result<WalkOptions> {
+        Ok(WalkOptions {
+            root: self.dir(),
+            max_depth: self.max_depth,
+            follow_symlinks: self.follow_symlinks,
+            exclude: exclude_set(&self.exclude)?,
+        })
+    }
+}
+
+// the single song dir from before there were several roots becomes the first one, once,
+// so removing every root later doesn't bring it back
+fn migrate_song_dir() -> Result<()> {
+    if get_setting::<bool>(ROOTS_MIGRATED)?.unwrap_or(false) {
+        return Ok(());
+    }
+
+    if let Some(song_dir) = get_songs_directory()? {
+        let root = LibraryRoot::new(&song_dir);
+        store_record(ROOTS_TABLE, &root.id, &root)?;
+    }
+
+    store_setting(ROOTS_MIGRATED, &true)
+}
+
+pub fn load_roots() -> Result<Vec<LibraryRoot>> {
+    migrate_song_dir()?;
+
+    let mut roots: Vec<LibraryRoot> = list_records::<LibraryRoot>(ROOTS_TABLE)?
+        .into_iter()
+        .map(|(_, root)| root)
+        .collect();
+
+    roots.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(roots)
+}
+
+// the innermost active root `path` is in, nested roots are allowed
+pub fn root_for<'a>(roots: &'a [LibraryRoot], path: &Path) -> Option<&'a LibraryRoot> {
+    roots
+        .iter()
+        .filter(|root| root.is_active() && path.starts_with(root.dir()))
+        .max_by_key(|root| root.path.len())
+}
+
+pub fn add_root(path: &str) -> Result<LibraryRoot> {
+    let root = LibraryRoot::new(path);
+
+    if let Some(existing) = get_record::<LibraryRoot>(ROOTS_TABLE, &root.id)? {
+        return Ok(existing);
+    }
+
+    store_record(ROOTS_TABLE, &root.id, &root)?;
+
+    Ok(root)
+}
+
+fn status(root: LibraryRoot) -> LibraryRootStatus {
+    let available = root.is_available();
+
+    LibraryRootStatus { root, available }
+}
+
+#[tauri::command]
+pub fn list_library_roots() -> Result<Vec<LibraryRootStatus>, String> {
+    Ok(load_roots().map_err(|e| e.to_string())?.into_iter().map(status).collect())
+}
+
+#[tauri::command]
+pub fn add_library_root(path: String) -> Result<LibraryRootStatus, String> {
+    if path.trim().is_empty() {
+        return Err("no folder given".to_string());
+    }
+
+    // the old song dir has to be migrated before it's added to
+    migrate_song_dir().map_err(|e| e.to_string())?;
+
+    add_root(&path).map(status).map_err(|e| e.to_string())
+}
+
+// enabled, depth, symlinks and excludes can change, the path is what identifies a root
+#[tauri::command]
+pub fn update_library_root(root: LibraryRoot) -> Result<LibraryRootStatus, String> {
+    let mut stored = get_record::<LibraryRoot>(ROOTS_TABLE, &root.id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "that folder is not in the library".to_string())?;
+
+    exclude_set(&root.exclude).map_err(|e| e.to_string())?;
+
+    stored.enabled = root.enabled;
+    stored.max_depth = root.max_depth;
+    stored.follow_symlinks = root.follow_symlinks;
+    stored.exclude = root.exclude;
+
+    store_record(ROOTS_TABLE, &stored.id, &stored).map_err(|e| e.to_string())?;
+
+    Ok(status(stored))
+}
+
+#[tauri::command]
+pub fn remove_library_root(id: String) -> Result<(), String> {
+    delete_record(ROOTS_TABLE, &id).map_err(|e| e.to_string())
+}
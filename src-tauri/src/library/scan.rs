@@ -0,0 +1,170 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::song_dir::path_to_string;
+use crate::util::lmdb::settings::{get_setting, store_setting};
+
+const SCAN_SETTINGS: &str = "scan-settings";
+
+// a big library finds thousands of files a second, the gui doesn't need to hear about every one
+const EMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+// at most one scan runs at a time, all of them go through the library watcher thread
+static CANCEL_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ScanSettings {
+    // 0 picks one per cpu. libraries on a nas are mostly waiting on the network, more threads than cpus help there
+    pub threads: usize,
+}
+
+impl ScanSettings {
+    pub fn load() -> Self {
+        match get_setting::<ScanSettings>(SCAN_SETTINGS) {
+            Ok(Some(settings)) => settings,
+            Ok(None) => ScanSettings::default(),
+            Err(e) => {
+                println!("error: {:?}", e);
+                ScanSettings::default()
+            }
+        }
+    }
+
+    pub fn store(&self) -> Result<()> {
+        store_setting(SCAN_SETTINGS, self)
+    }
+
+    pub fn thread_pool(&self) -> Result<ThreadPool> {
+        Ok(ThreadPoolBuilder::new()
+            .num_threads(self.threads)
+            .thread_name(|index| format!("library-scan-{}", index))
+            .build()?)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanProgress {
+    // files found while walking the folders
+    pub files_seen: usize,
+    // of those, the ones that were new or changed and had to be read
+    pub files_to_process: usize,
+    pub files_processed: usize,
+    pub current_path: Option<String>,
+    // None until reading has been going for long enough to tell
+    pub eta_secs: Option<f64>,
+    pub done: bool,
+    pub cancelled: bool,
+}
+
+// shared by every worker of a scan, reports through `emit` now and then
+pub struct ScanTracker {
+    files_seen: AtomicUsize,
+    files_to_process: AtomicUsize,
+    files_processed: AtomicUsize,
+    current_path: Mutex<Option<String>>,
+    processing_started: Mutex<Option<Instant>>,
+    last_emit: Mutex<Instant>,
+    emit: Box<dyn Fn(ScanProgress) + Send + Sync>,
+}
+
+impl ScanTracker {
+    pub fn new(emit: impl Fn(ScanProgress) + Send + Sync + 'static) -> Self {
+        // a cancel that came in while nothing was running isn't meant for this scan
+        CANCEL_REQUESTED.store(false, Ordering::Release);
+
+        Self {
+            files_seen: AtomicUsize::new(0),
+            files_to_process: AtomicUsize::new(0),
+            files_processed: AtomicUsize::new(0),
+            current_path: Mutex::new(None),
+            processing_started: Mutex::new(None),
+            last_emit: Mutex::new(Instant::now()),
+            emit: Box::new(emit),
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        CANCEL_REQUESTED.load(Ordering::Acquire)
+    }
+
+    pub fn saw(&self, path: &Path) {
+        self.files_seen.fetch_add(1, Ordering::Relaxed);
+        self.report(path);
+    }
+
+    pub fn start_processing(&self, count: usize) {
+        self.files_to_process.fetch_add(count, Ordering::Relaxed);
+        self.processing_started.lock().unwrap().get_or_insert_with(Instant::now);
+    }
+
+    pub fn processed(&self, path: &Path) {
+        self.files_processed.fetch_add(1, Ordering::Relaxed);
+        self.report(path);
+    }
+
+    pub fn finish(&self) {
+        (self.emit)(self.progress(true));
+    }
+
+    fn report(&self, path: &Path) {
+        let mut last_emit = match self.last_emit.try_lock() {
+            Ok(last_emit) => last_emit,
+            // another worker is reporting right now, that's recent enough
+            Err(_) => return,
+        };
+
+        if last_emit.elapsed() < EMIT_INTERVAL {
+            return;
+        }
+
+        *last_emit = Instant::now();
+        *self.current_path.lock().unwrap() = Some(path_to_string(path));
+
+        (self.emit)(self.progress(false));
+    }
+
+    fn progress(&self, done: bool) -> ScanProgress {
+        let to_process = self.files_to_process.load(Ordering::Relaxed);
+        let processed = self.files_processed.load(Ordering::Relaxed);
+
+        let eta_secs = self.processing_started.lock().unwrap().and_then(|started| {
+            let elapsed = started.elapsed().as_secs_f64();
+
+            (processed > 0 && !done).then(|| elapsed / processed as f64 * to_process.saturating_sub(processed) as f64)
+        });
+
+        ScanProgress {
+            files_seen: self.files_seen.load(Ordering::Relaxed),
+            files_to_process: to_process,
+            files_processed: processed,
+            current_path: if done { None } else { self.current_path.lock().unwrap().clone() },
+            eta_secs,
+            done,
+            cancelled: self.is_cancelled(),
+        }
+    }
+}
+
+#[tauri::command]
+pub fn cancel_scan() -> Result<(), String> {
+    CANCEL_REQUESTED.store(true, Ordering::Release);
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_scan_settings() -> Result<ScanSettings, String> {
+    Ok(ScanSettings::load())
+}
+
+// used from the next scan on
+#[tauri::command]
+pub fn set_scan_settings(settings: ScanSettings) -> Result<(), String> {
+    settings.store().map_err(|e| e.to_string())
+}
@@ -0,0 +1,354 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use deunicode::deunicode;
+use serde::{Deserialize, Serialize};
+use strsim::damerau_levenshtein;
+
+use crate::event_handler::SONGS;
+use crate::song_dir::Song;
+use super::{song_by_id, TrackId};
+
+const DEFAULT_LIMIT: usize = 50;
+
+lazy_static::lazy_static! {
+    // rebuilt every time the library is published, always matches SONGS
+    static ref SEARCH_INDEX: Mutex<SearchIndex> = Mutex::new(SearchIndex::default());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Field {
+    Title,
+    Artist,
+    Album,
+    Genre,
+    Path,
+}
+
+impl Field {
+    const ALL: [Field; 5] = [Field::Title, Field::Artist, Field::Album, Field::Genre, Field::Path];
+
+    // a hit in the title says more than the same word somewhere in the path
+    fn weight(self) -> f64 {
+        match self {
+            Field::Title => 3.0,
+            Field::Artist | Field::Album => 2.0,
+            Field::Genre => 1.0,
+            Field::Path => 0.5,
+        }
+    }
+}
+
+// lowercase ascii words, so "Björk" and "bjork" are the same thing
+fn normalize(text: &str) -> Vec<String> {
+    deunicode(text)
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// how well `word` from the query matches `term` from a track, 0 for not at all
+fn term_score(word: &str, term: &str) -> f64 {
+    if word == term {
+        return 1.0;
+    }
+
+    // typing isn't done yet
+    if word.len() >= 2 && term.starts_with(word) {
+        return 0.7;
+    }
+
+    let max_typos = match word.len() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    };
+
+    if max_typos > 0 && term.len().abs_diff(word.len()) <= max_typos && damerau_levenshtein(word, term) <= max_typos {
+        return 0.5;
+    }
+
+    0.0
+}
+
+#[derive(Debug, Default)]
+struct SearchDoc {
+    id: TrackId,
+    year: Option<u32>,
+    fields: HashMap<Field, Vec<String>>,
+}
+
+impl SearchDoc {
+    fn new(song: &Song) -> Self {
+        let tags = &song.tags;
+        let mut fields = HashMap::new();
+
+        fields.insert(Field::Title, normalize(tags.title.as_deref().unwrap_or(&song.name)));
+        fields.insert(
+            Field::Artist,
+            [&tags.artist, &tags.album_artist].into_iter().flatten().flat_map(|artist| normalize(artist)).collect(),
+        );
+        fields.insert(Field::Album, tags.album.as_deref().map(normalize).unwrap_or_default());
+        fields.insert(Field::Genre, tags.genre.as_deref().map(normalize).unwrap_or_default());
+        fields.insert(Field::Path, normalize(&song.path));
+
+        Self { id: song.id, year: tags.year, fields }
+    }
+
+    // every word of a filter has to be somewhere in the field
+    fn field_matches(&self, field: Field, words: &[String]) -> bool {
+        let terms = &self.fields[&field];
+
+        words.iter().all(|word| terms.iter().any(|term| term_score(word, term) > 0.0))
+    }
+
+    fn passes(&self, filters: &SearchFilters) -> bool {
+        let text_filters = [
+            (Field::Title, &filters.title),
+            (Field::Artist, &filters.artist),
+            (Field::Album, &filters.album),
+            (Field::Genre, &filters.genre),
+            (Field::Path, &filters.path),
+        ];
+
+        let text_ok = text_filters
+            .into_iter()
+            .all(|(field, value)| value.as_deref().is_none_or(|value| self.field_matches(field, &normalize(value))));
+
+        let year_ok = match (filters.year_min, filters.year_max) {
+            (None, None) => true,
+            (min, max) => self.year.is_some_and(|year| min.is_none_or(|min| year >= min) && max.is_none_or(|max| year <= max)),
+        };
+
+        text_ok && year_ok
+    }
+}
+
+#[derive(Debug, Default)]
+struct SearchIndex {
+    // in library order, which is also how ties are ranked
+    docs: Vec<SearchDoc>,
+    // word -> (doc, field) for every place it shows up
+    postings: HashMap<String, Vec<(usize, Field)>>,
+}
+
+impl SearchIndex {
+    fn build(songs: &[Song]) -> Self {
+        let docs: Vec<SearchDoc> = songs.iter().map(SearchDoc::new).collect();
+        let mut postings: HashMap<String, Vec<(usize, Field)>> = HashMap::new();
+
+        for (doc_index, doc) in docs.iter().enumerate() {
+            for field in Field::ALL {
+                for term in &doc.fields[&field] {
+                    let entry = postings.entry(term.clone()).or_default();
+
+                    if entry.last() != Some(&(doc_index, field)) {
+                        entry.push((doc_index, field));
+                    }
+                }
+            }
+        }
+
+        Self { docs, postings }
+    }
+
+    // best score of one query word per doc, across all the terms and fields it matched
+    fn word_scores(&self, word: &str) -> HashMap<usize, f64> {
+        let mut scores: HashMap<usize, f64> = HashMap::new();
+
+        for (term, places) in &self.postings {
+            let score = term_score(word, term);
+
+            if score == 0.0 {
+                continue;
+            }
+
+            for (doc_index, field) in places {
+                let weighted = score * field.weight();
+                let best = scores.entry(*doc_index).or_insert(0.0);
+
+                if weighted > *best {
+                    *best = weighted;
+                }
+            }
+        }
+
+        scores
+    }
+
+    // (doc, score) best first, every word has to match somewhere
+    fn search(&self, words: &[String], filters: &SearchFilters) -> Vec<(usize, f64)> {
+        let mut hits: Vec<(usize, f64)> = match words.split_first() {
+            None => (0..self.docs.len()).map(|doc_index| (doc_index, 0.0)).collect(),
+            Some((first, rest)) => {
+                let mut scores = self.word_scores(first);
+
+                for word in rest {
+                    let word_scores = self.word_scores(word);
+
+                    scores.retain(|doc_index, score| match word_scores.get(doc_index) {
+                        Some(word_score) => {
+                            *score += word_score;
+                            true
+                        }
+                        None => false,
+                    });
+                }
+
+                scores.into_iter().collect()
+            }
+        };
+
+        hits.retain(|(doc_index, _)| self.docs[*doc_index].passes(filters));
+        hits.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal).then(a.0.cmp(&b.0)));
+
+        hits
+    }
+}
+
+pub fn rebuild_search_index(songs: &[Song]) {
+    let index = SearchIndex::build(songs);
+
+    *SEARCH_INDEX.lock().unwrap() = index;
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchFilters {
+    pub title: Option<String>,
+    // album artist counts as well
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub path: Option<String>,
+    pub year_min: Option<u32>,
+    pub year_max: Option<u32>,
+}
+
+// "year:2000", "year:>2000", "year:<=1999" or "year:1990..1999"
+fn apply_year(filters: &mut SearchFilters, value: &str) -> bool {
+    let parse = |value: &str| value.trim().parse::<u32>().ok();
+
+    let (min, max) = if let Some((from, to)) = value.split_once("..") {
+        (parse(from), parse(to))
+    } else if let Some(year) = value.strip_prefix(">=") {
+        (parse(year), None)
+    } else if let Some(year) = value.strip_prefix("<=") {
+        (None, parse(year))
+    } else if let Some(year) = value.strip_prefix('>') {
+        (parse(year).and_then(|year| year.checked_add(1)), None)
+    } else if let Some(year) = value.strip_prefix('<') {
+        (None, parse(year).and_then(|year| year.checked_sub(1)))
+    } else {
+        let year = parse(value);
+        (year, year)
+    };
+
+    if min.is_none() && max.is_none() {
+        return false;
+    }
+
+    filters.year_min = min.or(filters.year_min);
+    filters.year_max = max.or(filters.year_max);
+
+    true
+}
+
+// splits on spaces, keeping anything in double quotes together
+fn split_query(query: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        parts.push(current);
+    }
+
+    parts
+}
+
+// pulls `field:value` filters like `artist:"Bjork" year:>2000` out of the query, the rest is searched for
+fn parse_query(query: &str, filters: &mut SearchFilters) -> Vec<String> {
+    let mut words = Vec::new();
+
+    for part in split_query(query) {
+        let handled = match part.split_once(':') {
+            Some((field, value)) if !value.is_empty() => {
+                let field = field.to_lowercase();
+
+                let text_filter = match field.as_str() {
+                    "title" => Some(&mut filters.title),
+                    "artist" => Some(&mut filters.artist),
+                    "album" => Some(&mut filters.album),
+                    "genre" => Some(&mut filters.genre),
+                    "path" => Some(&mut filters.path),
+                    _ => None,
+                };
+
+                match text_filter {
+                    Some(text_filter) => {
+                        *text_filter = Some(value.to_string());
+                        true
+                    }
+                    None => field == "year" && apply_year(filters, value),
+                }
+            }
+            _ => false,
+        };
+
+        if !handled {
+            words.extend(normalize(&part));
+        }
+    }
+
+    words
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    // hits before limit and offset, for paging
+    pub total: usize,
+    pub songs: Vec<Song>,
+}
+
+#[tauri::command]
+pub fn search(query: String, filters: Option<SearchFilters>, limit: Option<usize>, offset: Option<usize>) -> Result<SearchResults, String> {
+    let mut filters = filters.unwrap_or_default();
+    let words = parse_query(&query, &mut filters);
+
+    let (page, total): (Vec<TrackId>, usize) = {
+        let index = SEARCH_INDEX.lock().unwrap();
+        let hits = index.search(&words, &filters);
+
+        let page = hits
+            .iter()
+            .skip(offset.unwrap_or(0))
+            .take(limit.unwrap_or(DEFAULT_LIMIT))
+            .map(|(doc_index, _)| index.docs[*doc_index].id)
+            .collect();
+
+        (page, hits.len())
+    };
+
+    let songs = SONGS.lock().unwrap();
+
+    Ok(SearchResults {
+        total,
+        songs: page.into_iter().filter_map(|id| song_by_id(&songs, id).cloned()).collect(),
+    })
+}
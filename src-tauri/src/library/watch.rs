@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use notify::event::{MetadataKind, ModifyKind};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tauri::Manager;
+
+use super::roots::{load_roots, LibraryRoot};
+use super::scan::ScanTracker;
+use super::{load_index, update_index, update_paths, LibraryDiff, RESCAN_INTERVAL};
+
+const LIBRARY_CHANGED: &str = "library-changed";
+const SCAN_PROGRESS: &str = "scan-progress";
+
+// changes are applied once the folder has been quiet for this long
+const DEBOUNCE: Duration = Duration::from_millis(500);
+// copying a big album keeps the folder busy for a while, the library still catches up this often
+const MAX_DEBOUNCE: Duration = Duration::from_secs(5);
+// how often the roots are looked at while nothing happens
+const IDLE_TICK: Duration = Duration::from_secs(1);
+
+type WatchEvent = notify::Result<Event>;
+
+static RESCAN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+enum Pending {
+    Paths(HashSet<PathBuf>),
+    // the watcher lost track, e.g. the inotify queue overflowed, only a full rescan is reliable then
+    Rescan,
+}
+
+// reading a file is an event too, and scanning reads files, so only actual changes count
+fn is_change(kind: &EventKind) -> bool {
+    !matches!(
+        kind,
+        EventKind::Access(_) | EventKind::Modify(ModifyKind::Metadata(MetadataKind::AccessTime))
+    )
+}
+
+fn add_event(event: WatchEvent, paths: &mut HashSet<PathBuf>) -> bool {
+    match event {
+        Ok(event) if event.need_rescan() => false,
+        Ok(event) => {
+            if is_change(&event.kind) {
+                paths.extend(event.paths);
+            }
+
+            true
+        }
+        Err(e) => {
+            println!("error: {:?}", e);
+            false
+        }
+    }
+}
+
+// waits for a burst of events to settle down, None when nothing happened at all
+fn collect_events(rx: &Receiver<WatchEvent>) -> Option<Pending> {
+    let first = match rx.recv_timeout(IDLE_TICK) {
+        Ok(event) => event,
+        Err(_) => return None,
+    };
+
+    let started = Instant::now();
+    let mut paths = HashSet::new();
+    let mut rescan = !add_event(first, &mut paths);
+
+    while started.elapsed() < MAX_DEBOUNCE {
+        match rx.recv_timeout(DEBOUNCE) {
+            Ok(event) => rescan |= !add_event(event, &mut paths),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    if rescan {
+        Some(Pending::Rescan)
+    } else if paths.is_empty() {
+        None
+    } else {
+        Some(Pending::Paths(paths))
+    }
+}
+
+// one watcher covers every active root, None if any of them can't be watched
+fn watch(roots: &[LibraryRoot], tx: Sender<WatchEvent>) -> Result<RecommendedWatcher> {
+    let mut watcher = notify::recommended_watcher(tx)?;
+
+    for root in roots.iter().filter(|root| root.is_active()) {
+        watcher.watch(&root.dir(), RecursiveMode::Recursive)?;
+    }
+
+    Ok(watcher)
+}
+
+// a scan against no roots at all would drop the whole index, so when lmdb can't be read nothing is scanned
+fn try_load_roots() -> Option<Vec<LibraryRoot>> {
+    match load_roots() {
+        Ok(roots) => Some(roots),
+        Err(e) => {
+            println!("error: {:?}", e);
+            None
+        }
+    }
+}
+
+fn publish_diff(app_handle: &tauri::AppHandle, diff: Result<LibraryDiff>, always: bool) {
+    match diff {
+        Ok(diff) if always || !diff.is_empty() => {
+            println!(
+                "library changed: {} added, {} updated, {} removed",
+                diff.added.len(),
+                diff.updated.len(),
+                diff.removed.len()
+            );
+
+            if let Err(e) = app_handle.emit_all(LIBRARY_CHANGED, diff) {
+                println!("error: {:?}", e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => println!("error: {:?}", e),
+    }
+}
+
+fn run_scan(app_handle: &tauri::AppHandle, always: bool, scan: impl FnOnce(&ScanTracker) -> Result<LibraryDiff>) {
+    let progress_handle = app_handle.clone();
+
+    let tracker = ScanTracker::new(move |progress| {
+        if let Err(e) = progress_handle.emit_all(SCAN_PROGRESS, progress) {
+            println!("error: {:?}", e);
+        }
+    });
+
+    let diff = scan(&tracker);
+    tracker.finish();
+
+    publish_diff(app_handle, diff, always);
+}
+
+// every change to the library goes through this thread: the index from last time is loaded first,
+// then one full rescan catches up on what happened while the player was closed, and from then on
+// only what the watcher reports is looked at again
+pub fn start_library_watcher(app_handle: tauri::AppHandle) {
+    thread::spawn(move || {
+        let roots = loop {
+            match try_load_roots() {
+                Some(roots) => break roots,
+                None => thread::sleep(IDLE_TICK),
+            }
+        };
+
+        if let Err(e) = load_index(&roots) {
+            println!("error: {:?}", e);
+        }
+
+        let (tx, rx) = mpsc::channel();
+
+        // the roots as they were last looked at, along with whether they were active
+        let mut watched: Option<Vec<(LibraryRoot, bool)>> = None;
+        let mut watcher: Option<RecommendedWatcher> = None;
+        let mut last_full_scan = Instant::now();
+
+        loop {
+            let Some(roots) = try_load_roots() else {
+                // whatever the watcher reported stays queued until the roots can be read again
+                thread::sleep(IDLE_TICK);
+                continue;
+            };
+
+            let state: Vec<(LibraryRoot, bool)> = roots.iter().map(|root| (root.clone(), root.is_active())).collect();
+
+            // a root was added, removed or edited, or a drive came or went
+            if watched.as_ref() != Some(&state) {
+                // dropping the old watcher stops its events, anything still queued gets checked against the new roots
+                watcher = match watch(&roots, tx.clone()) {
+                    Ok(watcher) => Some(watcher),
+                    Err(e) => {
+                        println!("unable to watch the library, checking it every {:?} instead: {:?}", RESCAN_INTERVAL, e);
+                        None
+                    }
+                };
+
+                watched = Some(state);
+
+                // sent even without a diff, tracks of a root that came or went are shown or hidden again
+                run_scan(&app_handle, true, |tracker| update_index(&roots, tracker));
+                last_full_scan = Instant::now();
+            }
+
+            let polling = watcher.is_none() && last_full_scan.elapsed() >= RESCAN_INTERVAL;
+
+            if polling || RESCAN_REQUESTED.swap(false, Ordering::AcqRel) {
+                run_scan(&app_handle, false, |tracker| update_index(&roots, tracker));
+                last_full_scan = Instant::now();
+            }
+
+            match collect_events(&rx) {
+                Some(Pending::Paths(paths)) => run_scan(&app_handle, false, |tracker| update_paths(&roots, &paths, tracker)),
+                Some(Pending::Rescan) => {
+                    run_scan(&app_handle, false, |tracker| update_index(&roots, tracker));
+                    last_full_scan = Instant::now();
+                }
+                None => {}
+            }
+        }
+    });
+}
+
+// checks every root again, e.g. to finish a scan that was cancelled
+#[tauri::command]
+pub fn rescan_library() -> Result<(), String> {
+    RESCAN_REQUESTED.store(true, Ordering::Release);
+
+    Ok(())
+}
@@ -4,14 +4,39 @@
 
 use tauri::App;
 
-use crate::song_dir::{set_song_dir, get_songs_dir, get_songs};
-use crate::audio_controls::get_current_index;
+use crate::song_dir::{set_song_dir, get_songs_dir, get_songs, get_unsupported_files};
+use crate::audio_controls::{get_current_track, get_play_queue};
+use crate::audio_backend::transport::{set_transport_publish_rate, get_transport_state};
+use crate::audio_backend::playback_error::{get_error_policy, set_error_policy};
+use crate::audio_backend::crossfeed::get_crossfeed_settings;
+use crate::audio_backend::convolution::{get_convolution_settings, get_convolution_info};
+use crate::audio_backend::effects::chain::{list_effect_kinds, get_effect_chain, save_effect_states};
+use crate::audio_backend::effects::plugins::{scan_plugins, list_plugins, get_wasm_plugins_folder};
+use crate::audio_backend::silence::{get_silence_settings, set_silence_settings, get_silence_analysis};
+use crate::chapters::get_chapters;
+use crate::radio::{list_stations, save_station, remove_station};
+use crate::cover_art::{cover_protocol, get_cover_art, COVER_PROTOCOL};
+use crate::library::get_scan_warnings;
+use crate::library::browse::browse;
+use crate::library::search::search;
+use crate::library::catalog::{get_album_tracks, list_albums, list_artists, list_genres, list_years};
+use crate::library::scan::{cancel_scan, get_scan_settings, set_scan_settings};
+use crate::library::watch::rescan_library;
+use crate::library::roots::{list_library_roots, add_library_root, update_library_root, remove_library_root};
+use crate::resume::{list_resume_positions, clear_resume_position, clear_all_resume_positions, get_resume_settings, set_resume_settings};
 
 mod audio_backend;
 mod event_handler;
 mod audio_controls;
 mod util;
 mod song_dir;
+mod cue_sheet;
+mod chapters;
+mod resume;
+mod radio;
+mod tags;
+mod library;
+mod cover_art;
 
 
 fn main() {
@@ -26,7 +51,14 @@ fn main() {
             Ok(())
             
         })
-        .invoke_handler(tauri::generate_handler![set_song_dir, get_songs_dir, get_songs, get_current_index])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        // thumbnails are served straight to <img> tags instead of going through ipc as base64
+        .register_uri_scheme_protocol(COVER_PROTOCOL, |_app, request| cover_protocol(request))
+        .invoke_handler(tauri::generate_handler![set_song_dir, get_songs_dir, get_songs, get_unsupported_files, get_current_track, set_transport_publish_rate, get_transport_state, get_error_policy, set_error_policy, get_chapters, list_resume_positions, clear_resume_position, clear_all_resume_positions, get_resume_settings, set_resume_settings, list_stations, save_station, remove_station, get_silence_settings, set_silence_settings, get_silence_analysis, get_crossfeed_settings, get_convolution_settings, get_convolution_info, list_effect_kinds, get_effect_chain, scan_plugins, list_plugins, get_wasm_plugins_folder, list_library_roots, add_library_root, update_library_root, remove_library_root, get_scan_warnings, cancel_scan, get_scan_settings, set_scan_settings, rescan_library, browse, get_play_queue, search, list_artists, list_albums, list_genres, list_years, get_album_tracks, get_cover_art])
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                save_effect_states();
+            }
+        });
 }
@@ -0,0 +1,246 @@
+use std::io::Read;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::util::hash::fnv1a_64;
+use crate::util::lmdb::records::{delete_record, list_records, store_record};
+
+const STATIONS_TABLE: &str = "station";
+
+// playlists pointing at playlists do exist, but not endlessly
+const MAX_PLAYLIST_DEPTH: usize = 3;
+const MAX_PLAYLIST_BYTES: u64 = 1024 * 1024;
+
+const PLAYLIST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Station {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub added_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlaylistKind {
+    M3u,
+    Pls,
+}
+
+fn playlist_kind(url: &Url, content_type: &str) -> Option<PlaylistKind> {
+    match content_type.to_lowercase().as_str() {
+        "audio/x-mpegurl" | "audio/mpegurl" | "application/x-mpegurl" | "application/vnd.apple.mpegurl" => {
+            return Some(PlaylistKind::M3u)
+        }
+        "audio/x-scpls" | "application/pls+xml" => return Some(PlaylistKind::Pls),
+        _ => {}
+    }
+
+    // plenty of servers hand out playlists as text/plain or octet-stream, so the extension has the final say
+    let path = url.path().to_lowercase();
+
+    if path.ends_with(".m3u") || path.ends_with(".m3u8") {
+        Some(PlaylistKind::M3u)
+    } else if path.ends_with(".pls") {
+        Some(PlaylistKind::Pls)
+    } else {
+        None
+    }
+}
+
+// entries can be relative to the playlist itself
+fn resolve_entry(entry: &str, base: &Url) -> Option<String> {
+    base.join(entry.trim()).ok().map(|url| url.to_string())
+}
+
+pub fn parse_m3u(text: &str, base: &Url) -> Result<Vec<String>> {
+    if text.contains("#EXT-X-") {
+        return Err(anyhow::anyhow!("HLS playlists are not supported"));
+    }
+
+    Ok(text
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| resolve_entry(line, base))
+        .collect())
+}
+
+// [playlist]
+// File1=http://...
+// Title1=...
+pub fn parse_pls(text: &str, base: &Url) -> Vec<String> {
+    let mut entries: Vec<(u32, String)> = text
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            let number = key.trim().to_lowercase().strip_prefix("file")?.parse().ok()?;
+
+            Some((number, resolve_entry(value, base)?))
+        })
+        .collect();
+
+    entries.sort_by_key(|(number, _)| *number);
+
+    entries.into_iter().map(|(_, url)| url).collect()
+}
+
+// turns whatever the user pasted (a stream or a m3u/pls pointing at one) into the url of the actual stream
+pub fn resolve_stream_url(url: &str) -> Result<String> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(PLAYLIST_TIMEOUT)
+        .timeout_read(PLAYLIST_TIMEOUT)
+        .build();
+
+    let mut current = url.trim().to_string();
+
+    for _ in 0..MAX_PLAYLIST_DEPTH {
+        let parsed = Url::parse(&current)?;
+
+        let response = agent.get(&current).call()?;
+
+        let Some(kind) = playlist_kind(&parsed, response.content_type()) else {
+            // the actual stream, dropping the response closes the connection again
+            return Ok(current);
+        };
+
+        let mut bytes = Vec::new();
+        response.into_reader().take(MAX_PLAYLIST_BYTES).read_to_end(&mut bytes)?;
+
+        let text = String::from_utf8_lossy(&bytes);
+
+        let entries = match kind {
+            PlaylistKind::M3u => parse_m3u(&text, &parsed)?,
+            PlaylistKind::Pls => parse_pls(&text, &parsed),
+        };
+
+        current = entries
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("playlist {} does not contain any streams", current))?;
+    }
+
+    Err(anyhow::anyhow!("too many nested playlists behind {}", url))
+}
+
+fn station_id(url: &str) -> String {
+    format!("{:016x}", fnv1a_64(url.trim().as_bytes()))
+}
+
+#[tauri::command]
+pub fn list_stations() -> Result<Vec<Station>, String> {
+    let mut stations: Vec<Station> = list_records::<Station>(STATIONS_TABLE)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|(_, station)| station)
+        .collect();
+
+    stations.sort_by_key(|station| station.name.to_lowercase());
+
+    Ok(stations)
+}
+
+// saving the same url again just renames the station
+#[tauri::command]
+pub fn save_station(name: String, url: String) -> Result<Station, String> {
+    let parsed = Url::parse(url.trim()).map_err(|e| e.to_string())?;
+
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err(format!("unsupported url scheme: {}", parsed.scheme()));
+    }
+
+    let added_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs())
+        .unwrap_or(0);
+
+    let name = if name.trim().is_empty() {
+        parsed.host_str().unwrap_or_default().to_string()
+    } else {
+        name.trim().to_string()
+    };
+
+    let station = Station {
+        id: station_id(&url),
+        name,
+        url: url.trim().to_string(),
+        added_at,
+    };
+
+    store_record(STATIONS_TABLE, &station.id, &station).map_err(|e| e.to_string())?;
+
+    Ok(station)
+}
+
+#[tauri::command]
+pub fn remove_station(id: String) -> Result<(), String> {
+    delete_record(STATIONS_TABLE, &id).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::util::test_http::{response, serve};
+
+    fn base() -> Url {
+        Url::parse("http://radio.example/lists/station.m3u").unwrap()
+    }
+
+    #[test]
+    fn m3u_skips_comments_and_resolves_relative_entries() {
+        let text = "\u{feff}#EXTM3U\r\n#EXTINF:-1,Station\r\nhttp://stream.example/live\r\n\r\nbackup.mp3\r\n";
+
+        let entries = parse_m3u(text, &base()).unwrap();
+
+        assert_eq!(entries, vec!["http://stream.example/live", "http://radio.example/lists/backup.mp3"]);
+    }
+
+    #[test]
+    fn m3u_rejects_hls() {
+        let text = "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:10\nsegment0.ts\n";
+
+        assert!(parse_m3u(text, &base()).is_err());
+    }
+
+    #[test]
+    fn pls_is_ordered_by_entry_number() {
+        let text = "[playlist]\nNumberOfEntries=2\nFile2=http://stream.example/second\nTitle2=Second\nfile1 = first\nLength1=-1\nVersion=2\n";
+
+        let entries = parse_pls(text, &base());
+
+        assert_eq!(entries, vec!["http://radio.example/lists/first", "http://stream.example/second"]);
+    }
+
+    #[test]
+    fn stream_url_is_found_behind_a_playlist() {
+        let url = serve(|_, path| match path {
+            "/station.m3u" => response("audio/x-mpegurl", &[], b"#EXTM3U\nstream\n"),
+            _ => response("audio/mpeg", &[], &[0; 64]),
+        });
+
+        assert_eq!(resolve_stream_url(&format!("{}/station.m3u", url)).unwrap(), format!("{}/stream", url));
+    }
+
+    #[test]
+    fn nested_playlists_stop_at_the_depth_limit() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counted = requests.clone();
+
+        // a playlist that points at itself
+        let url = serve(move |_, _| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            response("audio/x-scpls", &[], b"[playlist]\nFile1=loop.pls\n")
+        });
+
+        let error = resolve_stream_url(&format!("{}/loop.pls", url)).unwrap_err();
+
+        assert!(error.to_string().contains("too many nested playlists"));
+        assert_eq!(requests.load(Ordering::SeqCst), MAX_PLAYLIST_DEPTH);
+    }
+}
@@ -0,0 +1,157 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::audio_backend::transport::frames_to_secs;
+use crate::song_dir::Song;
+use crate::util::hash::fnv1a_64;
+use crate::util::lmdb::records::{clear_records, delete_record, get_record, list_records, store_record};
+use crate::util::lmdb::settings::{get_setting, store_setting};
+
+const RESUME_TABLE: &str = "resume";
+const RESUME_SETTINGS: &str = "resume-settings";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ResumeSettings {
+    pub enabled: bool,
+    // short tracks always start from the top, this is meant for audiobooks, podcasts and mixes
+    pub min_duration_secs: f64,
+    // stopping closer than this to the end counts as having finished the track
+    pub finished_threshold_secs: f64,
+    pub save_interval_secs: u64,
+}
+
+impl Default for ResumeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_duration_secs: 10.0 * 60.0,
+            finished_threshold_secs: 30.0,
+            save_interval_secs: 10,
+        }
+    }
+}
+
+impl ResumeSettings {
+    pub fn load() -> Self {
+        match get_setting::<ResumeSettings>(RESUME_SETTINGS) {
+            Ok(Some(settings)) => settings,
+            Ok(None) => ResumeSettings::default(),
+            Err(e) => {
+                println!("error: {:?}", e);
+                ResumeSettings::default()
+            }
+        }
+    }
+}
+
+// positions are relative to the start of the track, which matters for cue tracks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumePosition {
+    pub path: String,
+    pub start_frame: Option<usize>,
+    pub name: String,
+    pub pos_frames: usize,
+    pub duration_frames: usize,
+    pub sample_rate: u32,
+    pub pos_secs: f64,
+    pub duration_secs: f64,
+    pub saved_at: u64,
+}
+
+// paths can be longer than lmdb's key size limit, so they get hashed
+fn resume_key(path: &str, start_frame: Option<usize>) -> String {
+    format!("{:016x}", fnv1a_64(format!("{}#{}", path, start_frame.unwrap_or(0)).as_bytes()))
+}
+
+pub fn save_resume_position(
+    song: &Song,
+    pos_frames: usize,
+    duration_frames: usize,
+    sample_rate: u32,
+    settings: &ResumeSettings,
+) -> Result<()> {
+    if !settings.enabled || sample_rate == 0 || pos_frames == 0 {
+        return Ok(());
+    }
+
+    let pos_secs = frames_to_secs(pos_frames, sample_rate);
+    let duration_secs = frames_to_secs(duration_frames, sample_rate);
+
+    if duration_secs < settings.min_duration_secs {
+        return Ok(());
+    }
+
+    let key = resume_key(&song.path, song.start_frame);
+
+    if pos_secs >= duration_secs - settings.finished_threshold_secs {
+        return delete_record(RESUME_TABLE, &key);
+    }
+
+    let saved_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs())
+        .unwrap_or(0);
+
+    let position = ResumePosition {
+        path: song.path.clone(),
+        start_frame: song.start_frame,
+        name: song.name.clone(),
+        pos_frames,
+        duration_frames,
+        sample_rate,
+        pos_secs,
+        duration_secs,
+        saved_at,
+    };
+
+    store_record(RESUME_TABLE, &key, &position)
+}
+
+pub fn resume_frame_for(song: &Song) -> Option<usize> {
+    if !ResumeSettings::load().enabled {
+        return None;
+    }
+
+    match get_record::<ResumePosition>(RESUME_TABLE, &resume_key(&song.path, song.start_frame)) {
+        Ok(position) => position.map(|position| position.pos_frames),
+        Err(e) => {
+            println!("error: {:?}", e);
+            None
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_resume_positions() -> Result<Vec<ResumePosition>, String> {
+    let mut positions: Vec<ResumePosition> = list_records::<ResumePosition>(RESUME_TABLE)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|(_, position)| position)
+        .collect();
+
+    positions.sort_by_key(|position| std::cmp::Reverse(position.saved_at));
+
+    Ok(positions)
+}
+
+#[tauri::command]
+pub fn clear_resume_position(path: String, start_frame: Option<usize>) -> Result<(), String> {
+    delete_record(RESUME_TABLE, &resume_key(&path, start_frame)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_all_resume_positions() -> Result<(), String> {
+    clear_records(RESUME_TABLE).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_resume_settings() -> Result<ResumeSettings, String> {
+    Ok(ResumeSettings::load())
+}
+
+#[tauri::command]
+pub fn set_resume_settings(settings: ResumeSettings) -> Result<(), String> {
+    store_setting(RESUME_SETTINGS, &settings).map_err(|e| e.to_string())
+}
@@ -1,10 +1,22 @@
 
-use std::{fs, path::Path};
+use std::collections::{HashMap, HashSet};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::UNIX_EPOCH;
+use std::{fs, path::{Path, PathBuf}};
 
 use crate::util::lmdb::audio_files_dir::{store_songs_directory,  get_songs_directory};
 use anyhow::Result;
+use globset::GlobSet;
+use rayon::prelude::*;
 
-use crate::event_handler::SONGS;
+use crate::audio_backend::probe::{probe_file, should_probe, ProbeOutcome, UnsupportedFile};
+use crate::cue_sheet::{read_cue_sheet, CueSheet};
+use crate::tags::{read_tags_or_default, TrackTags};
+use crate::audio_backend::transport::frames_to_secs;
+use crate::event_handler::{SONGS, UNSUPPORTED_FILES};
+use crate::library::roots::add_library_root;
+use crate::library::TrackId;
+use crate::library::scan::ScanTracker;
 
 use serde::{Serialize, Deserialize};
 
@@ -16,6 +28,9 @@ pub fn set_song_dir(file_path: &str) -> Result<(), String> {
     println!("file path: {}", file_path);
     store_songs_directory(&file_path).map_err(|e| e.to_string())?;
 
+    // picking a folder adds it to the library next to the ones already there
+    add_library_root(file_path)?;
+
     Ok(())
 }
 
@@ -30,37 +45,448 @@ pub fn get_songs_dir() -> Result<String, String> {
 }
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Song {
+    #[serde(default)]
+    pub id: TrackId,
     pub path: String,
     pub name: String,
     pub is_directory: bool,
+    pub codec: Option<String>,
+    // set for tracks that only cover part of a file, i.e. tracks from a cue sheet
+    pub start_frame: Option<usize>,
+    pub end_frame: Option<usize>,
+    #[serde(default)]
+    pub tags: TrackTags,
+}
+
+#[derive(Debug, Default)]
+pub struct ScanResult {
+    pub songs: Vec<Song>,
+    pub unsupported: Vec<UnsupportedFile>,
+}
+
+#[derive(Debug, Clone)]
+pub enum SourceKind {
+    Audio,
+    Cue(CueSheet),
+    BrokenCue(String),
+}
+
+// one file that turns into songs, either an audio file or a cue sheet together with the images it describes.
+// size and modified cover all of those files, so touching any of them means the source has to be scanned again
+#[derive(Debug, Clone)]
+pub struct ScanSource {
+    pub path: PathBuf,
+    pub kind: SourceKind,
+    pub size: u64,
+    // seconds since the epoch
+    pub modified: u64,
+}
+
+pub fn path_to_string(path: &Path) -> String {
+    path.to_string_lossy().to_string().replace("\\", "/")
+}
+
+// a path the scan had to skip, and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanWarning {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct WalkResult {
+    pub sources: Vec<ScanSource>,
+    pub warnings: Vec<ScanWarning>,
+    // folders that couldn't be listed, whatever was found below them before is still there as far as we know
+    pub unreadable: Vec<PathBuf>,
+    // (canonical path, path it was walked through) of every folder listed
+    folders: Vec<(PathBuf, PathBuf)>,
+}
+
+impl WalkResult {
+    fn warn(&mut self, path: &Path, message: impl ToString) {
+        self.warnings.push(ScanWarning {
+            path: path_to_string(path),
+            message: message.to_string(),
+        });
+    }
+
+    pub fn extend(&mut self, other: WalkResult) {
+        self.sources.extend(other.sources);
+        self.warnings.extend(other.warnings);
+        self.unreadable.extend(other.unreadable);
+        self.folders.extend(other.folders);
+    }
+
+    // followed links can reach the same folder through several paths, and which branch of a parallel walk gets
+    // there first changes from scan to scan. the first path in sort order wins, so its tracks keep their ids
+    fn drop_aliases(&mut self) {
+        let mut chosen: HashMap<PathBuf, PathBuf> = HashMap::new();
+
+        for (canonical, path) in &self.folders {
+            chosen
+                .entry(canonical.clone())
+                .and_modify(|kept| {
+                    if path < kept {
+                        *kept = path.clone();
+                    }
+                })
+                .or_insert_with(|| path.clone());
+        }
+
+        let mut aliases: HashSet<PathBuf> = HashSet::new();
+
+        for (canonical, path) in std::mem::take(&mut self.folders) {
+            let kept = &chosen[&canonical];
+
+            if path != *kept {
+                self.warn(&path, format!("skipped, the same folder is scanned as {}", path_to_string(kept)));
+                aliases.insert(path);
+            }
+        }
+
+        if !aliases.is_empty() {
+            self.sources.retain(|source| !source.path.parent().is_some_and(|folder| aliases.contains(folder)));
+        }
+    }
 }
 
-pub fn scan_dir(dir: &Path) -> Result<Vec<Song>> {
-    let mut songs: Vec<Song> = Vec::new();
+fn file_stamp(path: &Path) -> std::io::Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
 
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|modified| modified.as_secs())
+        .unwrap_or(0);
 
-            if path.is_dir() {
-                let sub_songs = scan_dir(&path)?;
+    Ok((metadata.len(), modified))
+}
+
+// how a library root is walked, see LibraryRoot
+#[derive(Debug, Clone)]
+pub struct WalkOptions {
+    pub root: PathBuf,
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    pub exclude: GlobSet,
+}
+
+impl WalkOptions {
+    fn is_excluded(&self, path: &Path) -> bool {
+        path.strip_prefix(&self.root).is_ok_and(|relative| self.exclude.is_match(relative))
+    }
+
+    // `path` or any folder between it and the root, for walks and changes that start below an excluded folder
+    pub fn excludes(&self, path: &Path) -> bool {
+        path.ancestors()
+            .take_while(|ancestor| *ancestor != self.root && ancestor.starts_with(&self.root))
+            .any(|ancestor| self.is_excluded(ancestor))
+    }
+
+    // how many folders below the root `dir` is
+    fn depth_of(&self, dir: &Path) -> usize {
+        dir.strip_prefix(&self.root).map(|relative| relative.components().count()).unwrap_or(0)
+    }
+
+    fn within_depth(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max_depth| depth <= max_depth)
+    }
+
+    fn skips_link(&self, path: &Path) -> bool {
+        !self.follow_symlinks && fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+    }
+}
+
+// only looks at names and file metadata, nothing gets opened except cue sheets, which are tiny.
+// nothing in here stops the walk, entries that can't be read end up in the warnings
+// subfolders are walked in parallel on the current rayon pool
+pub fn walk_dir(dir: &Path, options: &WalkOptions, tracker: &ScanTracker) -> WalkResult {
+    let mut result = WalkResult::default();
+
+    walk_dir_into(dir, options.depth_of(dir), true, options, &[], tracker, &mut result);
+    result.drop_aliases();
+
+    result
+}
+
+// just the files directly in `dir`, for when only they changed
+pub fn walk_dir_shallow(dir: &Path, options: &WalkOptions, tracker: &ScanTracker) -> WalkResult {
+    let mut result = WalkResult::default();
+
+    walk_dir_into(dir, options.depth_of(dir), false, options, &[], tracker, &mut result);
+    result.folders.clear();
+
+    result
+}
+
+fn walk_dir_into(
+    dir: &Path,
+    depth: usize,
+    recursive: bool,
+    options: &WalkOptions,
+    // canonical paths of the folders above this one in the current branch
+    ancestors: &[PathBuf],
+    tracker: &ScanTracker,
+    result: &mut WalkResult,
+) {
+    if !dir.is_dir() || !options.within_depth(depth) || options.excludes(dir) || tracker.is_cancelled() {
+        return;
+    }
+
+    // a followed link back up to a folder above would never end. one to somewhere else is walked,
+    // duplicates are sorted out once the whole walk is done
+    let canonical = match fs::canonicalize(dir) {
+        Ok(canonical) => canonical,
+        Err(e) => {
+            result.warn(dir, e);
+            result.unreadable.push(dir.to_path_buf());
+            return;
+        }
+    };
+
+    if ancestors.contains(&canonical) {
+        result.warn(dir, "skipped, the folder links back to a folder above it");
+        return;
+    }
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            result.warn(dir, e);
+            result.unreadable.push(dir.to_path_buf());
+            return;
+        }
+    };
 
-                songs.extend(sub_songs);
-            } else if let Some(extension) = path.extension() {
-                if extension == "flac" || extension == "mp3" {
-                    let song = Song {
-                        path: path.to_string_lossy().to_string().replace("\\", "/"),
-                        name: path.file_name().unwrap().to_string_lossy().to_string(),
-                        is_directory: false,
-                    };
-                    songs.push(song);
+    let mut paths: Vec<PathBuf> = Vec::new();
+
+    for entry in entries {
+        match entry {
+            Ok(entry) => {
+                let path = entry.path();
+
+                if !options.skips_link(&path) && !options.is_excluded(&path) {
+                    paths.push(path);
                 }
             }
+            Err(e) => result.warn(dir, e),
         }
     }
 
-    Ok(songs)
+    paths.sort();
+
+    // cue sheets go first so the album images they describe aren't listed a second time as one huge track
+    let mut covered_by_cue: HashSet<PathBuf> = HashSet::new();
+
+    for path in paths.iter().filter(|path| is_cue_sheet(path)) {
+        let (mut size, mut modified) = match file_stamp(path) {
+            Ok(stamp) => stamp,
+            Err(e) => {
+                result.warn(path, e);
+                continue;
+            }
+        };
+
+        let kind = match read_cue_sheet(path) {
+            Ok(sheet) => {
+                for file in &sheet.files {
+                    // a missing image is reported when the sheet is scanned
+                    if let Ok((file_size, file_modified)) = file_stamp(&file.path) {
+                        size += file_size;
+                        modified = modified.max(file_modified);
+                    }
+
+                    covered_by_cue.insert(file.path.clone());
+                }
+
+                SourceKind::Cue(sheet)
+            }
+            Err(e) => SourceKind::BrokenCue(e.to_string()),
+        };
+
+        tracker.saw(path);
+
+        result.sources.push(ScanSource {
+            path: path.clone(),
+            kind,
+            size,
+            modified,
+        });
+    }
+
+    let mut subdirs: Vec<PathBuf> = Vec::new();
+
+    for path in paths {
+        if path.is_dir() {
+            if recursive {
+                subdirs.push(path);
+            }
+        } else if should_probe(&path) && !covered_by_cue.contains(&path) {
+            match file_stamp(&path) {
+                Ok((size, modified)) => {
+                    tracker.saw(&path);
+
+                    result.sources.push(ScanSource {
+                        path,
+                        kind: SourceKind::Audio,
+                        size,
+                        modified,
+                    });
+                }
+                Err(e) => result.warn(&path, e),
+            }
+        }
+    }
+
+    let mut below: Vec<PathBuf> = ancestors.to_vec();
+    below.push(canonical.clone());
+    result.folders.push((canonical, dir.to_path_buf()));
+
+    // every stat on a network share is a round trip, so the folders are looked at side by side.
+    // the branches don't share anything, so what they find doesn't depend on which one is faster
+    let subresults: Vec<WalkResult> = subdirs
+        .par_iter()
+        .map(|subdir| {
+            let mut subresult = WalkResult::default();
+            walk_dir_into(subdir, depth + 1, recursive, options, &below, tracker, &mut subresult);
+            subresult
+        })
+        .collect();
+
+    for subresult in subresults {
+        result.extend(subresult);
+    }
+}
+
+// probes and reads the tags of a single source, this is the expensive part of a scan.
+// a broken file that makes a decoder or tag reader panic is listed as unsupported instead of ending the scan
+pub fn scan_source(source: &ScanSource) -> ScanResult {
+    panic::catch_unwind(AssertUnwindSafe(|| scan_source_unchecked(source))).unwrap_or_else(|_| ScanResult {
+        songs: Vec::new(),
+        unsupported: vec![UnsupportedFile {
+            path: path_to_string(&source.path),
+            reason: "reading the file crashed the scanner".to_string(),
+        }],
+    })
+}
+
+fn scan_source_unchecked(source: &ScanSource) -> ScanResult {
+    let mut result = ScanResult::default();
+
+    match &source.kind {
+        SourceKind::Audio => audio_song(&source.path, &mut result),
+        SourceKind::Cue(sheet) => cue_songs(&source.path, sheet, &mut result),
+        SourceKind::BrokenCue(e) => {
+            result.unsupported.push(UnsupportedFile {
+                path: path_to_string(&source.path),
+                reason: format!("invalid cue sheet: {}", e),
+            });
+        }
+    }
+
+    result
+}
+
+fn audio_song(path: &Path, result: &mut ScanResult) {
+    let path_string = path_to_string(path);
+
+    match probe_file(path) {
+        ProbeOutcome::Playable { codec, .. } => {
+            let song = Song {
+                id: TrackId::of(&path_string),
+                path: path_string,
+                name: path.file_name().unwrap().to_string_lossy().to_string(),
+                is_directory: false,
+                codec: Some(codec),
+                start_frame: None,
+                end_frame: None,
+                tags: read_tags_or_default(path),
+            };
+            result.songs.push(song);
+        }
+        ProbeOutcome::Unsupported(reason) => {
+            result.unsupported.push(UnsupportedFile {
+                path: path_string,
+                reason,
+            });
+        }
+        ProbeOutcome::NotAudio => {}
+    }
+}
+
+fn cue_songs(cue_path: &Path, sheet: &CueSheet, result: &mut ScanResult) {
+    for file in &sheet.files {
+        let (codec, sample_rate) = match probe_file(&file.path) {
+            ProbeOutcome::Playable { codec, sample_rate: Some(sample_rate) } => (codec, sample_rate),
+            ProbeOutcome::Playable { sample_rate: None, .. } => {
+                result.unsupported.push(UnsupportedFile {
+                    path: path_to_string(cue_path),
+                    reason: format!("{} does not report a sample rate", file.path.display()),
+                });
+                continue;
+            }
+            ProbeOutcome::Unsupported(reason) => {
+                result.unsupported.push(UnsupportedFile {
+                    path: path_to_string(cue_path),
+                    reason: format!("{}: {}", file.path.display(), reason),
+                });
+                continue;
+            }
+            ProbeOutcome::NotAudio => {
+                result.unsupported.push(UnsupportedFile {
+                    path: path_to_string(cue_path),
+                    reason: format!("{} is missing or not an audio file", file.path.display()),
+                });
+                continue;
+            }
+        };
+
+        // the technical details are the same for every track in the file, names and numbers come from the sheet
+        let file_tags = read_tags_or_default(&file.path);
+
+        for (i, track) in file.tracks.iter().enumerate() {
+            let title = track.title.clone().unwrap_or_else(|| format!("Track {:02}", track.number));
+
+            // a track ends where the next one starts, the last one runs to the end of the file
+            let start_frame = track.start_frame(sample_rate);
+            let end_frame = file.tracks.get(i + 1).map(|next| next.start_frame(sample_rate));
+
+            let duration_secs = match end_frame {
+                Some(end_frame) => Some(frames_to_secs(end_frame.saturating_sub(start_frame), sample_rate)),
+                None => file_tags
+                    .duration_secs
+                    .map(|file_secs| (file_secs - frames_to_secs(start_frame, sample_rate)).max(0.0)),
+            };
+
+            let tags = TrackTags {
+                title: track.title.clone().or_else(|| file_tags.title.clone()),
+                artist: track.performer.clone().or_else(|| sheet.performer.clone()).or_else(|| file_tags.artist.clone()),
+                album: sheet.title.clone().or_else(|| file_tags.album.clone()),
+                album_artist: sheet.performer.clone().or_else(|| file_tags.album_artist.clone()),
+                track_number: Some(track.number),
+                track_total: Some(file.tracks.len() as u32),
+                duration_secs,
+                ..file_tags.clone()
+            };
+
+            result.songs.push(Song {
+                id: TrackId::of_cue_track(&path_to_string(cue_path), &path_to_string(&file.path), start_frame),
+                path: path_to_string(&file.path),
+                name: format!("{:02}. {}", track.number, title),
+                is_directory: false,
+                codec: Some(codec.clone()),
+                start_frame: Some(start_frame),
+                end_frame,
+                tags,
+            });
+        }
+    }
+}
+
+fn is_cue_sheet(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("cue"))
 }
 
 
@@ -74,3 +500,10 @@ pub fn get_songs() -> Result<Vec<Song>, String> {
 
     Ok(songs)
 }
+
+#[tauri::command]
+pub fn get_unsupported_files() -> Result<Vec<UnsupportedFile>, String> {
+    let unsupported_guard = UNSUPPORTED_FILES.lock().unwrap();
+
+    Ok(unsupported_guard.clone())
+}
@@ -0,0 +1,98 @@
+use std::path::Path;
+
+use anyhow::Result;
+use lofty::file::{AudioFile, TaggedFileExt};
+use lofty::tag::{Accessor, ItemKey, Tag};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrackTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<u32>,
+    pub track_total: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub disc_total: Option<u32>,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+    // tracks indexed before this was read don't have it
+    #[serde(default)]
+    pub compilation: bool,
+
+    pub duration_secs: Option<f64>,
+    pub sample_rate: Option<u32>,
+    pub bit_depth: Option<u8>,
+    pub channels: Option<u8>,
+    // kbit/s
+    pub bitrate: Option<u32>,
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|value| !value.is_empty()).map(str::to_string)
+}
+
+// "2009", "2009-05-12" and "2009/05" are all out there
+fn parse_year(date: &str) -> Option<u32> {
+    let digits: String = date.trim().chars().take_while(|c| c.is_ascii_digit()).collect();
+
+    (digits.len() == 4).then(|| digits.parse().ok()).flatten()
+}
+
+// fills in whatever is still missing from one tag, so an mp3 with id3v2 and a leftover id3v1/ape
+// tag gets the best of both
+fn merge_tag(tags: &mut TrackTags, tag: &Tag) {
+    tags.title = tags.title.take().or_else(|| non_empty(tag.title().as_deref()));
+    tags.artist = tags.artist.take().or_else(|| non_empty(tag.artist().as_deref()));
+    tags.album = tags.album.take().or_else(|| non_empty(tag.album().as_deref()));
+    tags.album_artist = tags.album_artist.take().or_else(|| non_empty(tag.get_string(ItemKey::AlbumArtist)));
+    tags.genre = tags.genre.take().or_else(|| non_empty(tag.genre().as_deref()));
+    tags.compilation = tags.compilation || tag.get_string(ItemKey::FlagCompilation).is_some_and(|flag| flag.trim() == "1");
+
+    tags.track_number = tags.track_number.or_else(|| tag.track());
+    tags.track_total = tags.track_total.or_else(|| tag.track_total());
+    tags.disc_number = tags.disc_number.or_else(|| tag.disk());
+    tags.disc_total = tags.disc_total.or_else(|| tag.disk_total());
+
+    tags.year = tags.year.or_else(|| {
+        tag.date()
+            .map(|date| date.year as u32)
+            .or_else(|| tag.get_string(ItemKey::Year).and_then(parse_year))
+            .or_else(|| tag.get_string(ItemKey::RecordingDate).and_then(parse_year))
+    });
+}
+
+// id3v1/v2, vorbis comments, mp4 atoms, ape and riff info, whichever of them the file has
+pub fn read_tags(path: &Path) -> Result<TrackTags> {
+    let tagged_file = lofty::read_from_path(path)?;
+
+    let mut tags = TrackTags::default();
+
+    if let Some(primary) = tagged_file.primary_tag() {
+        merge_tag(&mut tags, primary);
+    }
+
+    for tag in tagged_file.tags() {
+        merge_tag(&mut tags, tag);
+    }
+
+    let properties = tagged_file.properties();
+    let duration = properties.duration();
+
+    tags.duration_secs = (!duration.is_zero()).then_some(duration.as_secs_f64());
+    tags.sample_rate = properties.sample_rate();
+    tags.bit_depth = properties.bit_depth();
+    tags.channels = properties.channels();
+    tags.bitrate = properties.audio_bitrate().or_else(|| properties.overall_bitrate());
+
+    Ok(tags)
+}
+
+// a file without readable tags is still playable, it's just listed by its file name
+pub fn read_tags_or_default(path: &Path) -> TrackTags {
+    read_tags(path).unwrap_or_else(|e| {
+        println!("error reading tags of {}: {:?}", path.display(), e);
+        TrackTags::default()
+    })
+}
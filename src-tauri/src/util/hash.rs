@@ -0,0 +1,12 @@
+// fnv-1a, used where we need short keys that stay the same across runs and rust versions
+// (std's DefaultHasher makes no such promise)
+pub fn fnv1a_64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
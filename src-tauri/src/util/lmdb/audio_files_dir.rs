@@ -1,19 +1,13 @@
 extern crate lmdb_rs as lmdb;
 
-use lmdb::{EnvBuilder, DbFlags, core::MdbError};
-use std::path::PathBuf;
+use lmdb::{DbFlags, core::MdbError};
 
 use anyhow::Result;
 
-use crate::util::setup_lmdb::lmdb_data_folder;
+use crate::util::setup_lmdb::lmdb_env;
 
 pub fn store_songs_directory(dir: &str) -> Result<()> {
-    let data_folder = lmdb_data_folder();
-
-    let data_folder_pathbuf = PathBuf::from(data_folder);
-
-    let env = EnvBuilder::new()
-        .open(data_folder_pathbuf, 0o777)?;
+    let env = lmdb_env()?;
 
     let db_handle = env.get_default_db(DbFlags::empty())?;
 
@@ -29,12 +23,7 @@ pub fn store_songs_directory(dir: &str) -> Result<()> {
 }
 
 pub fn get_songs_directory() -> Result<Option<String>> {
-    let data_folder = lmdb_data_folder();
-
-    let data_folder_pathbuf = PathBuf::from(data_folder);
-
-    let env = EnvBuilder::new()
-        .open(data_folder_pathbuf, 0o777)?;
+    let env = lmdb_env()?;
 
     let db_handle = env.get_default_db(DbFlags::empty())?;
 
@@ -1 +1,3 @@
-pub mod audio_files_dir;
\ No newline at end of file
+pub mod audio_files_dir;
+pub mod settings;
+pub mod records;
\ No newline at end of file
@@ -0,0 +1,177 @@
+extern crate lmdb_rs as lmdb;
+
+use lmdb::{DbFlags, core::MdbError};
+use serde::{de::DeserializeOwned, Serialize};
+
+use anyhow::Result;
+
+use crate::util::setup_lmdb::lmdb_env;
+
+// everything lives in the default db, "tables" are just key prefixes like "resume:<key>"
+fn record_key(table: &str, key: &str) -> String {
+    format!("{}:{}", table, key)
+}
+
+pub fn store_record<T: Serialize>(table: &str, key: &str, value: &T) -> Result<()> {
+    let env = lmdb_env()?;
+
+    let db_handle = env.get_default_db(DbFlags::empty())?;
+
+    let value = serde_json::to_string(value)?;
+
+    let txn = env.new_transaction()?;
+    {
+        let db = txn.bind(&db_handle);
+        db.set(&record_key(table, key), &value)?;
+    }
+
+    txn.commit()?;
+
+    Ok(())
+}
+
+// all in one transaction, committing once per record is far too slow for a whole library
+pub fn store_records<T: Serialize>(table: &str, records: &[(String, T)]) -> Result<()> {
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let env = lmdb_env()?;
+
+    let db_handle = env.get_default_db(DbFlags::empty())?;
+
+    let txn = env.new_transaction()?;
+    {
+        let db = txn.bind(&db_handle);
+
+        for (key, value) in records {
+            db.set(&record_key(table, key), &serde_json::to_string(value)?)?;
+        }
+    }
+
+    txn.commit()?;
+
+    Ok(())
+}
+
+pub fn get_record<T: DeserializeOwned>(table: &str, key: &str) -> Result<Option<T>> {
+    let env = lmdb_env()?;
+
+    let db_handle = env.get_default_db(DbFlags::empty())?;
+
+    let reader = env.get_reader()?;
+
+    let db = reader.bind(&db_handle);
+
+    match db.get::<String>(&record_key(table, key)) {
+        Ok(value) => Ok(Some(serde_json::from_str(&value)?)),
+        Err(e) => {
+            match e {
+                MdbError::NotFound => Ok(None),
+                _ => Err(anyhow::Error::new(e).context(format!("failed to get {} from lmdb", record_key(table, key))))
+            }
+        }
+    }
+}
+
+pub fn delete_record(table: &str, key: &str) -> Result<()> {
+    let env = lmdb_env()?;
+
+    let db_handle = env.get_default_db(DbFlags::empty())?;
+
+    let txn = env.new_transaction()?;
+    {
+        let db = txn.bind(&db_handle);
+
+        match db.del(&record_key(table, key)) {
+            Ok(()) | Err(MdbError::NotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    txn.commit()?;
+
+    Ok(())
+}
+
+pub fn delete_records(table: &str, keys: &[String]) -> Result<()> {
+    if keys.is_empty() {
+        return Ok(());
+    }
+
+    let env = lmdb_env()?;
+
+    let db_handle = env.get_default_db(DbFlags::empty())?;
+
+    let txn = env.new_transaction()?;
+    {
+        let db = txn.bind(&db_handle);
+
+        for key in keys {
+            match db.del(&record_key(table, key)) {
+                Ok(()) | Err(MdbError::NotFound) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    txn.commit()?;
+
+    Ok(())
+}
+
+// (key, value) pairs of a table, with the table prefix stripped from the keys
+pub fn list_records<T: DeserializeOwned>(table: &str) -> Result<Vec<(String, T)>> {
+    let env = lmdb_env()?;
+
+    let db_handle = env.get_default_db(DbFlags::empty())?;
+
+    let reader = env.get_reader()?;
+
+    let db = reader.bind(&db_handle);
+
+    let prefix = record_key(table, "");
+
+    let mut records = Vec::new();
+
+    for kv in db.keyrange_from(&prefix)? {
+        let key: &str = kv.get_key();
+
+        let Some(key) = key.strip_prefix(&prefix) else {
+            break;
+        };
+
+        let value: &str = kv.get_value();
+
+        match serde_json::from_str(value) {
+            Ok(value) => records.push((key.to_string(), value)),
+            Err(e) => println!("skipping broken record {}{}: {:?}", prefix, key, e),
+        }
+    }
+
+    Ok(records)
+}
+
+pub fn clear_records(table: &str) -> Result<()> {
+    let keys: Vec<String> = list_records::<serde_json::Value>(table)?
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect();
+
+    let env = lmdb_env()?;
+
+    let db_handle = env.get_default_db(DbFlags::empty())?;
+
+    let txn = env.new_transaction()?;
+    {
+        let db = txn.bind(&db_handle);
+
+        for key in keys {
+            db.del(&record_key(table, &key))?;
+        }
+    }
+
+    txn.commit()?;
+
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use anyhow::Result;
+
+use super::records::{get_record, store_record};
+
+// settings are records like any other, one per key in this table
+const SETTINGS_TABLE: &str = "setting";
+
+pub fn store_setting<T: Serialize>(key: &str, value: &T) -> Result<()> {
+    store_record(SETTINGS_TABLE, key, value)
+}
+
+pub fn get_setting<T: DeserializeOwned>(key: &str) -> Result<Option<T>> {
+    get_record(SETTINGS_TABLE, key)
+}
@@ -1,2 +1,5 @@
 pub mod lmdb;
-pub mod setup_lmdb;
\ No newline at end of file
+pub mod setup_lmdb;
+pub mod hash;
+#[cfg(test)]
+pub mod test_http;
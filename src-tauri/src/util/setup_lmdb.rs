@@ -1,6 +1,20 @@
+extern crate lmdb_rs as lmdb;
+
+use lmdb::{EnvBuilder, Environment};
 use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
 use tracing::error;
 
+use anyhow::Result;
+
+// lmdb defaults to a 10mb map which is way too small once we store more than the song dir
+const LMDB_MAP_SIZE: u64 = 1024 * 1024 * 1024;
+
+lazy_static::lazy_static! {
+    static ref LMDB_ENV: Mutex<Option<Environment>> = Mutex::new(None);
+}
+
 pub fn lmdb_data_folder() -> String {
     let mut lmdb_data_folder = String::new();
 
@@ -23,7 +37,26 @@ pub fn lmdb_data_folder() -> String {
         error!("unable to get the document directory");
     }
 
-    
+
 
     lmdb_data_folder
-}
\ No newline at end of file
+}
+
+// lmdb must not be opened twice by the same process, so everyone shares this one environment
+pub fn lmdb_env() -> Result<Environment> {
+    let mut env_guard = LMDB_ENV.lock().unwrap();
+
+    if let Some(env) = env_guard.as_ref() {
+        return Ok(env.clone());
+    }
+
+    let data_folder_pathbuf = PathBuf::from(lmdb_data_folder());
+
+    let env = EnvBuilder::new()
+        .map_size(LMDB_MAP_SIZE)
+        .open(data_folder_pathbuf, 0o777)?;
+
+    *env_guard = Some(env.clone());
+
+    Ok(env)
+}
@@ -0,0 +1,55 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+// a throwaway http server on localhost for tests. `respond` gets the number of the connection (from 0) and
+// the requested path and returns the whole response, headers included. the connection is closed after it
+pub fn serve(respond: impl Fn(usize, &str) -> Vec<u8> + Send + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        for (connection, stream) in listener.incoming().enumerate() {
+            let Ok(mut stream) = stream else {
+                return;
+            };
+
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+
+            if reader.read_line(&mut request_line).is_err() {
+                continue;
+            }
+
+            // the rest of the headers, up to the empty line
+            let mut line = String::new();
+
+            while reader.read_line(&mut line).map(|read| read > 2).unwrap_or(false) {
+                line.clear();
+            }
+
+            let path = request_line.split_whitespace().nth(1).unwrap_or("/").to_string();
+
+            // clients that got what they wanted hang up early, that's fine
+            let _ = stream.write_all(&respond(connection, &path));
+        }
+    });
+
+    format!("http://{}", address)
+}
+
+pub fn response(content_type: &str, extra_headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+    // http/1.0 without a content-length, the body ends when the connection does, like with most radio servers
+    let mut response = format!("HTTP/1.0 200 OK\r\nContent-Type: {}\r\n", content_type);
+
+    for (name, value) in extra_headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+
+    response.push_str("\r\n");
+
+    let mut response = response.into_bytes();
+    response.extend_from_slice(body);
+
+    response
+}
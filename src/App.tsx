@@ -4,6 +4,8 @@ import "./App.css";
 
 
 import FilePickerComponent from "./ui_components/FilePicker";
+import ConvolutionPickerComponent from "./ui_components/ConvolutionPicker";
+import EffectChainComponent from "./ui_components/EffectChain";
 import Player from "./Player";
 import { useNavigate } from "react-router-dom";
 
@@ -38,6 +40,8 @@ function App() {
   return (
     <div className="container">
       <FilePickerComponent />
+      <ConvolutionPickerComponent />
+      <EffectChainComponent />
       <br/>
       <div className="all-devices">
         <h3>all of your audio devices :D</h3>
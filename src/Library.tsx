@@ -1,13 +1,39 @@
 import { useEffect, useState } from "react"
 import { invoke } from "@tauri-apps/api/tauri"
 import Player from "./Player";
+import LibraryRootsComponent from "./ui_components/LibraryRoots";
+import FolderBrowserComponent from "./ui_components/FolderBrowser";
+import { formatDuration } from "./util/format";
+import SearchComponent from "./ui_components/Search";
+import CatalogComponent from "./ui_components/Catalog";
 import './Library.css';
-import { emit } from "@tauri-apps/api/event";
+import { emit, listen } from "@tauri-apps/api/event";
 import { useNavigate } from "react-router-dom";
 
+interface TrackTags {
+    title: string | null;
+    artist: string | null;
+    album: string | null;
+    album_artist: string | null;
+    track_number: number | null;
+    track_total: number | null;
+    disc_number: number | null;
+    disc_total: number | null;
+    year: number | null;
+    genre: string | null;
+    duration_secs: number | null;
+    sample_rate: number | null;
+    bit_depth: number | null;
+    channels: number | null;
+    bitrate: number | null;
+}
+
 interface SongDir {
+    id: string;
     path: string;
     name: string;
+    codec: string | null;
+    tags: TrackTags;
 }
 
 export default function Library() {
@@ -40,11 +66,20 @@ export default function Library() {
 
         getSongDirPath();
         getSongs();
+
+        // the diff is there for finer updates, reloading the list is enough here
+        const unlisten = listen('library-changed', () => {
+            getSongs();
+        });
+
+        return () => {
+            unlisten.then(unlisten => unlisten());
+        }
     }, []);
 
-    const switchTrack = (track_index: number) => {
+    const switchTrack = (track_id: string) => {
         emit("switch_track", {
-            track_index: track_index
+            track_id: track_id
         });
     }
 
@@ -58,16 +93,24 @@ export default function Library() {
                 <div className="library-header-inner">
                     <button className="go-back-btn" onClick={() => nav("/")}>go back</button>
                     <p>{songDirPath}</p>
+                    <LibraryRootsComponent/>
                 </div>
             </div>
             <div className="library-content">
+                <SearchComponent/>
+                <FolderBrowserComponent/>
+                <CatalogComponent/>
                 <ul>
-                    {songsDir.map((song, index) => (
+                    {songsDir.map((song) => (
                         <div className="library-items">
                             <div className="library-items-inner">
-                                <li className="library-items-li" key={index}>
-                                    <p className="song-name">{song.name}</p>
-                                    <button onClick={() => switchTrack(index)}>play! :3</button>
+                                <li className="library-items-li" key={song.id}>
+                                    <p className="song-name">{song.tags.title ?? song.name}</p>
+                                    <p className="song-meta">
+                                        {[song.tags.artist, song.tags.album, song.tags.year].filter(Boolean).join(' - ')}
+                                        {song.tags.duration_secs !== null && ` (${formatDuration(song.tags.duration_secs)})`}
+                                    </p>
+                                    <button onClick={() => switchTrack(song.id)}>play! :3</button>
                                     <p className="song-path">{song.path}</p>
                                 </li> 
                             </div>
@@ -11,8 +11,13 @@ interface EventConstants {
     EVENT_RESTART: string;
     EVENT_REPEAT: string;
     EVENT_SEEK: string;
+    EVENT_SEEK_RELATIVE: string;
     EVENT_SKIP_NEXT: string;
     EVENT_SKIP_PREV: string;
+    EVENT_NEXT_CHAPTER: string;
+    EVENT_PREV_CHAPTER: string;
+    EVENT_SILENCE_MODE: string;
+    EVENT_CROSSFEED: string;
 }
   
 const eventConstants: EventConstants = {
@@ -21,10 +26,58 @@ const eventConstants: EventConstants = {
     EVENT_RESTART: 'restart',
     EVENT_REPEAT: 'repeat',
     EVENT_SEEK: 'seek',
+    EVENT_SEEK_RELATIVE: 'seek_relative',
     EVENT_SKIP_NEXT: 'skip_to_next',
-    EVENT_SKIP_PREV: 'skip_to_prev'
+    EVENT_SKIP_PREV: 'skip_to_prev',
+    EVENT_NEXT_CHAPTER: 'next_chapter',
+    EVENT_PREV_CHAPTER: 'prev_chapter',
+    EVENT_SILENCE_MODE: 'silence_mode',
+    EVENT_CROSSFEED: 'crossfeed'
 };
 
+interface TransportState {
+    pos_frames: number;
+    total_frames: number;
+    sample_rate: number;
+    pos_secs: number;
+    duration_secs: number;
+    pos_display: string;
+    duration_display: string;
+    output_latency_ms: number;
+    buffering: boolean;
+    playback_state: "playing" | "paused";
+    skipped_secs: number;
+}
+
+type SilenceMode = "off" | "skip" | "speed_up";
+
+type CrossfeedPreset = "default" | "cmoy" | "jmeier";
+
+interface CrossfeedSettings {
+    enabled: boolean;
+    preset: CrossfeedPreset;
+}
+
+interface Chapter {
+    index: number;
+    title: string;
+    start_frame: number;
+    end_frame: number | null;
+    start_secs: number;
+}
+
+interface NowPlaying {
+    url: string;
+    station: string | null;
+    title: string | null;
+}
+
+interface TrackChanged {
+    track_id: string;
+    // a url the <img> can load directly, null without cover art
+    cover: string | null;
+}
+
 enum Skip {
   SkipToPrev,
   SkipToNext
@@ -35,49 +88,54 @@ export default function Player() {
     const [isRepeating, setIsRepeating] = useState(false);
     const [currentFrames, setCurrentFrames] = useState(0);
     const [totalFrames, setTotalFrames] = useState(0);
+    const [posDisplay, setPosDisplay] = useState("00:00");
+    const [durationDisplay, setDurationDisplay] = useState("00:00");
     const [buffering, setBuffering] = useState(false);
+    const [chapterTitle, setChapterTitle] = useState<string | null>(null);
+    const [nowPlaying, setNowPlaying] = useState<NowPlaying | null>(null);
+    const [cover, setCover] = useState<string | null>(null);
+    const [skippedSecs, setSkippedSecs] = useState(0);
+    const [silenceMode, setSilenceMode] = useState<SilenceMode>("off");
+    const [crossfeed, setCrossfeed] = useState<CrossfeedSettings>({ enabled: false, preset: "default" });
   
 
-    const switchTrack = (track_index: number) => {
+    const switchTrack = (track_id: string) => {
       emit("switch_track", {
-          track_index: track_index
+          track_id: track_id
       });
     }
-
-   
-
-    const totalsongs = async () => {
-      try {
-        const result: number[] = await invoke("get_current_index");
-        return result[1];
-      } catch(e) {
-        console.error(e);
-      }
-    }
     
 
     useEffect(() => {
         let isSubscribed = true;
 
-        const fetchCurrentFrames = async () => {
+        const fetchTransportState = async () => {
           try {
-            const unlisten = await listen('pos-frames', (event: any) => {
+            const unlisten = await listen<TransportState>('transport-state', (event) => {
               if (isSubscribed) {
-                setCurrentFrames(event.payload);
+                setCurrentFrames(event.payload.pos_frames);
+                setTotalFrames(event.payload.total_frames);
+                setPosDisplay(event.payload.pos_display);
+                setDurationDisplay(event.payload.duration_display);
+                setBuffering(event.payload.buffering);
+                setSkippedSecs(event.payload.skipped_secs);
+                // only live streams have no length
+                if (event.payload.total_frames > 0) {
+                  setNowPlaying(null);
+                }
               }
             });
             return unlisten;
           } catch (e) {
             console.error(e);
           }
-    
         }
-    
-        const fetchTotalFrames = async () => {
+
+        const fetchChapterChanged = async () => {
           try {
-            const unlisten = await listen('total-frames', (event: any) => {
+            const unlisten = await listen<Chapter>('chapter-changed', (event) => {
               if (isSubscribed) {
-                setTotalFrames(event.payload);
+                setChapterTitle(event.payload.title);
               }
             });
             return unlisten;
@@ -85,39 +143,41 @@ export default function Player() {
             console.error(e);
           }
         }
-    
-        const fetchBufferStatus = async () => {
+
+        const fetchNowPlaying = async () => {
           try {
-            const unlisten = await listen('buffer-status', (event) => {
-                
-              if (typeof event.payload === 'boolean') {
-                if (isSubscribed) {
-                  setBuffering(event.payload);
-                }
+            const unlisten = await listen<NowPlaying>('now-playing', (event) => {
+              if (isSubscribed) {
+                setNowPlaying(event.payload);
               }
             });
-            return unlisten 
+            return unlisten;
           } catch (e) {
             console.error(e);
           }
-        } 
-
+        }
 
+        const fetchTrackChanged = async () => {
+          try {
+            const unlisten = await listen<TrackChanged>('track-changed', (event) => {
+              if (isSubscribed) {
+                setCover(event.payload.cover);
+              }
+            });
+            return unlisten;
+          } catch (e) {
+            console.error(e);
+          }
+        }
 
         const awaitDropAndNext = async () => {
             try {
-              const unlisten = await listen("drop-and-next", async (event) => {
-                let event_payload: number = Number(event.payload) + 1;
-                let total_songs: number | undefined = await totalsongs();
-
+              // the id of the track to play next, null when the library is empty
+              const unlisten = await listen<string | null>("drop-and-next", async (event) => {
                 if (isSubscribed) {
                   console.log(event.payload);
-                  if (total_songs !== undefined) {
-                    if (event_payload <= total_songs) {
-                      switchTrack(event_payload);
-                    } else {
-                      switchTrack(0);
-                    }
+                  if (event.payload !== null) {
+                    switchTrack(event.payload);
                   }
                 }
               });
@@ -127,11 +187,20 @@ export default function Player() {
             }
         }
 
+        invoke<{ mode: SilenceMode }>("get_silence_settings")
+          .then(settings => isSubscribed && setSilenceMode(settings.mode))
+          .catch(e => console.error(e));
+
+        invoke<CrossfeedSettings>("get_crossfeed_settings")
+          .then(settings => isSubscribed && setCrossfeed(settings))
+          .catch(e => console.error(e));
+
         const unlistenFunctions: any[] = [];
 
-        fetchCurrentFrames().then(unlisten => unlistenFunctions.push(unlisten));
-        fetchTotalFrames().then(unlisten => unlistenFunctions.push(unlisten));
-        fetchBufferStatus().then(unlisten => unlistenFunctions.push(unlisten));
+        fetchTransportState().then(unlisten => unlistenFunctions.push(unlisten));
+        fetchChapterChanged().then(unlisten => unlistenFunctions.push(unlisten));
+        fetchNowPlaying().then(unlisten => unlistenFunctions.push(unlisten));
+        fetchTrackChanged().then(unlisten => unlistenFunctions.push(unlisten));
         awaitDropAndNext().then(unlisten => unlistenFunctions.push(unlisten));
        
 
@@ -175,6 +244,28 @@ export default function Player() {
         });
     }
 
+    const handleSeekRelative = (secs: number) => {
+        emit(eventConstants.EVENT_SEEK_RELATIVE, {
+          secs: secs
+        });
+    }
+
+    const handleSilenceMode = (mode: SilenceMode) => {
+        setSilenceMode(mode);
+        emit(eventConstants.EVENT_SILENCE_MODE, {
+          mode: mode
+        });
+    }
+
+    const handleCrossfeed = (value: string) => {
+        const settings: CrossfeedSettings = value === "off"
+          ? { ...crossfeed, enabled: false }
+          : { enabled: true, preset: value as CrossfeedPreset };
+
+        setCrossfeed(settings);
+        emit(eventConstants.EVENT_CROSSFEED, settings);
+    }
+
     const handleSkip = async (skip: Skip) => {
       try {
         
@@ -195,6 +286,7 @@ export default function Player() {
 
     return (
         <div className="player">
+            {cover && <img className="cover-art" src={cover} alt="cover art" />}
             <ProgressBar
                 value={currentFrames}
                 min={0}
@@ -207,6 +299,8 @@ export default function Player() {
             <div className="control-btns">
                 <div className="control-btns-inner">
                 <button className="control-btn" onClick={() => handleSkip(Skip.SkipToPrev)}>prev track</button>
+                <button className="control-btn" onClick={() => emit(eventConstants.EVENT_PREV_CHAPTER)}>prev chapter</button>
+                <button className="control-btn" onClick={() => handleSeekRelative(-10)}>-10s</button>
                 <button className="control-btn" onClick={() => send_event(eventConstants.EVENT_PLAY)}>play music!</button>
                 <button className="control-btn" onClick={() => send_event(eventConstants.EVENT_PAUSE)}>pause music!</button>
                 <button className="control-btn" onClick={() => send_event(eventConstants.EVENT_RESTART)}>restart track!</button>
@@ -214,13 +308,29 @@ export default function Player() {
                     setIsRepeating(prevValue => !prevValue);
                     send_event(eventConstants.EVENT_REPEAT)
                 }}>repeat button yay!</button>
+                <button className="control-btn" onClick={() => handleSeekRelative(10)}>+10s</button>
+                <button className="control-btn" onClick={() => emit(eventConstants.EVENT_NEXT_CHAPTER)}>next chapter</button>
                 <button className="control-btn" onClick={() => handleSkip(Skip.SkipToNext)}>next track</button>
+                <select className="control-btn" value={silenceMode} onChange={(e) => handleSilenceMode(e.target.value as SilenceMode)}>
+                    <option value="off">play silence</option>
+                    <option value="skip">skip silence</option>
+                    <option value="speed_up">speed through silence</option>
+                </select>
+                <select className="control-btn" value={crossfeed.enabled ? crossfeed.preset : "off"} onChange={(e) => handleCrossfeed(e.target.value)}>
+                    <option value="off">crossfeed off</option>
+                    <option value="default">crossfeed</option>
+                    <option value="cmoy">crossfeed (Chu Moy)</option>
+                    <option value="jmeier">crossfeed (Jan Meier)</option>
+                </select>
                 </div>
                 
             </div>
             <div className="player-state">
                 <div className="player-state-inner">
-                    <h4 className="frames-count">{currentFrames} / {totalFrames}</h4>
+                    <h4 className="frames-count">{posDisplay} / {durationDisplay}</h4>
+                    {chapterTitle && <h4 className="chapter-title">{chapterTitle}</h4>}
+                    {nowPlaying && <h4 className="now-playing">{nowPlaying.station ?? nowPlaying.url}{nowPlaying.title && ` - ${nowPlaying.title}`}</h4>}
+                    {skippedSecs > 0 && <h4 className="time-saved">saved {skippedSecs.toFixed(1)}s of silence</h4>}
                     {buffering ? <h4 className="is-buffering">buffering</h4> : <h4 className="is-buffered">buffered!</h4>}
                 </div>
             </div>
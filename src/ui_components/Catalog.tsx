@@ -0,0 +1,154 @@
+import { event, invoke } from '@tauri-apps/api';
+import React, { useEffect, useState } from 'react';
+import { formatDuration } from '../util/format';
+
+type CatalogView = 'artists' | 'albums' | 'genres' | 'years';
+type CatalogSort = 'name' | 'artist' | 'year' | 'track_count' | 'duration';
+
+interface CatalogGroup {
+    name: string;
+    album_count: number;
+    track_count: number;
+    duration_secs: number;
+}
+
+interface AlbumSummary {
+    id: string;
+    title: string;
+    artist: string;
+    year: number | null;
+    genres: string[];
+    track_count: number;
+    disc_count: number;
+    duration_secs: number;
+    compilation: boolean;
+    folder: string;
+}
+
+interface AlbumFilter {
+    artist?: string;
+    genre?: string;
+    year?: number;
+}
+
+interface Page<T> {
+    total: number;
+    items: T[];
+}
+
+interface AlbumTrack {
+    id: string;
+    name: string;
+    tags: {
+        title: string | null;
+        artist: string | null;
+        track_number: number | null;
+        disc_number: number | null;
+    };
+}
+
+const PAGE_SIZE = 100;
+
+const CatalogComponent: React.FC = () => {
+
+    const [view, setView] = useState<CatalogView>('albums');
+    const [sort, setSort] = useState<CatalogSort>('name');
+    const [descending, setDescending] = useState<boolean>(false);
+    const [offset, setOffset] = useState<number>(0);
+    const [filter, setFilter] = useState<AlbumFilter>({});
+    const [groups, setGroups] = useState<Page<CatalogGroup> | null>(null);
+    const [albums, setAlbums] = useState<Page<AlbumSummary> | null>(null);
+    const [openAlbum, setOpenAlbum] = useState<string | null>(null);
+    const [albumTracks, setAlbumTracks] = useState<AlbumTrack[]>([]);
+
+    useEffect(() => {
+        const load = () => {
+            const options = { sort, descending, limit: PAGE_SIZE, offset };
+
+            if (view === 'albums') {
+                invoke<Page<AlbumSummary>>('list_albums', { filter, options }).then(setAlbums).catch(console.error);
+            } else {
+                invoke<Page<CatalogGroup>>(`list_${view}`, { options }).then(setGroups).catch(console.error);
+            }
+        }
+
+        load();
+
+        const unlisten = event.listen('library-changed', load);
+
+        return () => {
+            unlisten.then(f => f());
+        }
+    }, [view, sort, descending, offset, filter]);
+
+    useEffect(() => {
+        if (openAlbum === null) {
+            setAlbumTracks([]);
+            return;
+        }
+
+        invoke<AlbumTrack[]>('get_album_tracks', { albumId: openAlbum }).then(setAlbumTracks).catch(console.error);
+    }, [openAlbum]);
+
+    const showView = (next: CatalogView) => {
+        setView(next);
+        setOffset(0);
+        setFilter({});
+    }
+
+    // picking an artist, genre or year lists its albums
+    const openGroup = (group: CatalogGroup) => {
+        const groupFilter: AlbumFilter = view === 'artists'
+            ? { artist: group.name }
+            : view === 'genres' ? { genre: group.name } : { year: Number(group.name) };
+
+        setView('albums');
+        setOffset(0);
+        setFilter(groupFilter);
+    }
+
+    const total = view === 'albums' ? albums?.total ?? 0 : groups?.total ?? 0;
+
+    return (
+        <div>
+            {(['artists', 'albums', 'genres', 'years'] as CatalogView[]).map(name => (
+                <button key={name} disabled={view === name && Object.keys(filter).length === 0} onClick={() => showView(name)}>{name}</button>
+            ))}
+            <select value={sort} onChange={(e) => setSort(e.target.value as CatalogSort)}>
+                <option value="name">Name</option>
+                {view === 'albums' && <option value="artist">Artist</option>}
+                {view === 'albums' && <option value="year">Year</option>}
+                <option value="track_count">Tracks</option>
+                <option value="duration">Duration</option>
+            </select>
+            <label>
+                <input type="checkbox" checked={descending} onChange={(e) => setDescending(e.target.checked)} />
+                Descending
+            </label>
+            {offset > 0 && <button onClick={() => setOffset(Math.max(0, offset - PAGE_SIZE))}>Previous</button>}
+            {offset + PAGE_SIZE < total && <button onClick={() => setOffset(offset + PAGE_SIZE)}>Next</button>}
+            {view === 'albums' && albums?.items.map(album => (
+                <div key={album.id}>
+                    <button onClick={() => setOpenAlbum(openAlbum === album.id ? null : album.id)}>{album.title}</button>
+                    <span> {album.artist}{album.year !== null && ` (${album.year})`}, {album.track_count} tracks, {formatDuration(album.duration_secs)}</span>
+                    <button onClick={() => event.emit('play_album', { album_id: album.id })}>Play</button>
+                    <button onClick={() => event.emit('enqueue_album', { album_id: album.id })}>Enqueue</button>
+                    {openAlbum === album.id && albumTracks.map(track => (
+                        <p key={track.id}>
+                            {album.disc_count > 1 && `${track.tags.disc_number ?? 1}-`}{track.tags.track_number ?? ''} {track.tags.title ?? track.name}
+                            {album.compilation && track.tags.artist && ` - ${track.tags.artist}`}
+                        </p>
+                    ))}
+                </div>
+            ))}
+            {view !== 'albums' && groups?.items.map(group => (
+                <div key={group.name}>
+                    <button onClick={() => openGroup(group)}>{group.name}</button>
+                    <span> {group.album_count} albums, {group.track_count} tracks, {formatDuration(group.duration_secs)}</span>
+                </div>
+            ))}
+        </div>
+    )
+}
+
+export default CatalogComponent;
@@ -0,0 +1,75 @@
+import { dialog, event, invoke } from '@tauri-apps/api';
+import React, { useEffect, useState } from 'react';
+
+interface ConvolutionSettings {
+    enabled: boolean;
+    ir_path: string | null;
+    partition_size: number;
+}
+
+interface ConvolutionInfo {
+    ir_path: string;
+    ir_channels: number;
+    ir_sample_rate: number;
+    output_sample_rate: number;
+    resampled: boolean;
+    ir_frames: number;
+    partition_size: number;
+    partitions: number;
+    latency_frames: number;
+    latency_ms: number;
+}
+
+interface ConvolutionState {
+    info: ConvolutionInfo | null;
+    error: string | null;
+}
+
+const ConvolutionPickerComponent: React.FC = () => {
+
+    const [settings, setSettings] = useState<ConvolutionSettings | null>(null);
+    const [info, setInfo] = useState<ConvolutionInfo | null>(null);
+    const [error, setError] = useState<string | null>(null);
+
+    useEffect(() => {
+        invoke<ConvolutionSettings>('get_convolution_settings').then(setSettings).catch(console.error);
+        invoke<ConvolutionInfo | null>('get_convolution_info').then(setInfo).catch(console.error);
+
+        const unlisten = event.listen<ConvolutionState>('convolution-state', (e) => {
+            setInfo(e.payload.info);
+            setError(e.payload.error);
+        });
+
+        return () => {
+            unlisten.then(f => f());
+        }
+    }, []);
+
+    const applySettings = (next: ConvolutionSettings) => {
+        setSettings(next);
+        event.emit('convolution', next);
+    }
+
+    const handleFileSelection = async () => {
+        const result = await dialog.open({
+            directory: false,
+            multiple: false,
+            filters: [{ name: 'Impulse Response', extensions: ['wav'] }]
+        }).catch(console.error);
+
+        if (typeof result === 'string' && settings) {
+            applySettings({ ...settings, enabled: true, ir_path: result });
+        }
+    }
+
+    return (
+        <div>
+            <button onClick={handleFileSelection}>Choose Impulse Response</button>
+            {settings?.enabled && <button onClick={() => applySettings({ ...settings, enabled: false })}>Disable Convolution</button>}
+            {info && <p>{info.ir_path} ({info.ir_channels} ch, {info.ir_sample_rate} Hz{info.resampled && ` resampled to ${info.output_sample_rate} Hz`}), adds {info.latency_ms.toFixed(1)} ms latency</p>}
+            {error && <p>{error}</p>}
+        </div>
+    )
+}
+
+export default ConvolutionPickerComponent;
@@ -0,0 +1,118 @@
+import { event, invoke } from '@tauri-apps/api';
+import React, { useEffect, useState } from 'react';
+
+interface ParameterInfo {
+    id: string;
+    name: string;
+    min: number;
+    max: number;
+    default: number;
+}
+
+interface EffectKind {
+    kind: string;
+    name: string;
+    parameters: ParameterInfo[];
+}
+
+interface EffectConfig {
+    id: number;
+    kind: string;
+    bypassed: boolean;
+    parameters: Record<string, number>;
+    error?: string;
+}
+
+interface EffectChainConfig {
+    effects: EffectConfig[];
+}
+
+interface PluginInfo {
+    key: string;
+    format: 'clap' | 'lv2' | 'wasm';
+    path: string;
+    name: string;
+    error: string | null;
+}
+
+const EDIT_EFFECT_CHAIN = 'edit_effect_chain';
+
+const EffectChainComponent: React.FC = () => {
+
+    const [kinds, setKinds] = useState<EffectKind[]>([]);
+    const [chain, setChain] = useState<EffectChainConfig>({ effects: [] });
+    const [unusablePlugins, setUnusablePlugins] = useState<PluginInfo[]>([]);
+    const [wasmFolder, setWasmFolder] = useState<string>('');
+
+    useEffect(() => {
+        invoke<EffectKind[]>('list_effect_kinds').then(setKinds).catch(console.error);
+        invoke<EffectChainConfig>('get_effect_chain').then(setChain).catch(console.error);
+        invoke<string>('get_wasm_plugins_folder').then(setWasmFolder).catch(console.error);
+
+        const unlisten = event.listen<EffectChainConfig>('effect-chain', (e) => {
+            setChain(e.payload);
+        });
+
+        return () => {
+            unlisten.then(f => f());
+        }
+    }, []);
+
+    const scanPlugins = async () => {
+        const plugins = await invoke<PluginInfo[]>('scan_plugins').catch(console.error);
+
+        if (plugins) {
+            setUnusablePlugins(plugins.filter(plugin => plugin.error !== null));
+            invoke<EffectKind[]>('list_effect_kinds').then(setKinds).catch(console.error);
+        }
+    }
+
+    const edit = (payload: object) => {
+        event.emit(EDIT_EFFECT_CHAIN, payload);
+    }
+
+    return (
+        <div>
+            <select value="" onChange={(e) => edit({ action: 'insert', kind: e.target.value, position: null })}>
+                <option value="" disabled>Add effect</option>
+                {kinds.map(kind => <option key={kind.kind} value={kind.kind}>{kind.name}</option>)}
+            </select>
+            <button onClick={scanPlugins}>Scan Plugins</button>
+            <p>Scanning finds CLAP plugins, LV2 plugins aren't supported</p>
+            {wasmFolder && <p>Put your own .wasm/.wat effects into {wasmFolder}</p>}
+            {unusablePlugins.map(plugin => <p key={plugin.key}>{plugin.name} ({plugin.format}): {plugin.error}</p>)}
+            {chain.effects.map((effect, position) => {
+                const kind = kinds.find(kind => kind.kind === effect.kind);
+
+                return (
+                    <div key={effect.id}>
+                        <span>{kind?.name ?? effect.kind}</span>
+                        <button disabled={position === 0} onClick={() => edit({ action: 'move', id: effect.id, position: position - 1 })}>Up</button>
+                        <button disabled={position === chain.effects.length - 1} onClick={() => edit({ action: 'move', id: effect.id, position: position + 1 })}>Down</button>
+                        <label>
+                            <input type="checkbox" checked={effect.bypassed} onChange={(e) => edit({ action: 'bypass', id: effect.id, bypassed: e.target.checked })} />
+                            Bypass
+                        </label>
+                        <button onClick={() => edit({ action: 'remove', id: effect.id })}>Remove</button>
+                        {effect.error && <span>{effect.error}</span>}
+                        {kind?.parameters.map(parameter => (
+                            <label key={parameter.id}>
+                                {parameter.name}
+                                <input
+                                    type="range"
+                                    min={parameter.min}
+                                    max={parameter.max}
+                                    step={(parameter.max - parameter.min) / 100}
+                                    value={effect.parameters[parameter.id] ?? parameter.default}
+                                    onChange={(e) => edit({ action: 'set_parameter', id: effect.id, parameter: parameter.id, value: Number(e.target.value) })}
+                                />
+                            </label>
+                        ))}
+                    </div>
+                )
+            })}
+        </div>
+    )
+}
+
+export default EffectChainComponent;
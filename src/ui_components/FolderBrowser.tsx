@@ -0,0 +1,66 @@
+import { event, invoke } from '@tauri-apps/api';
+import React, { useEffect, useState } from 'react';
+import { formatDuration } from '../util/format';
+
+interface BrowseEntry {
+    id: string;
+    path: string;
+    name: string;
+    is_directory: boolean;
+    track_count: number;
+    duration_secs: number;
+}
+
+const FolderBrowserComponent: React.FC = () => {
+
+    // folders we went into, the last one is shown. empty for the library roots
+    const [trail, setTrail] = useState<string[]>([]);
+    const [entries, setEntries] = useState<BrowseEntry[]>([]);
+    const [error, setError] = useState<string>('');
+
+    const current = trail.length > 0 ? trail[trail.length - 1] : '';
+
+    useEffect(() => {
+        const load = () => {
+            invoke<BrowseEntry[]>('browse', { path: current })
+                .then(result => {
+                    setEntries(result);
+                    setError('');
+                })
+                .catch(e => setError(String(e)));
+        }
+
+        load();
+
+        const unlisten = event.listen('library-changed', load);
+
+        return () => {
+            unlisten.then(f => f());
+        }
+    }, [current]);
+
+    return (
+        <div>
+            {trail.length > 0 && <button onClick={() => setTrail(trail.slice(0, -1))}>Up</button>}
+            <span>{current || 'Library'}</span>
+            {error && <p>{error}</p>}
+            {entries.map(entry => (
+                <div key={entry.id}>
+                    {entry.is_directory
+                        ? <>
+                            <button onClick={() => setTrail([...trail, entry.path])}>{entry.name}</button>
+                            <span> {entry.track_count} tracks, {formatDuration(entry.duration_secs)}</span>
+                            <button onClick={() => event.emit('play_folder', { path: entry.path })}>Play</button>
+                            <button onClick={() => event.emit('enqueue_folder', { path: entry.path })}>Enqueue</button>
+                        </>
+                        : <>
+                            <span>{entry.name} ({formatDuration(entry.duration_secs)})</span>
+                            <button onClick={() => event.emit('switch_track', { track_id: entry.id })}>Play</button>
+                        </>}
+                </div>
+            ))}
+        </div>
+    )
+}
+
+export default FolderBrowserComponent;
@@ -0,0 +1,166 @@
+import { dialog, event, invoke } from '@tauri-apps/api';
+import React, { useEffect, useState } from 'react';
+
+interface LibraryRoot {
+    id: string;
+    path: string;
+    enabled: boolean;
+    max_depth: number | null;
+    follow_symlinks: boolean;
+    exclude: string[];
+    available: boolean;
+}
+
+interface ScanProgress {
+    files_seen: number;
+    files_to_process: number;
+    files_processed: number;
+    current_path: string | null;
+    eta_secs: number | null;
+    done: boolean;
+    cancelled: boolean;
+}
+
+interface ScanSettings {
+    threads: number;
+}
+
+interface ScanWarning {
+    path: string;
+    message: string;
+}
+
+const LibraryRootsComponent: React.FC = () => {
+
+    const [roots, setRoots] = useState<LibraryRoot[]>([]);
+    const [error, setError] = useState<string>('');
+    const [warnings, setWarnings] = useState<ScanWarning[]>([]);
+    const [progress, setProgress] = useState<ScanProgress | null>(null);
+    const [scanSettings, setScanSettings] = useState<ScanSettings>({ threads: 0 });
+
+    const refresh = () => {
+        invoke<LibraryRoot[]>('list_library_roots').then(setRoots).catch(console.error);
+        invoke<ScanWarning[]>('get_scan_warnings').then(setWarnings).catch(console.error);
+    }
+
+    useEffect(() => {
+        refresh();
+        invoke<ScanSettings>('get_scan_settings').then(setScanSettings).catch(console.error);
+
+        // roots going unavailable or coming back show up as a library change
+        const unlisten = event.listen('library-changed', refresh);
+        const unlistenProgress = event.listen<ScanProgress>('scan-progress', (e) => {
+            setProgress(e.payload.done && !e.payload.cancelled ? null : e.payload);
+        });
+
+        return () => {
+            unlisten.then(f => f());
+            unlistenProgress.then(f => f());
+        }
+    }, []);
+
+    const saveScanSettings = (settings: ScanSettings) => {
+        setScanSettings(settings);
+        invoke('set_scan_settings', { settings }).catch(console.error);
+    }
+
+    const addRoot = async () => {
+        const result = await dialog.open({
+            directory: true,
+            multiple: false
+        }).catch(console.error);
+
+        if (typeof result === 'string') {
+            await invoke('add_library_root', { path: result }).catch(console.error);
+            refresh();
+        }
+    }
+
+    const updateRoot = async (root: LibraryRoot) => {
+        try {
+            await invoke('update_library_root', { root });
+            setError('');
+        } catch (e) {
+            setError(String(e));
+        }
+
+        refresh();
+    }
+
+    const removeRoot = async (id: string) => {
+        await invoke('remove_library_root', { id }).catch(console.error);
+        refresh();
+    }
+
+    return (
+        <div>
+            <button onClick={addRoot}>Add Folder</button>
+            <button onClick={() => invoke('rescan_library').catch(console.error)}>Rescan</button>
+            <label>
+                Scan threads
+                <input
+                    type="number"
+                    min={0}
+                    placeholder="auto"
+                    value={scanSettings.threads || ''}
+                    onChange={(e) => saveScanSettings({ threads: Number(e.target.value) })}
+                />
+            </label>
+            {progress && (
+                <div>
+                    {progress.cancelled
+                        ? <span>Scan cancelled after {progress.files_processed} of {progress.files_to_process} files</span>
+                        : <span>
+                            Scanning: {progress.files_seen} found, {progress.files_processed}/{progress.files_to_process} read
+                            {progress.eta_secs !== null && `, about ${Math.ceil(progress.eta_secs)}s left`}
+                        </span>}
+                    {!progress.done && <button onClick={() => invoke('cancel_scan').catch(console.error)}>Cancel</button>}
+                    {progress.current_path && <p>{progress.current_path}</p>}
+                </div>
+            )}
+            {error && <p>{error}</p>}
+            {roots.map(root => (
+                <div key={root.id}>
+                    <span>{root.path}</span>
+                    {!root.available && <span> (unavailable)</span>}
+                    <label>
+                        <input type="checkbox" checked={root.enabled} onChange={(e) => updateRoot({ ...root, enabled: e.target.checked })} />
+                        Enabled
+                    </label>
+                    <label>
+                        <input type="checkbox" checked={root.follow_symlinks} onChange={(e) => updateRoot({ ...root, follow_symlinks: e.target.checked })} />
+                        Follow symlinks
+                    </label>
+                    <label>
+                        Depth
+                        <input
+                            type="number"
+                            min={0}
+                            placeholder="any"
+                            defaultValue={root.max_depth ?? ''}
+                            onBlur={(e) => updateRoot({ ...root, max_depth: e.target.value === '' ? null : Number(e.target.value) })}
+                        />
+                    </label>
+                    <label>
+                        Exclude
+                        <input
+                            type="text"
+                            placeholder="**/Samples/**, *.tmp.flac"
+                            defaultValue={root.exclude.join(', ')}
+                            onBlur={(e) => updateRoot({ ...root, exclude: e.target.value.split(',').map(glob => glob.trim()).filter(Boolean) })}
+                        />
+                    </label>
+                    <button onClick={() => removeRoot(root.id)}>Remove</button>
+                </div>
+            ))}
+            {warnings.length > 0 && (
+                <details>
+                    <summary>{warnings.length} path(s) skipped while scanning</summary>
+                    {warnings.map((warning, index) => <p key={index}>{warning.path}: {warning.message}</p>)}
+                </details>
+            )}
+        </div>
+    )
+}
+
+export default LibraryRootsComponent;
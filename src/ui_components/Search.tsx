@@ -0,0 +1,80 @@
+import { event, invoke } from '@tauri-apps/api';
+import React, { useEffect, useState } from 'react';
+
+interface SearchSong {
+    id: string;
+    path: string;
+    name: string;
+    tags: {
+        title: string | null;
+        artist: string | null;
+        album: string | null;
+        year: number | null;
+    };
+}
+
+interface SearchResults {
+    total: number;
+    songs: SearchSong[];
+}
+
+const PAGE_SIZE = 50;
+
+const SearchComponent: React.FC = () => {
+
+    // field filters go right into the query, e.g. artist:"Bjork" year:>2000
+    const [query, setQuery] = useState<string>('');
+    const [offset, setOffset] = useState<number>(0);
+    const [results, setResults] = useState<SearchResults | null>(null);
+
+    useEffect(() => {
+        if (query.trim() === '') {
+            setResults(null);
+            return;
+        }
+
+        let isCurrent = true;
+
+        invoke<SearchResults>('search', { query, filters: null, limit: PAGE_SIZE, offset })
+            .then(result => {
+                if (isCurrent) {
+                    setResults(result);
+                }
+            })
+            .catch(console.error);
+
+        return () => {
+            isCurrent = false;
+        }
+    }, [query, offset]);
+
+    return (
+        <div>
+            <input
+                type="search"
+                placeholder='Search, e.g. jóga artist:"Bjork" year:>1995'
+                value={query}
+                onChange={(e) => {
+                    setQuery(e.target.value);
+                    setOffset(0);
+                }}
+            />
+            {results && (
+                <div>
+                    <span>{results.total} result(s)</span>
+                    {offset > 0 && <button onClick={() => setOffset(Math.max(0, offset - PAGE_SIZE))}>Previous</button>}
+                    {offset + PAGE_SIZE < results.total && <button onClick={() => setOffset(offset + PAGE_SIZE)}>Next</button>}
+                    {results.songs.map(song => (
+                        <div key={song.id}>
+                            <span>{song.tags.title ?? song.name}</span>
+                            <span> {[song.tags.artist, song.tags.album, song.tags.year].filter(Boolean).join(' - ')}</span>
+                            <button onClick={() => event.emit('switch_track', { track_id: song.id })}>Play</button>
+                        </div>
+                    ))}
+                </div>
+            )}
+        </div>
+    )
+}
+
+export default SearchComponent;
@@ -0,0 +1,8 @@
+// m:ss, or h:mm:ss once it's an hour or longer
+export const formatDuration = (secs: number) => {
+    const hours = Math.floor(secs / 3600);
+    const minutes = Math.floor(secs % 3600 / 60).toString().padStart(hours > 0 ? 2 : 1, '0');
+    const seconds = Math.floor(secs % 60).toString().padStart(2, '0');
+
+    return hours > 0 ? `${hours}:${minutes}:${seconds}` : `${minutes}:${seconds}`;
+}